@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{FolderChip, ProtocolError};
+
+/// One change to a player's folder, indexes are into the folder as it is when the change is made
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum FolderOp {
+    Added{index: u32, chip: FolderChip},
+    Removed{index: u32},
+    /// a chip was marked used or unused
+    Used{index: u32, used: bool},
+}
+
+/// the changes that turn old into new
+///
+/// chips that stay where they are only get their used flag changed, the rest are removed and added
+pub fn diff(old: &[FolderChip], new: &[FolderChip]) -> Vec<FolderOp> {
+    let same_chip = |(a, b): &(&FolderChip, &FolderChip)| a.name == b.name && a.custom == b.custom;
+    let prefix = old.iter().zip(new.iter()).take_while(same_chip).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(same_chip).count();
+    let old_end = old.len() - suffix;
+    let new_end = new.len() - suffix;
+
+    // removed from the back so the indexes in front stay put
+    let mut ops = (prefix..old_end).rev().map(|index| FolderOp::Removed{index: index as u32}).collect::<Vec<FolderOp>>();
+    ops.extend((prefix..new_end).map(|index| FolderOp::Added{index: index as u32, chip: new[index].clone()}));
+    let kept = (0..prefix).map(|index| (index, index)).chain((0..suffix).map(|offset| (old_end + offset, new_end + offset)));
+    for (old_index, new_index) in kept {
+        if old[old_index].used != new[new_index].used {
+            ops.push(FolderOp::Used{index: new_index as u32, used: new[new_index].used});
+        }
+    }
+    ops
+}
+
+/// make the changes to a folder, an index that's out of range means the folder has drifted from the sender's
+pub fn apply(folder: &mut Vec<FolderChip>, ops: &[FolderOp]) -> Result<(), ProtocolError> {
+    for op in ops {
+        match op {
+            FolderOp::Added{index, chip} if *index as usize <= folder.len() => folder.insert(*index as usize, chip.clone()),
+            FolderOp::Removed{index} if (*index as usize) < folder.len() => {
+                folder.remove(*index as usize);
+            }
+            FolderOp::Used{index, used} if (*index as usize) < folder.len() => folder[*index as usize].used = *used,
+            _ => return Err(ProtocolError::Malformed(String::from("folder change out of range"))),
+        }
+    }
+    Ok(())
+}
+
+/// Counts the deltas since the last full folder, so one that goes missing can be spotted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sequence {
+    last: u32,
+}
+
+impl Sequence {
+    /// the number to send the next delta with
+    pub fn advance(&mut self) -> u32 {
+        self.last = self.last.wrapping_add(1);
+        self.last
+    }
+
+    /// a full folder was sent or received, deltas count from 1 again
+    pub fn reset(&mut self) {
+        self.last = 0;
+    }
+
+    /// true if seq is the one that should come next, which then counts as seen
+    pub fn accept(&mut self, seq: u32) -> bool {
+        if seq != self.last.wrapping_add(1) {
+            return false;
+        }
+        self.last = seq;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(chips: &[(&str, bool)]) -> Vec<FolderChip> {
+        chips.iter().map(|(name, used)| FolderChip{name: (*name).to_owned(), used: *used, custom: None}).collect()
+    }
+
+    #[test]
+    fn diffs_turn_one_folder_into_the_other() {
+        let cases = [
+            (folder(&[]), folder(&[("Cannon", false)])),
+            (folder(&[("Cannon", false), ("Sword", false)]), folder(&[("Cannon", true), ("Sword", false)])),
+            (folder(&[("Cannon", false), ("Sword", true), ("Recov10", false)]), folder(&[("Cannon", false), ("Recov10", true)])),
+            (folder(&[("Cannon", false), ("Sword", false)]), folder(&[("Sword", false), ("Cannon", false), ("Sword", true)])),
+            (folder(&[("Cannon", true), ("Sword", false)]), folder(&[])),
+        ];
+        for (old, new) in cases.iter() {
+            let mut changed = old.clone();
+            apply(&mut changed, &diff(old, new)).unwrap();
+            assert_eq!(&changed, new);
+        }
+
+        // a chip being used is the common case, and should be a single small change
+        assert_eq!(diff(&cases[1].0, &cases[1].1), vec![FolderOp::Used{index: 0, used: true}]);
+        assert!(diff(&cases[1].1, &cases[1].1).is_empty());
+        assert!(apply(&mut folder(&[]), &[FolderOp::Removed{index: 0}]).is_err());
+    }
+
+    #[test]
+    fn gaps_are_spotted() {
+        let mut sent = Sequence::default();
+        let mut received = Sequence::default();
+        assert!(received.accept(sent.advance()));
+        sent.advance();
+        assert!(!received.accept(sent.advance()));
+        received.reset();
+        assert!(received.accept(1));
+    }
+}
@@ -0,0 +1,106 @@
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{FolderChip, ProtocolError};
+
+/// everything that can't go in a path segment as it is
+const SEGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b':').add(b'/').add(b'?').add(b'#').add(b'[')
+                                    .add(b']').add(b'@').add(b'!').add(b'$').add(b'&').add(b'\'')
+                                    .add(b'(').add(b')').add(b'*').add(b'+').add(b',').add(b';')
+                                    .add(b'=').add(b'%');
+
+/// A folder chip the way the protocol from before versioning sends it
+///
+/// servers that speak it decode bincode with exactly these fields, so nothing can be added here
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LegacyChip {
+    pub name: String,
+    pub used: bool,
+}
+
+/// What a server speaking the protocol from before versioning sends, the layout can never change
+///
+/// clients send nothing but their whole folder, as a bincode `Vec<LegacyChip>`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SocketMsg {
+    FoldersUpdated(HashMap<String, Vec<LegacyChip>>),
+    Error(String),
+    /// the player is in the group and should send their folder
+    Ready,
+}
+
+impl From<LegacyChip> for FolderChip {
+    fn from(chip: LegacyChip) -> Self {
+        FolderChip{name: chip.name, used: chip.used, custom: None}
+    }
+}
+
+/// custom chips go as just their name, there's nowhere to put the rest
+impl From<&FolderChip> for LegacyChip {
+    fn from(chip: &FolderChip) -> Self {
+        LegacyChip{name: chip.name.clone(), used: chip.used}
+    }
+}
+
+/// the path to join a group at, after the server's join address
+pub fn join_path(group: &str, player: &str) -> String {
+    utf8_percent_encode(group, SEGMENT).to_string() + "/" + &utf8_percent_encode(player, SEGMENT).to_string()
+}
+
+/// the group and player from the end of a join path, None if it doesn't end in exactly two segments
+pub fn parse_join_path(path: &str) -> Option<(String, String)> {
+    let mut segments = path.trim_end_matches('/').rsplitn(3, '/');
+    let player = segments.next()?;
+    let group = segments.next()?;
+    let decode = |segment: &str| percent_decode_str(segment).decode_utf8().ok().map(|text| text.into_owned());
+    let (group, player) = (decode(group)?, decode(player)?);
+    if group.is_empty() || player.is_empty() {
+        return None;
+    }
+    Some((group, player))
+}
+
+pub fn encode_folder(folder: &[FolderChip]) -> Vec<u8> {
+    let folder = folder.iter().map(LegacyChip::from).collect::<Vec<LegacyChip>>();
+    // a Vec of plain structs can't fail to serialize
+    bincode::serialize(&folder).unwrap_or_default()
+}
+
+pub fn decode_folder(data: &[u8]) -> Result<Vec<FolderChip>, ProtocolError> {
+    let folder = bincode::deserialize::<Vec<LegacyChip>>(data).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+    Ok(folder.into_iter().map(FolderChip::from).collect())
+}
+
+pub fn encode_message(message: &SocketMsg) -> Vec<u8> {
+    bincode::serialize(message).unwrap_or_default()
+}
+
+pub fn decode_message(data: &[u8]) -> Result<SocketMsg, ProtocolError> {
+    bincode::deserialize(data).map_err(|e| ProtocolError::Malformed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_layout_never_changes() {
+        let custom = FolderChip{name: String::from("A"), used: true, custom: Some(String::from("{}"))};
+        assert_eq!(encode_folder(&[custom]), vec![1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, b'A', 1]);
+        assert_eq!(encode_message(&SocketMsg::Ready), vec![2, 0, 0, 0]);
+        assert_eq!(encode_message(&SocketMsg::Error(String::from("no"))), vec![1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, b'n', b'o']);
+
+        let folder = decode_folder(&[1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, b'A', 0]).unwrap();
+        assert_eq!(folder, vec![FolderChip{name: String::from("A"), used: false, custom: None}]);
+    }
+
+    #[test]
+    fn join_paths_round_trip() {
+        let path = join_path("Team A/B", "Lan?");
+        assert_eq!(path, "Team%20A%2FB/Lan%3F");
+        assert_eq!(parse_join_path(&(String::from("/join/") + &path)), Some((String::from("Team A/B"), String::from("Lan?"))));
+        assert_eq!(parse_join_path("/join/"), None);
+        assert_eq!(parse_join_path("Lan"), None);
+    }
+}
@@ -0,0 +1,274 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+mod delta;
+pub mod legacy;
+
+pub use delta::{apply, diff, FolderOp, Sequence};
+
+/// the newest version of the protocol this crate speaks
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// the oldest version this crate can still read
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// every binary frame starts with these, so anything else is turned away before it's decoded
+const MAGIC: [u8; 2] = *b"CM";
+
+/// the magic and the version
+const HEADER_LEN: usize = 4;
+
+/// Optional features, each side lists the ones it has in the handshake and only those both list get used
+pub mod capabilities {
+    /// players can be in a group just to watch, without sharing a folder
+    pub const SPECTATE: &str = "spectate";
+    /// folders can carry the whole of a custom chip so the rest of the group can see what it does
+    pub const CUSTOM_CHIPS: &str = "custom-chips";
+    /// folders are kept up to date with just what changed rather than sending the whole thing
+    pub const FOLDER_DELTAS: &str = "folder-deltas";
+}
+
+/// the capabilities both sides have, in the order ours lists them
+pub fn negotiate(ours: &[&str], theirs: &[String]) -> Vec<String> {
+    ours.iter().filter(|capability| theirs.iter().any(|other| other == *capability)).map(|capability| (*capability).to_owned()).collect()
+}
+
+/// the version to speak with a side whose newest is theirs, None if there isn't one both understand
+pub fn negotiate_version(theirs: u16) -> Option<u16> {
+    if theirs < MIN_PROTOCOL_VERSION {
+        return None;
+    }
+    Some(theirs.min(PROTOCOL_VERSION))
+}
+
+/// How frames are written out, binary for everyday use and JSON for reading along while debugging
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Binary,
+    Json,
+}
+
+/// An encoded frame, binary ones go in binary websocket messages and JSON ones in text messages
+#[derive(Clone, Debug, PartialEq)]
+pub enum Payload {
+    Binary(Vec<u8>),
+    Text(String),
+}
+
+/// One message and the id it was sent with, each side counts its ids up from 1 on every connection
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Frame<M> {
+    pub id: u32,
+    pub message: M,
+}
+
+/// A chip in a player's folder
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FolderChip {
+    pub name: String,
+    pub used: bool,
+    /// the chip written out the way chips.json has it, only for custom chips
+    pub custom: Option<String>,
+}
+
+/// What a client sends
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    /// always the first message, the newest version the client speaks and the capabilities it has
+    Hello{version: u16, capabilities: Vec<String>},
+    /// sent once the server says welcome
+    Join{group: String, player: String, spectator: bool},
+    /// the player's whole folder, spectators don't send one
+    FolderUpdate(Vec<FolderChip>),
+    /// the player is leaving on purpose, the server can drop them from the group straight away
+    Leave,
+    Ping(u32),
+    Pong(u32),
+    /// what changed in the player's folder, seq counts up from 1 after each full folder
+    FolderDelta{seq: u32, ops: Vec<FolderOp>},
+    /// a delta went missing, the server should send every folder in full
+    Resync,
+}
+
+/// What a server sends
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    /// the answer to hello, the version the rest of the connection uses and the capabilities both sides have
+    Welcome{version: u16, capabilities: Vec<String>},
+    /// the player is in the group, their folder should be sent next
+    Joined,
+    /// every player's folder, by player name
+    FoldersUpdated(HashMap<String, Vec<FolderChip>>),
+    Ping(u32),
+    Pong(u32),
+    Error{code: ErrorCode, message: String},
+    /// what changed in one player's folder, seq counts up from 1 after each FoldersUpdated
+    FolderDelta{seq: u32, player: String, ops: Vec<FolderOp>},
+    /// a delta went missing, the client should send its folder in full
+    ResendFolder,
+}
+
+/// Why a server turned something down
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// there's no version both sides speak
+    UnsupportedVersion,
+    /// someone in the group already goes by that name
+    NameTaken,
+    /// a message that needs a group was sent before joining one
+    NotJoined,
+    /// the message couldn't be read, or wasn't expected
+    BadMessage,
+    /// the server had a problem of its own
+    ServerError,
+}
+
+impl ErrorCode {
+    /// true if trying again won't help, so the client should give up on the group
+    pub fn is_fatal(self) -> bool {
+        matches!(self, ErrorCode::UnsupportedVersion | ErrorCode::NameTaken)
+    }
+}
+
+/// Why a frame couldn't be read
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProtocolError {
+    /// it isn't a frame at all, most likely a server that speaks the old unversioned protocol
+    NotAFrame,
+    /// a frame from a version this crate can't read
+    UnsupportedVersion(u16),
+    /// it said it was a frame of a version that's understood, but the message didn't make sense
+    Malformed(String),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::NotAFrame => write!(f, "not a group protocol frame"),
+            ProtocolError::UnsupportedVersion(version) => write!(f, "unsupported group protocol version {}", version),
+            ProtocolError::Malformed(why) => write!(f, "malformed frame: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// a frame written as JSON, with the version alongside the rest
+#[derive(Serialize)]
+struct JsonFrame<'a, M> {
+    version: u16,
+    #[serde(flatten)]
+    frame: &'a Frame<M>,
+}
+
+/// write a frame out with the newest version
+pub fn encode<M: Serialize>(frame: &Frame<M>, encoding: Encoding) -> Result<Payload, ProtocolError> {
+    match encoding {
+        Encoding::Binary => {
+            let mut data = Vec::with_capacity(HEADER_LEN);
+            data.extend_from_slice(&MAGIC);
+            data.extend_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+            bincode::serialize_into(&mut data, frame).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+            Ok(Payload::Binary(data))
+        }
+        Encoding::Json => {
+            let text = serde_json::to_string(&JsonFrame{version: PROTOCOL_VERSION, frame}).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+            Ok(Payload::Text(text))
+        }
+    }
+}
+
+fn check_version(version: u16) -> Result<(), ProtocolError> {
+    if (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(ProtocolError::UnsupportedVersion(version))
+    }
+}
+
+/// read a frame from a binary message
+pub fn decode_binary<M: DeserializeOwned>(data: &[u8]) -> Result<Frame<M>, ProtocolError> {
+    if data.len() < HEADER_LEN || data[..2] != MAGIC {
+        return Err(ProtocolError::NotAFrame);
+    }
+    check_version(u16::from_le_bytes([data[2], data[3]]))?;
+    bincode::deserialize(&data[HEADER_LEN..]).map_err(|e| ProtocolError::Malformed(e.to_string()))
+}
+
+/// read a frame from a text message
+pub fn decode_text<M: DeserializeOwned>(text: &str) -> Result<Frame<M>, ProtocolError> {
+    let mut value = serde_json::from_str::<serde_json::Value>(text).map_err(|_| ProtocolError::NotAFrame)?;
+    let version = value.as_object_mut()
+        .and_then(|fields| fields.remove("version"))
+        .and_then(|version| version.as_u64())
+        .ok_or(ProtocolError::NotAFrame)?;
+    check_version(u16::try_from(version).unwrap_or(u16::MAX))?;
+    serde_json::from_value(value).map_err(|e| ProtocolError::Malformed(e.to_string()))
+}
+
+/// read a frame from whichever kind of message it came in
+pub fn decode<M: DeserializeOwned>(payload: &Payload) -> Result<Frame<M>, ProtocolError> {
+    match payload {
+        Payload::Binary(data) => decode_binary(data),
+        Payload::Text(text) => decode_text(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder_update() -> Frame<ClientMessage> {
+        Frame {
+            id: 3,
+            message: ClientMessage::FolderUpdate(vec![
+                FolderChip{name: String::from("Cannon"), used: false, custom: None},
+                FolderChip{name: String::from("Flame Fist"), used: true, custom: Some(String::from(r#"{"Name": "Flame Fist"}"#))},
+            ]),
+        }
+    }
+
+    #[test]
+    fn frames_survive_both_encodings() {
+        let frame = folder_update();
+        for encoding in [Encoding::Binary, Encoding::Json] {
+            let payload = encode(&frame, encoding).unwrap();
+            assert_eq!(decode::<ClientMessage>(&payload).unwrap(), frame);
+        }
+
+        let welcome = Frame{id: 1, message: ServerMessage::Welcome{version: 1, capabilities: vec![String::from(capabilities::SPECTATE)]}};
+        let text = match encode(&welcome, Encoding::Json).unwrap() {
+            Payload::Text(text) => text,
+            Payload::Binary(_) => panic!("JSON should be sent as text"),
+        };
+        assert_eq!(text, r#"{"version":1,"id":1,"message":{"Welcome":{"version":1,"capabilities":["spectate"]}}}"#);
+    }
+
+    #[test]
+    fn unknown_frames_are_turned_away() {
+        // what the old protocol sent for Ready
+        assert_eq!(decode_binary::<ServerMessage>(&[2, 0, 0, 0]), Err(ProtocolError::NotAFrame));
+        assert_eq!(decode_text::<ServerMessage>("Ready"), Err(ProtocolError::NotAFrame));
+
+        let mut data = match encode(&folder_update(), Encoding::Binary).unwrap() {
+            Payload::Binary(data) => data,
+            Payload::Text(_) => panic!("binary should be sent as binary"),
+        };
+        data[2] = 2;
+        assert_eq!(decode_binary::<ClientMessage>(&data), Err(ProtocolError::UnsupportedVersion(2)));
+        assert_eq!(decode_text::<ClientMessage>(r#"{"version":9,"id":1,"message":"Leave"}"#), Err(ProtocolError::UnsupportedVersion(9)));
+        assert!(matches!(decode_text::<ClientMessage>(r#"{"version":1,"id":1,"message":"Dance"}"#), Err(ProtocolError::Malformed(_))));
+    }
+
+    #[test]
+    fn only_shared_capabilities_are_used() {
+        let theirs = vec![String::from(capabilities::CUSTOM_CHIPS), String::from("something-new")];
+        assert_eq!(negotiate(&[capabilities::SPECTATE, capabilities::CUSTOM_CHIPS], &theirs), vec![String::from("custom-chips")]);
+        assert_eq!(negotiate_version(7), Some(PROTOCOL_VERSION));
+        assert_eq!(negotiate_version(0), None);
+        assert!(ErrorCode::NameTaken.is_fatal());
+        assert!(!ErrorCode::BadMessage.is_fatal());
+    }
+}
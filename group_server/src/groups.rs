@@ -0,0 +1,132 @@
+use group_protocol::{apply, capabilities, diff, ErrorCode, FolderChip, FolderOp, ProtocolError, Sequence, ServerMessage};
+use std::collections::HashMap;
+
+use crate::outbox::Outbox;
+
+/// Someone in a group
+struct Member {
+    outbox: Outbox,
+    spectator: bool,
+    folder: Vec<FolderChip>,
+    /// counts the deltas sent to them since they were last sent every folder
+    seq: Sequence,
+}
+
+/// Every group with someone in it, each one's members by name
+#[derive(Default)]
+pub(crate) struct Groups {
+    groups: HashMap<String, HashMap<String, Member>>,
+}
+
+pub(crate) fn error(code: ErrorCode, message: &str) -> ServerMessage {
+    ServerMessage::Error{code, message: message.to_owned()}
+}
+
+impl Groups {
+    /// add a player to a group, they're told they're in before everyone gets the new list of folders
+    pub(crate) fn join(&mut self, group: &str, player: &str, spectator: bool, outbox: Outbox) -> Result<(), ServerMessage> {
+        let members = self.groups.entry(group.to_owned()).or_default();
+        if members.contains_key(player) {
+            return Err(error(ErrorCode::NameTaken, &(String::from("Someone in the group already goes by ") + player)));
+        }
+        outbox.send(ServerMessage::Joined);
+        members.insert(player.to_owned(), Member{outbox, spectator, folder: Vec::new(), seq: Sequence::default()});
+        send_everything(members);
+        Ok(())
+    }
+
+    pub(crate) fn leave(&mut self, group: &str, player: &str) {
+        let members = match self.groups.get_mut(group) {
+            Some(members) => members,
+            None => return,
+        };
+        if members.remove(player).is_none() {
+            return;
+        }
+        if members.is_empty() {
+            self.groups.remove(group);
+        } else {
+            send_everything(members);
+        }
+    }
+
+    /// a player sent their whole folder, the rest of the group only hears what changed
+    pub(crate) fn replace_folder(&mut self, group: &str, player: &str, folder: Vec<FolderChip>) -> Result<(), ServerMessage> {
+        let members = self.groups.get_mut(group).ok_or_else(not_joined)?;
+        let member = members.get_mut(player).ok_or_else(not_joined)?;
+        if member.spectator {
+            return Err(error(ErrorCode::BadMessage, "Spectators don't have a folder to share"));
+        }
+        let ops = diff(&member.folder, &folder);
+        member.folder = folder;
+        if !ops.is_empty() {
+            send_ops(members, player, &ops);
+        }
+        Ok(())
+    }
+
+    /// a player sent what changed in their folder, nothing changes if it doesn't fit their folder
+    pub(crate) fn apply_delta(&mut self, group: &str, player: &str, ops: &[FolderOp]) -> Result<(), ProtocolError> {
+        let members = match self.groups.get_mut(group) {
+            Some(members) => members,
+            None => return Ok(()),
+        };
+        let member = match members.get_mut(player) {
+            Some(member) if !member.spectator => member,
+            _ => return Ok(()),
+        };
+        let mut folder = member.folder.clone();
+        apply(&mut folder, ops)?;
+        member.folder = folder;
+        send_ops(members, player, ops);
+        Ok(())
+    }
+
+    /// send a player every folder again, after they've missed a delta
+    pub(crate) fn resync(&mut self, group: &str, player: &str) {
+        let members = match self.groups.get_mut(group) {
+            Some(members) => members,
+            None => return,
+        };
+        let folders = all_folders(members);
+        if let Some(member) = members.get_mut(player) {
+            send_folders(member, &folders);
+        }
+    }
+}
+
+fn not_joined() -> ServerMessage {
+    error(ErrorCode::NotJoined, "Join a group first")
+}
+
+fn all_folders(members: &HashMap<String, Member>) -> HashMap<String, Vec<FolderChip>> {
+    members.iter().map(|(name, member)| (name.clone(), member.folder.clone())).collect()
+}
+
+/// deltas count from 1 again after every folder is sent
+fn send_folders(member: &mut Member, folders: &HashMap<String, Vec<FolderChip>>) {
+    member.seq.reset();
+    let folders = folders.iter().map(|(name, folder)| (name.clone(), member.outbox.chips_for(folder))).collect();
+    member.outbox.send(ServerMessage::FoldersUpdated(folders));
+}
+
+/// someone joined or left, so everyone gets every folder
+fn send_everything(members: &mut HashMap<String, Member>) {
+    let folders = all_folders(members);
+    for member in members.values_mut() {
+        send_folders(member, &folders);
+    }
+}
+
+/// one player's folder changed, members that can't take deltas get every folder instead
+fn send_ops(members: &mut HashMap<String, Member>, player: &str, ops: &[FolderOp]) {
+    let folders = all_folders(members);
+    for member in members.values_mut() {
+        if member.outbox.has(capabilities::FOLDER_DELTAS) {
+            let seq = member.seq.advance();
+            member.outbox.send(ServerMessage::FolderDelta{seq, player: player.to_owned(), ops: member.outbox.ops_for(ops)});
+        } else {
+            send_folders(member, &folders);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use futures_util::{SinkExt, StreamExt};
+use group_protocol::{capabilities, decode, legacy, negotiate, negotiate_version, ClientMessage, Encoding, ErrorCode, FolderChip, FolderOp, Payload, ProtocolError, Sequence, ServerMessage};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+
+mod groups;
+mod outbox;
+
+use groups::{error, Groups};
+use outbox::Outbox;
+
+/// everything this server can do
+const CAPABILITIES: [&str; 3] = [capabilities::SPECTATE, capabilities::CUSTOM_CHIPS, capabilities::FOLDER_DELTAS];
+
+type SharedGroups = Arc<Mutex<Groups>>;
+
+/// a panic in one connection shouldn't take the groups down with it
+fn lock(groups: &SharedGroups) -> MutexGuard<'_, Groups> {
+    groups.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// accept websocket connections on listener until it fails, each connection gets a task of its own
+pub async fn serve(listener: TcpListener) -> std::io::Result<()> {
+    let groups = SharedGroups::default();
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(handle(stream, groups.clone()));
+    }
+}
+
+/// the group and player from a path like `/join/{group}/{player}`, which is how clients from before versioning join
+fn legacy_join(path: &str) -> Option<(String, String)> {
+    let rest = path.strip_prefix("/join/")?;
+    if rest.trim_end_matches('/').matches('/').count() != 1 {
+        return None;
+    }
+    legacy::parse_join_path(rest)
+}
+
+async fn handle(stream: TcpStream, groups: SharedGroups) {
+    let mut path = String::new();
+    // the error type is tungstenite's to pick
+    #[allow(clippy::result_large_err)]
+    let read_path = |request: &Request, response: Response| {
+        path = request.uri().path().to_owned();
+        Ok(response)
+    };
+    let socket = match tokio_tungstenite::accept_hdr_async(stream, read_path).await {
+        Ok(socket) => socket,
+        Err(why) => {
+            eprintln!("Couldn't open a websocket: {}", why);
+            return;
+        }
+    };
+    let (mut sink, mut stream) = socket.split();
+
+    // everything sent to the client goes through the queue, so a group can send to anyone in it
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = receiver.recv().await {
+            if sink.send(message).await.is_err() {
+                return;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let mut connection = Connection::new(Outbox::new(sender), groups);
+    // a client joining by its address is turned away before anything is read if the name's taken
+    let mut open = match legacy_join(&path) {
+        Some((group, player)) => connection.join_legacy(group, player).is_ok(),
+        None => true,
+    };
+    while open {
+        let payload = match stream.next().await {
+            Some(Ok(Message::Binary(data))) => Payload::Binary(data),
+            Some(Ok(Message::Text(text))) => Payload::Text(text),
+            Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+            Some(Ok(_)) => continue,
+        };
+        open = connection.received(&payload).is_ok();
+    }
+    connection.left();
+
+    // dropping the connection drops its outbox, which lets the writer finish what's queued and close
+    drop(connection);
+    let _ = writer.await;
+}
+
+/// the connection should be closed
+struct Close;
+
+/// One client, from its hello to when it goes
+struct Connection {
+    outbox: Outbox,
+    groups: SharedGroups,
+    said_hello: bool,
+    /// the group and player name, once it's joined
+    joined: Option<(String, String)>,
+    /// the client's deltas since its last whole folder
+    seq: Sequence,
+    /// a delta went missing and the folder's been asked for, so the deltas until it comes are dropped
+    awaiting_folder: bool,
+    /// it speaks the protocol from before versioning, so all it ever sends is its folder
+    legacy: bool,
+}
+
+impl Connection {
+    fn new(outbox: Outbox, groups: SharedGroups) -> Connection {
+        Connection{outbox, groups, said_hello: false, joined: None, seq: Sequence::default(), awaiting_folder: false, legacy: false}
+    }
+
+    fn received(&mut self, payload: &Payload) -> Result<(), Close> {
+        if self.legacy {
+            self.received_legacy(payload);
+            return Ok(());
+        }
+        // answered in the encoding the client's using, even when what it sent can't be read
+        if !self.said_hello {
+            self.outbox.set_encoding(match payload {
+                Payload::Binary(_) => Encoding::Binary,
+                Payload::Text(_) => Encoding::Json,
+            });
+        }
+        let frame = match decode::<ClientMessage>(payload) {
+            Ok(frame) => frame,
+            Err(ProtocolError::UnsupportedVersion(version)) => {
+                self.unsupported_version(version);
+                return Err(Close);
+            }
+            Err(why) => {
+                self.outbox.send(error(ErrorCode::BadMessage, &why.to_string()));
+                return Ok(());
+            }
+        };
+
+        if !self.said_hello {
+            return self.hello(frame.message);
+        }
+        match frame.message {
+            ClientMessage::Hello{..} => self.outbox.send(error(ErrorCode::BadMessage, "Already said hello")),
+            ClientMessage::Join{group, player, spectator} => return self.join(group, player, spectator),
+            ClientMessage::FolderUpdate(folder) => self.folder_update(folder),
+            ClientMessage::FolderDelta{seq, ops} => self.folder_delta(seq, &ops),
+            ClientMessage::Resync => {
+                if let Some((group, player)) = &self.joined {
+                    lock(&self.groups).resync(group, player);
+                }
+            }
+            ClientMessage::Leave => return Err(Close),
+            ClientMessage::Ping(n) => self.outbox.send(ServerMessage::Pong(n)),
+            ClientMessage::Pong(_) => (),
+        }
+        Ok(())
+    }
+
+    /// the client joined by its address, it's in the group straight away and there's no handshake
+    fn join_legacy(&mut self, group: String, player: String) -> Result<(), Close> {
+        self.legacy = true;
+        self.said_hello = true;
+        self.outbox.set_legacy();
+        self.join(group, player, false)
+    }
+
+    /// a client from before versioning sent its whole folder, it has no way to hear about one that can't be read
+    fn received_legacy(&mut self, payload: &Payload) {
+        let folder = match payload {
+            Payload::Binary(data) => legacy::decode_folder(data),
+            Payload::Text(_) => Err(ProtocolError::NotAFrame),
+        };
+        match folder {
+            Ok(folder) => self.folder_update(folder),
+            Err(why) => eprintln!("Couldn't read a folder: {}", why),
+        }
+    }
+
+    fn unsupported_version(&self, version: u16) {
+        let message = format!("This server speaks group protocol version {} and can't understand version {}", group_protocol::PROTOCOL_VERSION, version);
+        self.outbox.send(error(ErrorCode::UnsupportedVersion, &message));
+    }
+
+    fn hello(&mut self, message: ClientMessage) -> Result<(), Close> {
+        let (version, theirs) = match message {
+            ClientMessage::Hello{version, capabilities} => (version, capabilities),
+            _ => {
+                self.outbox.send(error(ErrorCode::BadMessage, "Say hello first"));
+                return Ok(());
+            }
+        };
+        let version = match negotiate_version(version) {
+            Some(version) => version,
+            None => {
+                self.unsupported_version(version);
+                return Err(Close);
+            }
+        };
+        let capabilities = negotiate(&CAPABILITIES, &theirs);
+        self.outbox.set_capabilities(capabilities.clone());
+        self.outbox.send(ServerMessage::Welcome{version, capabilities});
+        self.said_hello = true;
+        Ok(())
+    }
+
+    fn join(&mut self, group: String, player: String, spectator: bool) -> Result<(), Close> {
+        if self.joined.is_some() {
+            self.outbox.send(error(ErrorCode::BadMessage, "Already in a group"));
+            return Ok(());
+        }
+        if spectator && !self.outbox.has(capabilities::SPECTATE) {
+            self.outbox.send(error(ErrorCode::BadMessage, "Spectating wasn't agreed on"));
+            return Ok(());
+        }
+        if let Err(why) = lock(&self.groups).join(&group, &player, spectator, self.outbox.clone()) {
+            self.outbox.send(why);
+            return Err(Close);
+        }
+        self.joined = Some((group, player));
+        Ok(())
+    }
+
+    fn folder_update(&mut self, folder: Vec<FolderChip>) {
+        let (group, player) = match &self.joined {
+            Some(joined) => joined,
+            None => return self.outbox.send(error(ErrorCode::NotJoined, "Join a group first")),
+        };
+        self.seq.reset();
+        self.awaiting_folder = false;
+        if let Err(why) = lock(&self.groups).replace_folder(group, player, folder) {
+            self.outbox.send(why);
+        }
+    }
+
+    fn folder_delta(&mut self, seq: u32, ops: &[FolderOp]) {
+        let (group, player) = match &self.joined {
+            Some(joined) => joined,
+            None => return self.outbox.send(error(ErrorCode::NotJoined, "Join a group first")),
+        };
+        if self.awaiting_folder {
+            return;
+        }
+        let applied = self.seq.accept(seq) && lock(&self.groups).apply_delta(group, player, ops).is_ok();
+        if !applied {
+            self.awaiting_folder = true;
+            self.outbox.send(ServerMessage::ResendFolder);
+        }
+    }
+
+    /// the client's gone, whether it said so or not
+    fn left(&mut self) {
+        if let Some((group, player)) = self.joined.take() {
+            lock(&self.groups).leave(&group, &player);
+        }
+    }
+}
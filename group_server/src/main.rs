@@ -0,0 +1,12 @@
+use tokio::net::TcpListener;
+
+/// where to listen when no address is given
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let address = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDRESS.to_owned());
+    let listener = TcpListener::bind(&address).await?;
+    println!("Group folder server listening on ws://{}", listener.local_addr()?);
+    group_server::serve(listener).await
+}
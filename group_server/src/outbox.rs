@@ -0,0 +1,107 @@
+use group_protocol::legacy::{self, LegacyChip, SocketMsg};
+use group_protocol::{capabilities, encode, Encoding, FolderChip, FolderOp, Frame, Payload, ServerMessage};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::Message;
+
+/// The sending half of a connection, shared by the connection and the group it's in
+#[derive(Clone)]
+pub(crate) struct Outbox {
+    /// the encoding the client spoke to the server in
+    encoding: Encoding,
+    /// what the server said both sides can do
+    capabilities: Vec<String>,
+    /// the client speaks the protocol from before versioning
+    legacy: bool,
+    queue: Arc<Mutex<Queue>>,
+}
+
+/// the frame ids are given out as frames are queued so they always count up in the order they're sent
+struct Queue {
+    frame_id: u32,
+    sender: UnboundedSender<Message>,
+}
+
+impl Outbox {
+    pub(crate) fn new(sender: UnboundedSender<Message>) -> Outbox {
+        Outbox {
+            encoding: Encoding::Binary,
+            capabilities: Vec::new(),
+            legacy: false,
+            queue: Arc::new(Mutex::new(Queue{frame_id: 0, sender})),
+        }
+    }
+
+    /// answer in the same encoding the client is using
+    pub(crate) fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// send what the client can take of each message the way the protocol from before versioning does
+    pub(crate) fn set_legacy(&mut self) {
+        self.legacy = true;
+    }
+
+    pub(crate) fn set_capabilities(&mut self, capabilities: Vec<String>) {
+        self.capabilities = capabilities;
+    }
+
+    pub(crate) fn has(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|other| other == capability)
+    }
+
+    pub(crate) fn send(&self, message: ServerMessage) {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.legacy {
+            if let Some(message) = legacy_message(message) {
+                let _ = queue.sender.send(Message::Binary(legacy::encode_message(&message)));
+            }
+            return;
+        }
+        queue.frame_id = queue.frame_id.wrapping_add(1);
+        let frame = Frame{id: queue.frame_id, message};
+        let message = match encode(&frame, self.encoding) {
+            Ok(Payload::Binary(data)) => Message::Binary(data),
+            Ok(Payload::Text(text)) => Message::Text(text),
+            Err(why) => {
+                eprintln!("Couldn't encode a frame: {}", why);
+                return;
+            }
+        };
+        // the connection is already closing if nothing's reading the queue
+        let _ = queue.sender.send(message);
+    }
+
+    /// a folder the way this client can take it, custom chips are left as just names if it can't
+    pub(crate) fn chips_for(&self, chips: &[FolderChip]) -> Vec<FolderChip> {
+        chips.iter().map(|chip| self.chip_for(chip)).collect()
+    }
+
+    pub(crate) fn ops_for(&self, ops: &[FolderOp]) -> Vec<FolderOp> {
+        ops.iter().map(|op| match op {
+            FolderOp::Added{index, chip} => FolderOp::Added{index: *index, chip: self.chip_for(chip)},
+            other => other.clone(),
+        }).collect()
+    }
+
+    fn chip_for(&self, chip: &FolderChip) -> FolderChip {
+        if self.has(capabilities::CUSTOM_CHIPS) {
+            return chip.clone();
+        }
+        FolderChip{name: chip.name.clone(), used: chip.used, custom: None}
+    }
+}
+
+/// what a client speaking the protocol from before versioning hears of a message, most of them it has no way to take
+///
+/// it has no capabilities, so it's never sent deltas or custom chips in the first place
+fn legacy_message(message: ServerMessage) -> Option<SocketMsg> {
+    match message {
+        ServerMessage::Joined => Some(SocketMsg::Ready),
+        ServerMessage::FoldersUpdated(folders) => Some(SocketMsg::FoldersUpdated(folders.into_iter().map(|(player, folder)| {
+            (player, folder.iter().map(LegacyChip::from).collect())
+        }).collect())),
+        ServerMessage::Error{message, ..} => Some(SocketMsg::Error(message)),
+        _ => None,
+    }
+}
@@ -0,0 +1,297 @@
+use futures_util::{SinkExt, StreamExt};
+use group_protocol::legacy::{self, LegacyChip, SocketMsg};
+use group_protocol::{capabilities, decode, encode, ClientMessage, Encoding, ErrorCode, FolderChip, FolderOp, Frame, Payload, ServerMessage, PROTOCOL_VERSION};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const ALL: [&str; 3] = [capabilities::SPECTATE, capabilities::CUSTOM_CHIPS, capabilities::FOLDER_DELTAS];
+
+/// start a server on a free port and give back its address
+async fn start() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(group_server::serve(listener));
+    address
+}
+
+/// A simulated chipmanager
+struct Client {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    encoding: Encoding,
+    frame_id: u32,
+}
+
+impl Client {
+    async fn connect(address: &str, encoding: Encoding) -> Client {
+        let (socket, _) = tokio_tungstenite::connect_async(address).await.unwrap();
+        Client{socket, encoding, frame_id: 0}
+    }
+
+    async fn send(&mut self, message: ClientMessage) {
+        self.frame_id += 1;
+        let message = match encode(&Frame{id: self.frame_id, message}, self.encoding).unwrap() {
+            Payload::Binary(data) => Message::Binary(data),
+            Payload::Text(text) => Message::Text(text),
+        };
+        self.socket.send(message).await.unwrap();
+    }
+
+    async fn receive(&mut self) -> ServerMessage {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), self.socket.next()).await
+                .expect("the server didn't answer")
+                .expect("the server closed the connection")
+                .unwrap();
+            let payload = match message {
+                Message::Binary(data) => {
+                    assert_eq!(self.encoding, Encoding::Binary);
+                    Payload::Binary(data)
+                }
+                Message::Text(text) => {
+                    assert_eq!(self.encoding, Encoding::Json);
+                    Payload::Text(text)
+                }
+                _ => continue,
+            };
+            return decode::<ServerMessage>(&payload).unwrap().message;
+        }
+    }
+
+    /// true once the server has closed the connection
+    async fn closed(&mut self) -> bool {
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), self.socket.next()).await {
+                Ok(Some(Ok(Message::Close(_)))) | Ok(Some(Err(_))) | Ok(None) => return true,
+                Ok(Some(Ok(_))) => continue,
+                Err(_) => return false,
+            }
+        }
+    }
+
+    async fn hello(&mut self, capabilities: &[&str]) -> Vec<String> {
+        let capabilities = capabilities.iter().map(|capability| (*capability).to_owned()).collect();
+        self.send(ClientMessage::Hello{version: PROTOCOL_VERSION, capabilities}).await;
+        match self.receive().await {
+            ServerMessage::Welcome{version, capabilities} => {
+                assert_eq!(version, PROTOCOL_VERSION);
+                capabilities
+            }
+            other => panic!("expected a welcome, got {:?}", other),
+        }
+    }
+
+    /// say hello and join, giving back everyone's folders as they were when this client joined
+    async fn join(&mut self, capabilities: &[&str], group: &str, player: &str, spectator: bool) -> HashMap<String, Vec<FolderChip>> {
+        self.hello(capabilities).await;
+        self.send(ClientMessage::Join{group: group.to_owned(), player: player.to_owned(), spectator}).await;
+        assert_eq!(self.receive().await, ServerMessage::Joined);
+        self.folders().await
+    }
+
+    async fn folders(&mut self) -> HashMap<String, Vec<FolderChip>> {
+        match self.receive().await {
+            ServerMessage::FoldersUpdated(folders) => folders,
+            other => panic!("expected every folder, got {:?}", other),
+        }
+    }
+
+    async fn error(&mut self) -> ErrorCode {
+        match self.receive().await {
+            ServerMessage::Error{code, ..} => code,
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
+
+/// what a server sends a client from before versioning
+async fn receive_legacy(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> SocketMsg {
+    match tokio::time::timeout(Duration::from_secs(5), socket.next()).await.unwrap().unwrap().unwrap() {
+        Message::Binary(data) => legacy::decode_message(&data).unwrap(),
+        other => panic!("expected a binary message, got {:?}", other),
+    }
+}
+
+fn chip(name: &str, used: bool) -> FolderChip {
+    FolderChip{name: name.to_owned(), used, custom: None}
+}
+
+fn names(folders: &HashMap<String, Vec<FolderChip>>) -> Vec<&str> {
+    let mut names = folders.keys().map(String::as_str).collect::<Vec<&str>>();
+    names.sort_unstable();
+    names
+}
+
+#[tokio::test]
+async fn players_see_each_others_folders() {
+    let address = start().await;
+    let mut lan = Client::connect(&address, Encoding::Binary).await;
+    let mut mayl = Client::connect(&address, Encoding::Binary).await;
+
+    assert_eq!(names(&lan.join(&ALL, "A", "Lan", false).await), ["Lan"]);
+    assert_eq!(names(&mayl.join(&[capabilities::SPECTATE], "A", "Mayl", false).await), ["Lan", "Mayl"]);
+    assert_eq!(names(&lan.folders().await), ["Lan", "Mayl"]);
+
+    let custom = FolderChip{name: String::from("Flame Fist"), used: false, custom: Some(String::from(r#"{"Name": "Flame Fist"}"#))};
+    lan.send(ClientMessage::FolderUpdate(vec![chip("Cannon", false), custom.clone()])).await;
+
+    // lan can take deltas and custom chips, mayl gets the whole lot without what the custom chip does
+    assert_eq!(lan.receive().await, ServerMessage::FolderDelta{
+        seq: 1,
+        player: String::from("Lan"),
+        ops: vec![FolderOp::Added{index: 0, chip: chip("Cannon", false)}, FolderOp::Added{index: 1, chip: custom}],
+    });
+    assert_eq!(mayl.folders().await["Lan"], vec![chip("Cannon", false), chip("Flame Fist", false)]);
+
+    lan.send(ClientMessage::FolderDelta{seq: 1, ops: vec![FolderOp::Used{index: 0, used: true}]}).await;
+    assert_eq!(lan.receive().await, ServerMessage::FolderDelta{seq: 2, player: String::from("Lan"), ops: vec![FolderOp::Used{index: 0, used: true}]});
+    assert_eq!(mayl.folders().await["Lan"][0], chip("Cannon", true));
+}
+
+#[tokio::test]
+async fn names_are_only_taken_within_a_group() {
+    let address = start().await;
+    let mut first = Client::connect(&address, Encoding::Binary).await;
+    let mut second = Client::connect(&address, Encoding::Binary).await;
+    let mut elsewhere = Client::connect(&address, Encoding::Binary).await;
+
+    first.join(&ALL, "A", "Lan", false).await;
+    second.hello(&ALL).await;
+    second.send(ClientMessage::Join{group: String::from("A"), player: String::from("Lan"), spectator: false}).await;
+    assert_eq!(second.error().await, ErrorCode::NameTaken);
+    assert!(second.closed().await);
+
+    assert_eq!(names(&elsewhere.join(&ALL, "B", "Lan", false).await), ["Lan"]);
+}
+
+#[tokio::test]
+async fn spectators_watch_without_a_folder() {
+    let address = start().await;
+    let mut lan = Client::connect(&address, Encoding::Binary).await;
+    let mut chaud = Client::connect(&address, Encoding::Binary).await;
+
+    lan.join(&ALL, "A", "Lan", false).await;
+    let folders = chaud.join(&ALL, "A", "Chaud", true).await;
+    assert_eq!(names(&folders), ["Chaud", "Lan"]);
+    assert!(folders["Chaud"].is_empty());
+    lan.folders().await;
+
+    chaud.send(ClientMessage::FolderUpdate(vec![chip("Cannon", false)])).await;
+    assert_eq!(chaud.error().await, ErrorCode::BadMessage);
+
+    // without the capability there's no spectating
+    let mut other = Client::connect(&address, Encoding::Binary).await;
+    other.hello(&[]).await;
+    other.send(ClientMessage::Join{group: String::from("A"), player: String::from("Mayl"), spectator: true}).await;
+    assert_eq!(other.error().await, ErrorCode::BadMessage);
+}
+
+#[tokio::test]
+async fn missing_deltas_are_made_up_for() {
+    let address = start().await;
+    let mut lan = Client::connect(&address, Encoding::Binary).await;
+    lan.join(&ALL, "A", "Lan", false).await;
+
+    lan.send(ClientMessage::FolderDelta{seq: 2, ops: vec![FolderOp::Added{index: 0, chip: chip("Cannon", false)}]}).await;
+    assert_eq!(lan.receive().await, ServerMessage::ResendFolder);
+
+    // the folder comes whole, and the server's deltas count from the last whole folder it sent
+    lan.send(ClientMessage::FolderUpdate(vec![chip("Cannon", false)])).await;
+    assert!(matches!(lan.receive().await, ServerMessage::FolderDelta{seq: 1, ..}));
+    lan.send(ClientMessage::Resync).await;
+    assert_eq!(lan.folders().await["Lan"], vec![chip("Cannon", false)]);
+    lan.send(ClientMessage::FolderDelta{seq: 1, ops: vec![FolderOp::Removed{index: 0}]}).await;
+    assert!(matches!(lan.receive().await, ServerMessage::FolderDelta{seq: 1, ..}));
+
+    // a delta that doesn't fit the folder is as good as missing
+    lan.send(ClientMessage::FolderDelta{seq: 2, ops: vec![FolderOp::Removed{index: 0}]}).await;
+    assert_eq!(lan.receive().await, ServerMessage::ResendFolder);
+}
+
+#[tokio::test]
+async fn the_handshake_is_checked() {
+    let address = start().await;
+
+    let mut json = Client::connect(&address, Encoding::Json).await;
+    assert_eq!(json.hello(&[capabilities::FOLDER_DELTAS, "telepathy"]).await, [capabilities::FOLDER_DELTAS]);
+    json.send(ClientMessage::Ping(7)).await;
+    assert_eq!(json.receive().await, ServerMessage::Pong(7));
+    json.send(ClientMessage::FolderUpdate(Vec::new())).await;
+    assert_eq!(json.error().await, ErrorCode::NotJoined);
+
+    let mut rude = Client::connect(&address, Encoding::Binary).await;
+    rude.send(ClientMessage::Join{group: String::from("A"), player: String::from("Lan"), spectator: false}).await;
+    assert_eq!(rude.error().await, ErrorCode::BadMessage);
+    rude.socket.send(Message::Binary(vec![1, 2, 3])).await.unwrap();
+    assert_eq!(rude.error().await, ErrorCode::BadMessage);
+
+    let mut future = Client::connect(&address, Encoding::Json).await;
+    future.socket.send(Message::Text(String::from(r#"{"version":9,"id":1,"message":"Leave"}"#))).await.unwrap();
+    assert_eq!(future.error().await, ErrorCode::UnsupportedVersion);
+    assert!(future.closed().await);
+}
+
+#[tokio::test]
+async fn the_group_hears_when_someone_goes() {
+    let address = start().await;
+    let mut lan = Client::connect(&address, Encoding::Binary).await;
+    let mut mayl = Client::connect(&address, Encoding::Binary).await;
+    let mut dex = Client::connect(&address, Encoding::Binary).await;
+
+    lan.join(&ALL, "A", "Lan", false).await;
+    mayl.join(&ALL, "A", "Mayl", false).await;
+    lan.folders().await;
+    dex.join(&ALL, "A", "Dex", false).await;
+    lan.folders().await;
+    mayl.folders().await;
+
+    mayl.send(ClientMessage::Leave).await;
+    assert!(mayl.closed().await);
+    assert_eq!(names(&lan.folders().await), ["Dex", "Lan"]);
+    dex.folders().await;
+
+    drop(dex);
+    assert_eq!(names(&lan.folders().await), ["Lan"]);
+
+    // the name's free again once they've gone
+    let mut back = Client::connect(&address, Encoding::Binary).await;
+    assert_eq!(names(&back.join(&ALL, "A", "Dex", false).await), ["Dex", "Lan"]);
+}
+
+#[tokio::test]
+async fn old_clients_join_by_address() {
+    let address = start().await;
+    let mut mayl = Client::connect(&address, Encoding::Binary).await;
+    mayl.join(&ALL, "Team A", "Mayl", false).await;
+
+    let (mut old, _) = tokio_tungstenite::connect_async(address.clone() + "/join/" + &legacy::join_path("Team A", "Lan")).await.unwrap();
+    assert_eq!(receive_legacy(&mut old).await, SocketMsg::Ready);
+    assert!(matches!(receive_legacy(&mut old).await, SocketMsg::FoldersUpdated(folders) if folders.len() == 2));
+    assert_eq!(names(&mayl.folders().await), ["Lan", "Mayl"]);
+
+    old.send(Message::Binary(legacy::encode_folder(&[chip("Cannon", false)]))).await.unwrap();
+    assert!(matches!(mayl.receive().await, ServerMessage::FolderDelta{ops, ..} if ops == vec![FolderOp::Added{index: 0, chip: chip("Cannon", false)}]));
+    // it can't take deltas, so it hears about its own folder whole too
+    assert!(matches!(receive_legacy(&mut old).await, SocketMsg::FoldersUpdated(folders) if folders["Lan"].len() == 1));
+
+    // custom chips go to it as just their names
+    let custom = FolderChip{name: String::from("Flame Fist"), used: true, custom: Some(String::from("{}"))};
+    mayl.send(ClientMessage::FolderUpdate(vec![custom])).await;
+    match receive_legacy(&mut old).await {
+        SocketMsg::FoldersUpdated(folders) => assert_eq!(folders["Mayl"], vec![LegacyChip{name: String::from("Flame Fist"), used: true}]),
+        other => panic!("expected every folder, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn old_clients_are_turned_away_from_a_taken_name() {
+    let address = start().await;
+    let mut lan = Client::connect(&address, Encoding::Binary).await;
+    lan.join(&ALL, "Team A", "Lan", false).await;
+
+    let (mut old, _) = tokio_tungstenite::connect_async(address.clone() + "/join/" + &legacy::join_path("Team A", "Lan")).await.unwrap();
+    assert!(matches!(receive_legacy(&mut old).await, SocketMsg::Error(_)));
+    assert!(matches!(old.next().await, Some(Ok(Message::Close(_))) | None));
+}
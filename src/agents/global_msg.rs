@@ -8,6 +8,11 @@ pub(crate) enum Request {
     JoinGroup,
     EraseData,
     ImportData,
+    ImportText,
+    ManageProfiles,
+    ManageCustomChips,
+    ManageSources,
+    ManageSettings,
 }
 
 pub(crate) struct GlobalMsgBus {
@@ -3,40 +3,51 @@ use std::collections::{HashSet, HashMap};
 use std::time::Duration;
 use yew::{Callback, worker::*};
 //use yew::prelude::*;
-use yew::format::Binary;
+use yew::format::{Binary, Text};
 use yew::services::{
     websocket::{WebSocketService, WebSocketTask, WebSocketStatus},
     timeout::{TimeoutService, TimeoutTask},
     ConsoleService,
 };
-use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
-use unchecked_unwrap::UncheckedUnwrap;
-const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b':').add(b'/').add(b'?').add(b'#').add(b'[')
-                                        .add(b']').add(b'@').add(b'!').add(b'$').add(b'&').add(b'\'')
-                                        .add(b'(').add(b')').add(b'*').add(b'+').add(b',').add(b';')
-                                        .add(b'=').add(b'%');
+use group_protocol::{
+    apply, capabilities, decode, diff, encode, legacy::{self, SocketMsg}, negotiate, negotiate_version,
+    ClientMessage, Encoding, ErrorCode, FolderChip, FolderOp, Frame, Payload, ProtocolError, Sequence, ServerMessage, PROTOCOL_VERSION,
+};
 //use wasm_bindgen::{JsCast, JsValue, closure::Closure};
 //use web_sys::WebSocket;
 
 use crate::util::alert;
 use crate::chip_library::{GroupFolderChip, ChipLibrary};
+use crate::agents::reconnect::{ConnectionState, Reconnector};
 
+/// the capabilities this app asks the server for
+const CAPABILITIES: &[&str] = &[capabilities::SPECTATE, capabilities::CUSTOM_CHIPS, capabilities::FOLDER_DELTAS];
+
+/// how long to wait for every folder after asking for them again, in milliseconds, before asking once more
+const RESYNC_TIMEOUT: u64 = 5000;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) enum GroupFldrAgentSocketMsg {
-    JoinedGroup,
-    LeftGroup,
-    GroupUpdated,
-    ServerError(String),
+    /// each socket is numbered so messages from ones that were replaced can be ignored
+    Opened(u32),
+    Received(u32, Payload),
+    /// the message couldn't be taken off the socket
+    Unreadable(u32),
+    ConnectionLost(u32),
+    /// every folder was asked for on this socket and hasn't come yet
+    ResyncTimedOut(u32),
+    Reconnect,
     CheckFolderUpdated,
-    DoNothing,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) enum GroupFldrAgentOutMsg {
     JoinedGroup,
     LeftGroup,
     GroupUpdated,
+    /// only this player's folder changed
+    PlayerUpdated(String),
+    /// the connection dropped, the group is kept while it tries to get back in
+    Reconnecting,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -46,11 +57,27 @@ pub(crate) enum GroupFldrAgentReq {
     LeaveGroup,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub(crate) enum SocketMsg {
-    FoldersUpdated(HashMap<String, Vec<GroupFolderChip>>),
-    Error(String),
-    Ready,
+/// A message from the server as it came in, it's read once it's known which protocol the server speaks
+struct Incoming(Option<Payload>);
+
+impl From<Binary> for Incoming {
+    fn from(data: Binary) -> Self {
+        Incoming(data.ok().map(Payload::Binary))
+    }
+}
+
+impl From<Text> for Incoming {
+    fn from(text: Text) -> Self {
+        Incoming(text.ok().map(Payload::Text))
+    }
+}
+
+/// What's needed to join the same group again after the connection drops
+#[derive(Clone)]
+struct GroupSession {
+    group_name: String,
+    player_name: String,
+    spectator: bool,
 }
 
 pub(crate) struct GroupFldrMsgBus {
@@ -59,7 +86,29 @@ pub(crate) struct GroupFldrMsgBus {
     web_socket: Option<WebSocketTask>,
     socket_update_timeout: Option<TimeoutTask>,
     timeout_callback: Callback<()>,
-    spectator: bool
+    session: Option<GroupSession>,
+    reconnector: Reconnector,
+    reconnect_timeout: Option<TimeoutTask>,
+    /// the server only speaks the protocol from before versioning, kept for as long as the group is
+    legacy: bool,
+    /// the server answered the hello on this socket, so it speaks the versioned protocol
+    welcomed: bool,
+    /// the number of the socket in use, anything from an older one is stale
+    socket_id: u32,
+    /// the id of the last frame sent on this socket
+    frame_id: u32,
+    encoding: Encoding,
+    /// what the server said both sides can do
+    capabilities: Vec<String>,
+    /// the folder as the server last heard it, changes are sent against this
+    sent_folder: Vec<FolderChip>,
+    sent_seq: Sequence,
+    /// every player's folder as the server has sent it, deltas are applied to these
+    group: HashMap<String, Vec<FolderChip>>,
+    received_seq: Sequence,
+    /// a delta went missing and every folder has been asked for, deltas are ignored until they come
+    resyncing: bool,
+    resync_timeout: Option<TimeoutTask>,
 }
 
 //static GroupMsgCallbackLink: Lazy<RwLock<Option<Callback<GroupFldrAgentMsg>>>> = Lazy::new(|| RwLock::new(None));
@@ -80,77 +129,104 @@ impl Agent for GroupFldrMsgBus {
             web_socket: None,
             socket_update_timeout: None,
             timeout_callback: callback,
-            spectator: false,
+            session: None,
+            reconnector: Reconnector::default(),
+            reconnect_timeout: None,
+            legacy: false,
+            welcomed: false,
+            socket_id: 0,
+            frame_id: 0,
+            encoding: Encoding::Binary,
+            capabilities: Vec::new(),
+            sent_folder: Vec::new(),
+            sent_seq: Sequence::default(),
+            group: HashMap::new(),
+            received_seq: Sequence::default(),
+            resyncing: false,
+            resync_timeout: None,
         }
     }
 
     fn update(&mut self, msg: Self::Message) {
         let response = match msg {
-            GroupFldrAgentSocketMsg::JoinedGroup => {
-                let folder = if self.spectator {
-                    let fake_fldr: Vec<GroupFolderChip> = Vec::new();
-                    unsafe{bincode::serialize(&fake_fldr).unchecked_unwrap()}
+            GroupFldrAgentSocketMsg::Opened(id) if id == self.socket_id => {
+                // an old server speaks first
+                if !self.legacy {
+                    self.send(ClientMessage::Hello{version: PROTOCOL_VERSION, capabilities: CAPABILITIES.iter().map(|capability| (*capability).to_owned()).collect()});
+                }
+                return;
+            }
+            GroupFldrAgentSocketMsg::Received(id, payload) if id == self.socket_id => {
+                let response = if self.legacy {
+                    self.received_legacy(&payload)
                 } else {
-                    ChipLibrary::get_instance().serialize_folder()
+                    match decode::<ServerMessage>(&payload) {
+                        Ok(frame) => self.received(frame.message),
+                        Err(why) => self.bad_frame(why),
+                    }
                 };
-
-                match &mut self.web_socket {
-                    Some(socket) => {
-                        socket.send_binary(Ok(folder));
-                    },
-                    None => {}
+                match response {
+                    Some(response) => response,
+                    None => return,
                 }
-                GroupFldrAgentOutMsg::JoinedGroup
             }
-            GroupFldrAgentSocketMsg::LeftGroup => {
-                self.web_socket.take();
-                self.socket_update_timeout.take();
-                self.clear_group_folders();
-                self.spectator = false;
-                GroupFldrAgentOutMsg::LeftGroup
+            GroupFldrAgentSocketMsg::Unreadable(id) if id == self.socket_id => {
+                ConsoleService::log("Couldn't read a message from the group server");
+                return;
             }
-            GroupFldrAgentSocketMsg::GroupUpdated => {
-                GroupFldrAgentOutMsg::GroupUpdated
+            GroupFldrAgentSocketMsg::ConnectionLost(id) if id == self.socket_id => {
+                match self.connection_lost() {
+                    Some(response) => response,
+                    None => return,
+                }
             }
-            GroupFldrAgentSocketMsg::ServerError(why) => {
-                unsafe{alert(&why)};
-                self.web_socket.take();
-                self.spectator = false;
-                self.socket_update_timeout.take();
-                self.clear_group_folders();
-                GroupFldrAgentOutMsg::LeftGroup
+            GroupFldrAgentSocketMsg::ResyncTimedOut(id) => {
+                self.resync_timeout.take();
+                if id == self.socket_id && self.resyncing {
+                    self.resync();
+                }
+                return;
+            }
+            GroupFldrAgentSocketMsg::Reconnect => {
+                self.reconnect_timeout.take();
+                self.reconnect();
+                return;
             }
             GroupFldrAgentSocketMsg::CheckFolderUpdated => {
                 self.socket_update_timeout.take();
                 self.check_folder_upated();
                 return;
             },
-            GroupFldrAgentSocketMsg::DoNothing => return,
+            // from a socket that's already been replaced
+            GroupFldrAgentSocketMsg::Opened(_) | GroupFldrAgentSocketMsg::Received(..)
+            | GroupFldrAgentSocketMsg::Unreadable(_) | GroupFldrAgentSocketMsg::ConnectionLost(_) => return,
             
         };
         for sub in self.subs.iter() {
-            self.link.respond(*sub, response);
+            self.link.respond(*sub, response.clone());
         }
     }
 
     fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
         match msg {
             GroupFldrAgentReq::JoinGroup { player_name, group_name, spectator } => {
-                if let Err(why) = self.join_group(group_name, player_name, spectator) {
+                self.leave_group();
+                self.session = Some(GroupSession{group_name, player_name, spectator});
+                let library = ChipLibrary::get_instance();
+                self.legacy = library.is_legacy_server(&library.config().group_server);
+                self.reconnector.joining();
+                if let Err(why) = self.connect() {
+                    self.leave_group();
                     unsafe{alert(&why)};
                 }
             }
             GroupFldrAgentReq::LeaveGroup => {
                 self.leave_group();
-                self.clear_group_folders();
                 for sub in self.subs.iter() {
                     self.link.respond(*sub, GroupFldrAgentOutMsg::LeftGroup);
                 }
             }
             GroupFldrAgentReq::UpdateFolder => {
-                if self.web_socket.is_none() {
-                    return;
-                }
                 self.check_folder_upated();
             }
         }
@@ -167,63 +243,313 @@ impl Agent for GroupFldrMsgBus {
 
 impl GroupFldrMsgBus {
 
-    fn join_group(&mut self, group_name: String, player_name: String, spectator: bool) -> Result<(), String> {
-        let encoded_group = utf8_percent_encode(&group_name, FRAGMENT).to_string();
-        let encoded_player = utf8_percent_encode(&player_name, FRAGMENT).to_string();
-        let url = String::from("wss://spartan364.hopto.org/manager/api/join/") + &encoded_group + "/" + &encoded_player;
-        //let mut socket = WebSocketService::new();
-        let message_callback = self.link.callback(|msg: Binary| {
-            let data = match msg {
-                Ok(data) => data,
-                Err(_) => return GroupFldrAgentSocketMsg::DoNothing,
-            };
-            //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(&data));
-            let res  = bincode::deserialize::<SocketMsg>(&data).ok();
-            match res {
-                Some(SocketMsg::FoldersUpdated(folders)) => {
-                    //let folders = serde_json::from_str::<HashMap<String, Vec<GroupFolderChip>>>(&folder_str).ok()?;
-                    //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str("Folders updated"));
-                    folders_updated(folders);
-                    /*
-                    let mut group = ChipLibrary::get_instance().group_folders.borrow_mut();
-                    *group = folders;
-                    drop(group);
-                    */
-                    GroupFldrAgentSocketMsg::GroupUpdated
-                }
-                Some(SocketMsg::Error(why)) => {
-                    GroupFldrAgentSocketMsg::ServerError(why)
-                }
-                Some(SocketMsg::Ready) => {
-                    GroupFldrAgentSocketMsg::JoinedGroup
-                }
-                None => GroupFldrAgentSocketMsg::DoNothing
+    /// open a socket to the group server, the handshake starts once it's open
+    ///
+    /// an old server is joined by going to the group and player's own address
+    fn connect(&mut self) -> Result<(), String> {
+        let session = self.session.as_ref().ok_or("Not in a group")?;
+        let config = ChipLibrary::get_instance().config();
+        let mut address = config.group_server.clone();
+        if self.legacy {
+            if !address.ends_with('/') {
+                address.push('/');
             }
-            //GroupFldrAgentMsg::GroupUpdated
+            address.push_str(&legacy::join_path(&session.group_name, &session.player_name));
+        }
 
+        // anything still coming from the last socket is ignored from here on
+        self.socket_id = self.socket_id.wrapping_add(1);
+        self.frame_id = 0;
+        self.encoding = config.protocol.encoding;
+        self.welcomed = false;
+        self.capabilities.clear();
+        self.sent_folder.clear();
+        self.sent_seq.reset();
+        self.group.clear();
+        self.received_seq.reset();
+        self.resyncing = false;
+        self.resync_timeout.take();
+        let id = self.socket_id;
+        let message_callback = self.link.callback(move |Incoming(payload)| {
+            match payload {
+                Some(payload) => GroupFldrAgentSocketMsg::Received(id, payload),
+                None => GroupFldrAgentSocketMsg::Unreadable(id),
+            }
         });
-        let socket_notification_callback = self.link.callback(|msg: WebSocketStatus| {
+        let socket_notification_callback = self.link.callback(move |msg: WebSocketStatus| {
             match msg {
-                WebSocketStatus::Opened => {
-                    //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str("Socket opened"));
-                    GroupFldrAgentSocketMsg::DoNothing
-                },
-                WebSocketStatus::Closed => GroupFldrAgentSocketMsg::LeftGroup,
-                WebSocketStatus::Error => GroupFldrAgentSocketMsg::ServerError("Socket Closed by Server".to_string()),
+                WebSocketStatus::Opened => GroupFldrAgentSocketMsg::Opened(id),
+                // a phone going to sleep looks the same as the server going away, so both get retried
+                WebSocketStatus::Closed | WebSocketStatus::Error => GroupFldrAgentSocketMsg::ConnectionLost(id),
             }
         });
-        let socket_task = WebSocketService::connect_binary(&url, message_callback, socket_notification_callback).map_err(|e| e.to_string())?;
+        let socket_task = WebSocketService::connect(&address, message_callback, socket_notification_callback).map_err(|e| e.to_string())?;
         self.web_socket = Some(socket_task);
-        self.spectator = spectator;
         Ok(())
     }
 
+    /// the next step of the handshake, or what the group's doing
+    fn received(&mut self, message: ServerMessage) -> Option<GroupFldrAgentOutMsg> {
+        match message {
+            ServerMessage::Welcome{version, capabilities} => {
+                self.welcomed = true;
+                if negotiate_version(version) != Some(version) {
+                    return Some(self.give_up("The group server speaks a different version of the group protocol than this app"));
+                }
+                self.capabilities = negotiate(CAPABILITIES, &capabilities);
+                let session = self.session.clone()?;
+                if session.spectator && !self.has_capability(capabilities::SPECTATE) {
+                    return Some(self.give_up("This group server doesn't allow spectators"));
+                }
+                self.send(ClientMessage::Join{group: session.group_name, player: session.player_name, spectator: session.spectator});
+                None
+            }
+            ServerMessage::Joined => {
+                // sent on every join so a reconnect picks up whatever changed while it was away
+                self.reconnector.connected();
+                self.send_folder(true);
+                Some(GroupFldrAgentOutMsg::JoinedGroup)
+            }
+            ServerMessage::FoldersUpdated(folders) => Some(self.folders_received(folders)),
+            ServerMessage::FolderDelta{seq, player, ops} => {
+                if self.resyncing {
+                    return None;
+                }
+                let applied = self.received_seq.accept(seq) && apply(self.group.entry(player.clone()).or_default(), &ops).is_ok();
+                if !applied {
+                    self.resync();
+                    return None;
+                }
+                player_folder_changed(&player, &ops, &self.group[&player]);
+                Some(GroupFldrAgentOutMsg::PlayerUpdated(player))
+            }
+            ServerMessage::ResendFolder => {
+                self.send_folder(true);
+                None
+            }
+            ServerMessage::Ping(number) => {
+                self.send(ClientMessage::Pong(number));
+                None
+            }
+            ServerMessage::Pong(_) => None,
+            ServerMessage::Error{code, message} => {
+                // the server might not have noticed the dropped connection yet, so it still has the name
+                if code == ErrorCode::NameTaken && self.reconnector.is_reconnecting() {
+                    ConsoleService::log(&message);
+                    return self.connection_lost();
+                }
+                if code.is_fatal() {
+                    return Some(self.give_up(&message));
+                }
+                ConsoleService::log(&message);
+                None
+            }
+        }
+    }
+
+    /// a message that isn't a frame most likely came from a server that only speaks the protocol from before versioning
+    fn bad_frame(&mut self, why: ProtocolError) -> Option<GroupFldrAgentOutMsg> {
+        match why {
+            ProtocolError::NotAFrame if !self.welcomed => self.fall_back_to_legacy(),
+            ProtocolError::NotAFrame | ProtocolError::UnsupportedVersion(_) => {
+                Some(self.give_up("The group server speaks a different version of the group protocol than this app"))
+            }
+            ProtocolError::Malformed(_) => {
+                ConsoleService::log(&why.to_string());
+                None
+            }
+        }
+    }
+
+    /// join again the way the protocol from before versioning does
+    fn fall_back_to_legacy(&mut self) -> Option<GroupFldrAgentOutMsg> {
+        self.legacy = true;
+        match self.connect() {
+            Ok(()) => None,
+            Err(why) => Some(self.give_up(&why)),
+        }
+    }
+
+    /// what a server that speaks the protocol from before versioning sent
+    fn received_legacy(&mut self, payload: &Payload) -> Option<GroupFldrAgentOutMsg> {
+        let message = match payload {
+            Payload::Binary(data) => legacy::decode_message(data),
+            Payload::Text(_) => Err(ProtocolError::NotAFrame),
+        };
+        match message {
+            Ok(SocketMsg::Ready) => {
+                self.reconnector.connected();
+                let library = ChipLibrary::get_instance();
+                if let Err(why) = library.mark_legacy_server(&library.config().group_server) {
+                    ConsoleService::log(why);
+                }
+                self.send_folder(true);
+                Some(GroupFldrAgentOutMsg::JoinedGroup)
+            }
+            Ok(SocketMsg::FoldersUpdated(folders)) => {
+                let folders = folders.into_iter().map(|(player, chips)| {
+                    (player, chips.into_iter().map(FolderChip::from).collect::<Vec<FolderChip>>())
+                }).collect::<HashMap<String, Vec<FolderChip>>>();
+                Some(self.folders_received(folders))
+            }
+            // there's no telling what an old server's error was, but while reconnecting it's most likely the name still being held
+            Ok(SocketMsg::Error(why)) if self.reconnector.is_reconnecting() => {
+                ConsoleService::log(&why);
+                self.connection_lost()
+            }
+            Ok(SocketMsg::Error(why)) => Some(self.give_up(&why)),
+            Err(why) => {
+                ConsoleService::log(&why.to_string());
+                None
+            }
+        }
+    }
+
+    /// every folder in the group, sent whole
+    fn folders_received(&mut self, folders: HashMap<String, Vec<FolderChip>>) -> GroupFldrAgentOutMsg {
+        self.received_seq.reset();
+        self.resyncing = false;
+        self.resync_timeout.take();
+        let converted = folders.iter().map(|(player, chips)| {
+            (player.clone(), chips.iter().cloned().map(GroupFolderChip::from).collect::<Vec<GroupFolderChip>>())
+        }).collect();
+        self.group = folders;
+        folders_updated(converted);
+        GroupFldrAgentOutMsg::GroupUpdated
+    }
+
+    /// spectators don't have a folder to share
+    fn is_spectator(&self) -> bool {
+        self.session.as_ref().map_or(false, |session| session.spectator)
+    }
+
+    fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|other| other == capability)
+    }
+
+    /// write out a message the way this socket was set up to
+    fn send(&mut self, message: ClientMessage) {
+        self.frame_id = self.frame_id.wrapping_add(1);
+        let frame = Frame{id: self.frame_id, message};
+        let socket = match &mut self.web_socket {
+            Some(socket) => socket,
+            None => return,
+        };
+        match encode(&frame, self.encoding) {
+            Ok(Payload::Binary(data)) => socket.send_binary(Ok(data)),
+            Ok(Payload::Text(text)) => socket.send(Ok(text)),
+            Err(why) => ConsoleService::log(&why.to_string()),
+        }
+    }
+
+    /// send the folder whole, or just what's changed since it was last sent if the server takes deltas
+    ///
+    /// an old server always gets it whole, and an empty one from spectators
+    fn send_folder(&mut self, full: bool) {
+        if self.legacy {
+            let folder = if self.is_spectator() {Vec::new()} else {ChipLibrary::get_instance().shared_folder(false)};
+            if let Some(socket) = &mut self.web_socket {
+                socket.send_binary(Ok(legacy::encode_folder(&folder)));
+            }
+            return;
+        }
+        if self.is_spectator() {
+            return;
+        }
+        let folder = ChipLibrary::get_instance().shared_folder(self.has_capability(capabilities::CUSTOM_CHIPS));
+        if full || !self.has_capability(capabilities::FOLDER_DELTAS) {
+            self.sent_seq.reset();
+            self.sent_folder = folder.clone();
+            self.send(ClientMessage::FolderUpdate(folder));
+            return;
+        }
+        let ops = diff(&self.sent_folder, &folder);
+        if ops.is_empty() {
+            return;
+        }
+        self.sent_folder = folder;
+        let seq = self.sent_seq.advance();
+        self.send(ClientMessage::FolderDelta{seq, ops});
+    }
+
+    /// a delta was missed, so every folder is asked for again, and again if they don't come
+    fn resync(&mut self) {
+        self.resyncing = true;
+        self.send(ClientMessage::Resync);
+        let id = self.socket_id;
+        let callback = self.link.callback(move |_| GroupFldrAgentSocketMsg::ResyncTimedOut(id));
+        self.resync_timeout = Some(TimeoutService::spawn(Duration::from_millis(RESYNC_TIMEOUT), callback));
+    }
+
+    /// the server said no in a way that trying again won't fix
+    fn give_up(&mut self, why: &str) -> GroupFldrAgentOutMsg {
+        unsafe{alert(why)};
+        self.leave_group();
+        GroupFldrAgentOutMsg::LeftGroup
+    }
+
+    /// wait a bit and try again, unless it's time to give up on the group
+    fn connection_lost(&mut self) -> Option<GroupFldrAgentOutMsg> {
+        self.web_socket.take();
+        self.socket_update_timeout.take();
+        // a closed socket often reports an error as well, that shouldn't count as a second try
+        self.socket_id = self.socket_id.wrapping_add(1);
+        let was_connected = match self.reconnector.state() {
+            // the group was already left
+            ConnectionState::Idle => return None,
+            // an old server turns away the hello's address, so it's tried the old way before giving up
+            ConnectionState::Joining if !self.legacy && !self.welcomed => return self.fall_back_to_legacy(),
+            ConnectionState::Joining => false,
+            ConnectionState::Connected | ConnectionState::Reconnecting{..} => true,
+        };
+        match self.reconnector.connection_lost() {
+            Some(delay) => {
+                let callback = self.link.callback(|_| GroupFldrAgentSocketMsg::Reconnect);
+                self.reconnect_timeout = Some(TimeoutService::spawn(delay, callback));
+                Some(GroupFldrAgentOutMsg::Reconnecting)
+            }
+            None => {
+                let why = if was_connected {
+                    "Lost the connection to the group and couldn't get it back"
+                } else {
+                    "Couldn't connect to the group server"
+                };
+                Some(self.give_up(why))
+            }
+        }
+    }
+
+    fn reconnect(&mut self) {
+        if !self.reconnector.is_reconnecting() {
+            return;
+        }
+        if self.connect().is_err() {
+            let id = self.socket_id;
+            self.link.send_message(GroupFldrAgentSocketMsg::ConnectionLost(id));
+        }
+    }
+
+    /// drop the connection and forget the group, nothing is retried after this
     fn leave_group(&mut self) {
+        // lets the server drop the player now rather than waiting to see if they come back
+        if self.reconnector.state() == ConnectionState::Connected && !self.legacy {
+            self.send(ClientMessage::Leave);
+        }
+        self.session = None;
+        self.reconnector.left();
+        self.reconnect_timeout.take();
         self.web_socket.take();
         self.socket_update_timeout.take();
+        self.resync_timeout.take();
+        self.socket_id = self.socket_id.wrapping_add(1);
+        self.clear_group_folders();
     }
 
     fn check_folder_upated(&mut self) {
+        if self.reconnector.state() != ConnectionState::Connected || self.is_spectator() {
+            return;
+        }
+
         let library = ChipLibrary::get_instance();
         if !library.folder_changed() {
             return;
@@ -233,21 +559,19 @@ impl GroupFldrMsgBus {
             return;
         }
 
-        let folder = library.serialize_folder();
-        match &mut self.web_socket {
-            Some(socket) => socket.send_binary(Ok(folder)),
-            None => {}
-        }
+        self.send_folder(false);
 
+        let delay = library.config().protocol.folder_update_delay;
         let timeout = TimeoutService::spawn(
-            Duration::from_secs(1),
+            Duration::from_millis(u64::from(delay)),
             self.timeout_callback.clone(),
         );
         self.socket_update_timeout = Some(timeout);
 
     }
 
-    fn clear_group_folders(&self) {
+    fn clear_group_folders(&mut self) {
+        self.group.clear();
         folders_updated(HashMap::default());
     }
 
@@ -265,5 +589,39 @@ fn folders_updated(new_folders: HashMap<String, Vec<GroupFolderChip>>) -> bool {
 
     //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str("Folders updated"));
     *folders = new_folders;
+    drop(folders);
+    ChipLibrary::get_instance().update_shared_chips();
     return true;
-}
\ No newline at end of file
+}
+
+/// make a delta's changes to the library's copy of a player's folder, only the chips it adds are read
+///
+/// folder is the player's whole folder with the delta applied, it's read in full if the copy has drifted from it
+fn player_folder_changed(player: &str, ops: &[FolderOp], folder: &[FolderChip]) -> bool {
+    let mut folders = match ChipLibrary::get_instance().group_folders.try_borrow_mut() {
+        Ok(folders) => folders,
+        Err(_) => {
+            ConsoleService::log("Folder update failed");
+            return false;
+        },
+    };
+    let chips = folders.entry(player.to_owned()).or_default();
+    let applied = ops.iter().all(|op| match op {
+        FolderOp::Added{index, chip} if *index as usize <= chips.len() => {
+            chips.insert(*index as usize, GroupFolderChip::from(chip.clone()));
+            true
+        }
+        FolderOp::Removed{index} if (*index as usize) < chips.len() => {
+            chips.remove(*index as usize);
+            true
+        }
+        FolderOp::Used{index, used} => chips.get_mut(*index as usize).map(|chip| chip.used = *used).is_some(),
+        _ => false,
+    });
+    if !applied || chips.len() != folder.len() {
+        *chips = folder.iter().cloned().map(GroupFolderChip::from).collect();
+    }
+    drop(folders);
+    ChipLibrary::get_instance().update_shared_chips();
+    true
+}
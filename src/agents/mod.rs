@@ -1,3 +1,4 @@
 pub(crate) mod global_msg;
 pub(crate) mod chip_desc;
-pub(crate) mod group_folder;
\ No newline at end of file
+pub(crate) mod group_folder;
+pub(crate) mod reconnect;
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+/// how long to wait before the first try at reconnecting, in milliseconds
+const FIRST_DELAY: u64 = 1000;
+
+/// the wait doubles after every failed try, up to this
+const MAX_DELAY: u64 = 30_000;
+
+/// tries in a row before giving up, a little over five minutes of trying
+const MAX_ATTEMPTS: u32 = 15;
+
+/// Where the connection to the group server is at
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ConnectionState {
+    /// not in a group
+    Idle,
+    /// waiting for the server to let the player into the group for the first time
+    Joining,
+    Connected,
+    /// the connection dropped, attempt is how many tries have been made since
+    Reconnecting{attempt: u32},
+}
+
+/// Decides when to try reconnecting to a group, waiting longer after each failed try
+///
+/// only a group that was joined gets reconnected to, a first join that fails is just a failure
+#[derive(Debug)]
+pub(crate) struct Reconnector {
+    state: ConnectionState,
+}
+
+impl Default for Reconnector {
+    fn default() -> Self {
+        Reconnector {
+            state: ConnectionState::Idle,
+        }
+    }
+}
+
+/// the wait before a try, doubling each time
+fn delay(attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_millis((FIRST_DELAY * factor).min(MAX_DELAY))
+}
+
+impl Reconnector {
+    pub(crate) fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    pub(crate) fn is_reconnecting(&self) -> bool {
+        matches!(self.state, ConnectionState::Reconnecting{..})
+    }
+
+    /// a group is being joined for the first time
+    pub(crate) fn joining(&mut self) {
+        self.state = ConnectionState::Joining;
+    }
+
+    /// the server let the player in, true if it was a reconnect
+    pub(crate) fn connected(&mut self) -> bool {
+        let reconnected = self.is_reconnecting();
+        self.state = ConnectionState::Connected;
+        reconnected
+    }
+
+    /// the connection dropped or a try failed, how long to wait before trying again
+    ///
+    /// None means give up, either it never connected or it's tried too many times
+    pub(crate) fn connection_lost(&mut self) -> Option<Duration> {
+        let attempt = match self.state {
+            ConnectionState::Connected => 1,
+            ConnectionState::Reconnecting{attempt} if attempt < MAX_ATTEMPTS => attempt + 1,
+            _ => {
+                self.state = ConnectionState::Idle;
+                return None;
+            }
+        };
+        self.state = ConnectionState::Reconnecting{attempt};
+        Some(delay(attempt))
+    }
+
+    /// the group was left on purpose, or for good
+    pub(crate) fn left(&mut self) {
+        self.state = ConnectionState::Idle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_double_until_giving_up() {
+        let mut reconnector = Reconnector::default();
+        reconnector.joining();
+        assert!(!reconnector.connected());
+
+        let delays = (0..MAX_ATTEMPTS).map(|_| reconnector.connection_lost().unwrap().as_millis()).collect::<Vec<u128>>();
+        assert_eq!(&delays[..6], &[1000, 2000, 4000, 8000, 16000, 30000]);
+        assert_eq!(delays[MAX_ATTEMPTS as usize - 1], 30000);
+        assert_eq!(reconnector.state(), ConnectionState::Reconnecting{attempt: MAX_ATTEMPTS});
+        assert_eq!(reconnector.connection_lost(), None);
+        assert_eq!(reconnector.state(), ConnectionState::Idle);
+    }
+
+    #[test]
+    fn reconnecting_starts_over_once_connected() {
+        let mut reconnector = Reconnector::default();
+        reconnector.joining();
+        reconnector.connected();
+        reconnector.connection_lost();
+        reconnector.connection_lost();
+        assert!(reconnector.connected());
+        assert_eq!(reconnector.connection_lost(), Some(Duration::from_millis(FIRST_DELAY)));
+
+        // a first join that doesn't work isn't retried
+        let mut reconnector = Reconnector::default();
+        reconnector.joining();
+        assert_eq!(reconnector.connection_lost(), None);
+        reconnector.left();
+        assert_eq!(reconnector.connection_lost(), None);
+    }
+}
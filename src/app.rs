@@ -3,26 +3,31 @@ use yew::services::{
     reader::{ReaderService, ReaderTask, FileData},
     timeout::{TimeoutService, TimeoutTask},
     interval::{IntervalService, IntervalTask},
+    keyboard::{KeyboardService, KeyListenerHandle},
 };
 use std::borrow::Cow;
 use std::time::Duration;
 
-use crate::util::{storage_available, alert};
+use crate::util::{storage_available, alert, confirm, save_json, format_date, reload_page};
 use crate::components::{
     library::LibraryComponent as Library,
     pack::PackComponent as Pack,
     folder::FolderComponent as Folder,
     chip_desc::ChipDescComponent as ChipDescBox,
     group_folder::GroupFolderComponent as GroupFolder,
+    combat_log::CombatLogComponent as CombatLog,
+    custom_chips::CustomChipEditor,
+    settings::SettingsPanel,
     //group_folders::GroupFolders,
 };
 use crate::agents::{
     global_msg::{GlobalMsgBus, Request as GlobalReq},
     group_folder::{GroupFldrMsgBus, GroupFldrAgentOutMsg, GroupFldrAgentReq},
 };
-use crate::chip_library::ChipLibrary;
+use crate::chip_library::{ChipLibrary, ChipChange, ChipDiff, Command, DamageChange, ImportMode, SaveData, SourceStatus, BASE_SOURCE};
 
 use wasm_bindgen::JsCast;
+use unchecked_unwrap::UncheckedUnwrap;
 
 
 /// The different tabs that can be open
@@ -31,6 +36,7 @@ pub(crate) enum Tabs {
     Library,
     Pack,
     Folder,
+    Log,
     GroupFolder(String),
 }
 
@@ -47,6 +53,7 @@ impl Tabs {
                 Tabs::Library => Cow::Borrowed("Lb"),
                 Tabs::Pack => Cow::Borrowed("Pk"),
                 Tabs::Folder => Cow::Borrowed("Fl"),
+                Tabs::Log => Cow::Borrowed("Lg"),
                 Tabs::GroupFolder(grp_fldr) => {
                     let text = if grp_fldr.len() > 1 {
                         unsafe{grp_fldr.get_unchecked(..=1)}
@@ -61,6 +68,7 @@ impl Tabs {
                 Tabs::Library => Cow::Borrowed("Lib"),
                 Tabs::Pack => Cow::Borrowed("Pck"),
                 Tabs::Folder => Cow::Borrowed("Fldr"),
+                Tabs::Log => Cow::Borrowed("Log"),
                 Tabs::GroupFolder(grp_fldr) => {
                     let text = if grp_fldr.len() > 5 {
                         unsafe{grp_fldr.get_unchecked(..=5)}
@@ -81,6 +89,7 @@ impl Tabs {
             Tabs::Library => Cow::Borrowed("Library"),
             Tabs::Pack => Cow::Borrowed("Pack"),
             Tabs::Folder => Cow::Borrowed("Library"),
+            Tabs::Log => Cow::Borrowed("Combat Log"),
             Tabs::GroupFolder(grp_fldr) => {
                 if grp_fldr.len() > 15 {
                     let shortened_text = unsafe{grp_fldr.get_unchecked(..=12)};
@@ -103,6 +112,7 @@ impl PartialEq<str> for Tabs {
             Tabs::Library => {"Library" == other}
             Tabs::Pack => {"Pack" == other}
             Tabs::Folder => {"Folder" == other}
+            Tabs::Log => {"Log" == other}
             Tabs::GroupFolder(grp_fldr) => {
                 grp_fldr == other
             }
@@ -117,14 +127,35 @@ pub(crate) enum TopLevelMsg {
     SetMsg(String),
     JoinGroupData{group_name: String, player_name: String, spectator: bool},
     JoinGroup,
+    JoinedGroup,
     LeftGroup,
     GroupsUpdated,
+    PlayerFolderUpdated,
+    Reconnecting,
     EraseData,
     ImportData,
+    ImportText,
+    PreviewText,
     FileSelected(web_sys::File),
     LoadFile(Vec<u8>),
     CancelModal,
     ModalOk,
+    ManageProfiles,
+    ManageCustomChips,
+    CustomChipsChanged(String),
+    ManageSources,
+    ToggleSource(String, bool),
+    ManageSettings,
+    SettingsSaved(String),
+    CreateProfile,
+    SwitchProfile(String),
+    RenameProfile(String),
+    DeleteProfile(String),
+    ExportProfile(String),
+    SetImportMode(ImportMode),
+    ConfirmImport,
+    Undo,
+    Redo,
     DoNothing,
 }
 
@@ -146,6 +177,21 @@ impl From<GlobalReq> for TopLevelMsg {
             GlobalReq::ImportData => {
                 TopLevelMsg::ImportData
             }
+            GlobalReq::ImportText => {
+                TopLevelMsg::ImportText
+            }
+            GlobalReq::ManageProfiles => {
+                TopLevelMsg::ManageProfiles
+            }
+            GlobalReq::ManageCustomChips => {
+                TopLevelMsg::ManageCustomChips
+            }
+            GlobalReq::ManageSources => {
+                TopLevelMsg::ManageSources
+            }
+            GlobalReq::ManageSettings => {
+                TopLevelMsg::ManageSettings
+            }
         }
     }
 }
@@ -155,7 +201,7 @@ impl From<GroupFldrAgentOutMsg> for TopLevelMsg {
     fn from(msg: GroupFldrAgentOutMsg) -> Self {
         match msg {
             GroupFldrAgentOutMsg::JoinedGroup => {
-                TopLevelMsg::DoNothing   
+                TopLevelMsg::JoinedGroup
             }
             GroupFldrAgentOutMsg::LeftGroup => {
                 TopLevelMsg::LeftGroup
@@ -163,6 +209,12 @@ impl From<GroupFldrAgentOutMsg> for TopLevelMsg {
             GroupFldrAgentOutMsg::GroupUpdated => {
                 TopLevelMsg::GroupsUpdated
             }
+            GroupFldrAgentOutMsg::PlayerUpdated(_) => {
+                TopLevelMsg::PlayerFolderUpdated
+            }
+            GroupFldrAgentOutMsg::Reconnecting => {
+                TopLevelMsg::Reconnecting
+            }
         }
     }
 }
@@ -174,6 +226,13 @@ pub(crate) enum ModalStatus {
     JoinGroup,
     EraseData,
     ImportData,
+    PasteText,
+    ImportPreview,
+    Profiles,
+    CustomChips,
+    Sources,
+    Settings,
+    WhatsNew,
     Closed,
 }
 
@@ -192,7 +251,21 @@ pub(crate) struct App
     player_name: Option<String>,
     load_file_callback_promise: Option<ReaderTask>,
     file_input_ref: NodeRef,
+    profile_name_ref: NodeRef,
+    paste_text_ref: NodeRef,
+    /// a file that's been read and checked, waiting for the user to confirm the import
+    pending_import: Option<SaveData>,
+    /// chips left out of the pending import because the library doesn't have them
+    skipped_import: Vec<String>,
+    import_mode: ImportMode,
     _save_interval_handle: Option<IntervalTask>,
+    _keydown_handle: Option<KeyListenerHandle>,
+    /// bumped after undo or redo so the pack and folder re-render
+    data_revision: u32,
+    /// the connection to the group dropped and is being retried
+    reconnecting: bool,
+    /// the players whose folders have chips in them, each gets a tab
+    group_tabs: Vec<String>,
 }
 
 // the interval for ensuring that the data gets saved
@@ -229,6 +302,113 @@ fn join_group_callback(_: MouseEvent) -> TopLevelMsg {
     
 }
 
+/// Ctrl+Z to undo, Ctrl+Y or Ctrl+Shift+Z to redo
+///
+/// text inputs keep their own undo
+fn keydown_callback(e: KeyboardEvent) -> TopLevelMsg {
+    if !(e.ctrl_key() || e.meta_key()) {
+        return TopLevelMsg::DoNothing;
+    }
+    let in_input = e.target().map_or(false, |target| {
+        target.dyn_ref::<web_sys::HtmlInputElement>().is_some() || target.dyn_ref::<web_sys::HtmlTextAreaElement>().is_some()
+    });
+    if in_input {
+        return TopLevelMsg::DoNothing;
+    }
+    let msg = match e.key().as_str() {
+        "z" | "Z" if e.shift_key() => TopLevelMsg::Redo,
+        "z" | "Z" => TopLevelMsg::Undo,
+        "y" | "Y" => TopLevelMsg::Redo,
+        _ => return TopLevelMsg::DoNothing,
+    };
+    e.prevent_default();
+    msg
+}
+
+/// one section of the import preview, nothing if there's nothing in it
+fn import_changes(title: &str, changes: &[ChipChange]) -> Html {
+    if changes.is_empty() {
+        return html!{};
+    }
+    let rows = changes.iter().map(|change| {
+        let mut text = change.name.clone();
+        text.push_str(": ");
+        text.push_str(&change.before.to_string());
+        text.push_str(" -> ");
+        text.push_str(&change.after.to_string());
+        html!{<li>{text}</li>}
+    }).collect::<Html>();
+    html!{
+        <div>
+            <b>{title}</b>
+            <ul>{rows}</ul>
+        </div>
+    }
+}
+
+/// the players in the group with chips in their folders, sorted
+fn players_with_chips() -> Vec<String> {
+    let folders = ChipLibrary::get_instance().group_folders.borrow();
+    let mut players = folders.iter().filter(|(_, folder)| !folder.is_empty()).map(|(player, _)| player.clone()).collect::<Vec<String>>();
+    players.sort_unstable();
+    players
+}
+
+/// lets the user know the chips they see might be out of date
+fn cached_library_indicator() -> Html {
+    let saved = match ChipLibrary::get_instance().cached_library {
+        Some(saved) => saved,
+        None => return html!{},
+    };
+    let text = String::from("Offline library from ") + &format_date(saved);
+    html!{
+        <span class="cachedLibraryIndicator" title="The chip library couldn't be downloaded, a saved copy is being used instead">
+            {text}
+        </span>
+    }
+}
+
+fn whats_new_names(title: &str, names: &[String]) -> Html {
+    if names.is_empty() {
+        return html!{};
+    }
+    let rows = names.iter().map(|name| html!{<li>{name}</li>}).collect::<Html>();
+    html!{
+        <div>
+            <b>{title}</b>
+            <ul>{rows}</ul>
+        </div>
+    }
+}
+
+/// a changed chip and each field that changed, owned chips that got buffed or nerfed stand out
+fn whats_new_chip(chip: &ChipDiff, owned: bool) -> Html {
+    let class = match chip.damage {
+        DamageChange::Buffed if owned => "chipBuffed",
+        DamageChange::Nerfed if owned => "chipNerfed",
+        _ => "",
+    };
+    let fields = chip.fields.iter().map(|field| {
+        let text = String::from(field.field) + ": " + &field.before + " -> " + &field.after;
+        html!{<li>{text}</li>}
+    }).collect::<Html>();
+    let name = if owned {chip.name.clone() + " (owned)"} else {chip.name.clone()};
+    html!{
+        <li class=class>
+            {name}
+            <ul>{fields}</ul>
+        </li>
+    }
+}
+
+fn import_count_change(title: &str, before: usize, after: usize) -> Html {
+    if before == after {
+        return html!{};
+    }
+    let text = String::from(title) + ": " + &before.to_string() + " -> " + &after.to_string();
+    html!{<div>{text}</div>}
+}
+
 fn load_file_callback(e: ChangeData) -> TopLevelMsg {
     if let ChangeData::Files(files) = e {
         let file: Option<TopLevelMsg> = files.item(0).map(|f| TopLevelMsg::FileSelected(f));
@@ -274,6 +454,29 @@ impl App {
         
     }
 
+    fn groups_updated(&mut self) -> ShouldRender {
+        self.group_tabs = players_with_chips();
+        if let Tabs::GroupFolder(name) = &self.active_tab {
+            if ChipLibrary::get_instance().not_in_group_or_empty_fldr(name) {
+                // player left
+                self.active_tab = Tabs::Library;
+            }
+        }
+        true
+    }
+
+    /// lets the user know the group folders won't update until the connection is back
+    fn reconnecting_indicator(&self) -> Html {
+        if !self.reconnecting {
+            return html!{};
+        }
+        html!{
+            <span class="reconnectingIndicator" title="The connection to the group dropped, trying to get it back">
+                {"Reconnecting…"}
+            </span>
+        }
+    }
+
     fn gen_nav_tabs(&self) -> Html {
 
        if self.player_name.is_some() {
@@ -373,6 +576,7 @@ impl App {
                 <button class=folder_class onclick=folder_callback>{Tabs::Folder.shorten_string(Some(player_ct))}</button>
                 <button class=pack_class onclick=pack_callback>{Tabs::Pack.shorten_string(Some(player_ct))}</button>
                 <button class=library_class onclick=library_callback>{Tabs::Library.shorten_string(Some(player_ct))}</button>
+                {self.log_tab_button(Tabs::Log.shorten_string(Some(player_ct)))}
                 {player_tabs}
             </div>
         }
@@ -417,6 +621,15 @@ impl App {
                 let folder_class = "activeNavTab";
                 (pack_callback, folder_callback, library_callback, pack_class, library_class, folder_class)
             }
+            Tabs::Log => {
+                let pack_callback = self.link.callback(|_| TopLevelMsg::ChangeTab(Tabs::Pack));
+                let folder_callback = self.link.callback(|_| TopLevelMsg::ChangeTab(Tabs::Folder));
+                let library_callback = self.link.callback(|_| TopLevelMsg::ChangeTab(Tabs::Library));
+                let pack_class = "inactiveNavTab";
+                let library_class = "inactiveNavTab";
+                let folder_class = "inactiveNavTab";
+                (pack_callback, folder_callback, library_callback, pack_class, library_class, folder_class)
+            }
             Tabs::GroupFolder(_) => {
                 #[cfg(debug_assertions)]
                 unreachable!();
@@ -430,10 +643,22 @@ impl App {
                 <button class=folder_class onclick=folder_callback>{"Folder"}</button>
                 <button class=pack_class onclick=pack_callback>{"Pack"}</button>
                 <button class=library_class onclick=library_callback>{"Library"}</button>
+                {self.log_tab_button(Tabs::Log.shorten_string(None))}
             </div>
         }
     }
 
+    fn log_tab_button(&self, text: Cow<'static, str>) -> Html {
+        let (button_class, callback) = if self.active_tab == Tabs::Log {
+            ("activeNavTab", Callback::noop())
+        } else {
+            ("inactiveNavTab", self.link.callback(|_: MouseEvent| TopLevelMsg::ChangeTab(Tabs::Log)))
+        };
+        html!{
+            <button class=button_class onclick=callback>{text}</button>
+        }
+    }
+
     fn build_modal(&self) -> Html {
         match self.modal_status {
 
@@ -446,6 +671,35 @@ impl App {
             ModalStatus::ImportData => {
                 self.import_or_erase_modal(true)
             }
+            ModalStatus::PasteText => {
+                self.paste_text_modal()
+            }
+            ModalStatus::ImportPreview => {
+                self.import_preview_modal()
+            }
+            ModalStatus::Profiles => {
+                self.profiles_modal()
+            }
+            ModalStatus::CustomChips => {
+                let changed = self.link.callback(TopLevelMsg::CustomChipsChanged);
+                let closed = self.link.callback(|_: MouseEvent| TopLevelMsg::CancelModal);
+                html!{
+                    <CustomChipEditor changed=changed closed=closed/>
+                }
+            }
+            ModalStatus::Sources => {
+                self.sources_modal()
+            }
+            ModalStatus::Settings => {
+                let saved = self.link.callback(TopLevelMsg::SettingsSaved);
+                let closed = self.link.callback(|_: MouseEvent| TopLevelMsg::CancelModal);
+                html!{
+                    <SettingsPanel saved=saved closed=closed/>
+                }
+            }
+            ModalStatus::WhatsNew => {
+                self.whats_new_modal()
+            }
             
             //closed, display nothing
             ModalStatus::Closed => html!{},
@@ -488,6 +742,11 @@ impl App {
         let cancel_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::CancelModal);
         let ok_callback = self.link.callback(|_:MouseEvent| TopLevelMsg::ModalOk);
         let header_text = if import {"Import Data"} else {"Erase Data"};
+        let body_text = if import {
+            "Choose a file to import, you'll see what it changes before anything is overwritten"
+        } else {
+            "This will erase all existing data, are you sure?"
+        };
         html!{
             <div class="yew-modal">
                 <div class="yew-modal-content">
@@ -495,7 +754,7 @@ impl App {
                         <h2>{header_text}</h2>
                     </div>
                     <div class="yew-modal-body">
-                        {"This will erase all existing data, are you sure?"}
+                        {body_text}
                     </div>
                     <div class="yew-modal-footer">
                         <span style="padding-left: 5px">
@@ -511,6 +770,340 @@ impl App {
         //todo!();
     }
 
+    fn paste_text_modal(&self) -> Html {
+        let cancel_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::CancelModal);
+        let ok_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::PreviewText);
+        html!{
+            <div class="yew-modal">
+                <div class="yew-modal-content">
+                    <div class="yew-modal-header">
+                        <h2>{"Import Txt"}</h2>
+                    </div>
+                    <div class="yew-modal-body">
+                        {"Paste text from Export Txt, like \"Folder: Cannon, Sword (Used)\" and \"Pack: Cannon x3 (2 Used)\""}
+                        <textarea class="pasteText" rows="8" ref=self.paste_text_ref.clone()/>
+                    </div>
+                    <div class="yew-modal-footer">
+                        <span style="padding-left: 5px">
+                            <button class="ok-button" onclick=ok_callback>{"Preview"}</button>
+                        </span>
+                        <span style="float: right">
+                            <button class="inactiveNavTab" onclick=cancel_callback>{"Cancel"}</button>
+                        </span>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    /// read the pasted text, the dialog stays open if it can't be read so it can be fixed
+    fn preview_text(&mut self) -> bool {
+        let text = match self.paste_text_ref.cast::<web_sys::HtmlTextAreaElement>() {
+            Some(textarea) => textarea.value(),
+            None => return false,
+        };
+        match ChipLibrary::get_instance().parse_txt(&text) {
+            Ok(save) => {
+                self.stage_import(save);
+                true
+            }
+            Err(why) => {
+                unsafe{alert(&why)};
+                false
+            }
+        }
+    }
+
+    fn import_preview_modal(&self) -> Html {
+        let cancel_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::CancelModal);
+        let ok_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::ConfirmImport);
+        let incoming = match &self.pending_import {
+            Some(incoming) => incoming,
+            None => return html!{},
+        };
+        let preview = ChipLibrary::get_instance().preview_import(incoming, self.import_mode);
+
+        let mode_buttons = [ImportMode::Replace, ImportMode::MergeAdd, ImportMode::MergeMax].iter().map(|mode| {
+            let mode = *mode;
+            let class = if mode == self.import_mode {"activeNavTab"} else {"inactiveNavTab"};
+            let callback = self.link.callback(move |_: MouseEvent| TopLevelMsg::SetImportMode(mode));
+            html!{
+                <button class=class onclick=callback>{mode.describe()}</button>
+            }
+        }).collect::<Html>();
+
+        let skipped = if self.skipped_import.is_empty() {
+            html!{}
+        } else {
+            let text = String::from("Not in the library, these will be left out: ") + &self.skipped_import.join(", ");
+            html!{<div class="searchError">{text}</div>}
+        };
+
+        let body = match preview {
+            Err(why) => html!{<div class="searchError">{why}</div>},
+            Ok(preview) if preview.is_empty() => html!{<div>{"Nothing would change"}</div>},
+            Ok(preview) => html!{
+                <>
+                {import_changes("Added", &preview.added)}
+                {import_changes("Changed", &preview.changed)}
+                {import_changes("Removed", &preview.removed)}
+                {import_count_change("Folder chips", preview.folder_before, preview.folder_after)}
+                {import_count_change("Chip limit", preview.limit_before, preview.limit_after)}
+                </>
+            },
+        };
+
+        html!{
+            <div class="yew-modal">
+                <div class="yew-modal-content">
+                    <div class="yew-modal-header">
+                        <h2>{"Import Data"}</h2>
+                    </div>
+                    <div class="yew-modal-body">
+                        <div class="importModes">
+                            {mode_buttons}
+                        </div>
+                        <div class="importPreview">
+                            {skipped}
+                            {body}
+                        </div>
+                    </div>
+                    <div class="yew-modal-footer">
+                        <span style="padding-left: 5px">
+                            <button class="ok-button" onclick=ok_callback>{"Import"}</button>
+                        </span>
+                        <span style="float: right">
+                            <button class="inactiveNavTab" onclick=cancel_callback>{"Cancel"}</button>
+                        </span>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    fn profiles_modal(&self) -> Html {
+        let cancel_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::CancelModal);
+        let create_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::CreateProfile);
+        let profiles = unsafe{ChipLibrary::get_instance().profiles.try_borrow().unchecked_unwrap()};
+        let active = profiles.active_id();
+
+        let rows = profiles.list().iter().map(|profile| {
+            let switch_name = profile.name.clone();
+            let rename_name = profile.name.clone();
+            let delete_name = profile.name.clone();
+            let export_name = profile.name.clone();
+            let switch_callback = self.link.callback(move |_: MouseEvent| TopLevelMsg::SwitchProfile(switch_name.clone()));
+            let rename_callback = self.link.callback(move |_: MouseEvent| TopLevelMsg::RenameProfile(rename_name.clone()));
+            let delete_callback = self.link.callback(move |_: MouseEvent| TopLevelMsg::DeleteProfile(delete_name.clone()));
+            let export_callback = self.link.callback(move |_: MouseEvent| TopLevelMsg::ExportProfile(export_name.clone()));
+            let is_active = profile.id == active;
+            html!{
+                <div class="profileRow">
+                    <span class="profileName">
+                        {&profile.name}
+                        {if is_active {" (active)"} else {""}}
+                    </span>
+                    <button class="inactiveNavTab" onclick=switch_callback disabled=is_active>{"Switch"}</button>
+                    <button class="inactiveNavTab" onclick=rename_callback>{"Rename"}</button>
+                    <button class="inactiveNavTab" onclick=export_callback>{"Export"}</button>
+                    <button class="inactiveNavTab" onclick=delete_callback disabled=is_active>{"Delete"}</button>
+                </div>
+            }
+        }).collect::<Html>();
+
+        html!{
+            <div class="yew-modal">
+                <div class="yew-modal-content">
+                    <div class="yew-modal-header">
+                        <h2>{"Profiles"}</h2>
+                    </div>
+                    <div class="yew-modal-body">
+                        {rows}
+                        <br/>
+                        <input type="text" placeholder="profile name" ref=self.profile_name_ref.clone()/>
+                        <button class="ok-button" onclick=create_callback>{"Create"}</button>
+                        <br/>
+                        {"Type a name above to create a profile or rename one"}
+                    </div>
+                    <div class="yew-modal-footer">
+                        <span style="float: right">
+                            <button class="inactiveNavTab" onclick=cancel_callback>{"Close"}</button>
+                        </span>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    fn sources_modal(&self) -> Html {
+        let cancel_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::CancelModal);
+        let library = ChipLibrary::get_instance();
+        let rows = library.sources.iter().map(|source| self.source_row(source)).collect::<Html>();
+
+        let conflicts = if library.source_conflicts.is_empty() {
+            html!{}
+        } else {
+            let items = library.source_conflicts.iter().map(|conflict| {
+                let winner = conflict.sources.last().map_or("", |source| source.as_str());
+                html!{
+                    <li>{&conflict.name}{": "}{conflict.sources.join(", ")}{" (using "}{winner}{")"}</li>
+                }
+            }).collect::<Html>();
+            html!{
+                <div>
+                    <b>{"Conflicts"}</b>
+                    <ul>{items}</ul>
+                </div>
+            }
+        };
+
+        html!{
+            <div class="yew-modal">
+                <div class="yew-modal-content">
+                    <div class="yew-modal-header">
+                        <h2>{"Chip Sources"}</h2>
+                    </div>
+                    <div class="yew-modal-body">
+                        {"Sources lower in the list replace chips with the same name in the ones above them"}
+                        <br/>
+                        {rows}
+                        <div class="importPreview">
+                            {conflicts}
+                        </div>
+                    </div>
+                    <div class="yew-modal-footer">
+                        <span style="float: right">
+                            <button class="inactiveNavTab" onclick=cancel_callback>{"Close"}</button>
+                        </span>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    fn source_row(&self, source: &SourceStatus) -> Html {
+        let namespace = source.namespace.clone();
+        let enabled = source.enabled;
+        let toggle_callback = self.link.callback(move |_: MouseEvent| TopLevelMsg::ToggleSource(namespace.clone(), !enabled));
+        let status = match &source.error {
+            Some(why) => String::from(" (") + why + ")",
+            None if enabled && !source.problems.is_empty() => {
+                String::from(" (") + &source.chips.to_string() + " chips, " + &source.problems.len().to_string() + " problems)"
+            }
+            None if enabled => match source.cached {
+                Some(saved) => String::from(" (") + &source.chips.to_string() + " chips, offline copy from " + &format_date(saved) + ")",
+                None => String::from(" (") + &source.chips.to_string() + " chips)",
+            },
+            None => String::from(" (off)"),
+        };
+        html!{
+            <div class="profileRow">
+                <span class="profileName">{&source.namespace}{status}</span>
+                <input type="checkbox" checked=enabled disabled={source.namespace == BASE_SOURCE} onclick=toggle_callback/>
+            </div>
+        }
+    }
+
+    /// sources are merged when the library loads, so the page is reloaded to apply the change
+    fn toggle_source(&mut self, namespace: String, enabled: bool) -> bool {
+        if !enabled {
+            let question = String::from("Switch off ") + &namespace + "? The page will reload, and copies of its chips in your pack and folder will be put aside until it's switched back on";
+            if !confirm(&question) {
+                return true;
+            }
+        }
+        if let Err(why) = ChipLibrary::get_instance().set_source_enabled(&namespace, enabled) {
+            unsafe{alert(why)};
+            return true;
+        }
+        reload_page();
+        false
+    }
+
+    fn whats_new_modal(&self) -> Html {
+        let cancel_callback = self.link.callback(|_: MouseEvent| TopLevelMsg::CancelModal);
+        let library = ChipLibrary::get_instance();
+        let diff = match &library.library_diff {
+            Some(diff) => diff,
+            None => return html!{},
+        };
+
+        let renamed = diff.renamed.iter().map(|rename| rename.from.clone() + " -> " + &rename.to).collect::<Vec<String>>();
+
+        // the chips you have that got stronger or weaker matter the most, so they go first
+        let mut changed = diff.changed.iter().map(|chip| (chip, library.owns(&chip.name))).collect::<Vec<_>>();
+        changed.sort_by_key(|(chip, owned)| !(*owned && chip.damage != DamageChange::Same));
+        let changed = if changed.is_empty() {
+            html!{}
+        } else {
+            let rows = changed.iter().map(|(chip, owned)| whats_new_chip(chip, *owned)).collect::<Html>();
+            html!{
+                <div>
+                    <b>{"Changed"}</b>
+                    <ul>{rows}</ul>
+                </div>
+            }
+        };
+
+        html!{
+            <div class="yew-modal">
+                <div class="yew-modal-content">
+                    <div class="yew-modal-header">
+                        <h2>{"What's New"}</h2>
+                    </div>
+                    <div class="yew-modal-body">
+                        <div class="importPreview">
+                            {whats_new_names("Added", &diff.added)}
+                            {whats_new_names("Renamed", &renamed)}
+                            {changed}
+                            {whats_new_names("Removed", &diff.removed)}
+                        </div>
+                    </div>
+                    <div class="yew-modal-footer">
+                        <span style="float: right">
+                            <button class="inactiveNavTab" onclick=cancel_callback>{"Close"}</button>
+                        </span>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    /// the trimmed text typed into the profile name box
+    fn profile_name_input(&self) -> String {
+        match self.profile_name_ref.cast::<web_sys::HtmlInputElement>() {
+            Some(input) => input.value().trim().to_owned(),
+            None => String::new(),
+        }
+    }
+
+    /// create, rename, or delete a profile from the profiles modal
+    fn change_profiles(&mut self, res: Result<(), &'static str>, msg: String) -> bool {
+        match res {
+            Ok(()) => {
+                if let Some(input) = self.profile_name_ref.cast::<web_sys::HtmlInputElement>() {
+                    input.set_value("");
+                }
+                self.set_message(msg)
+            }
+            Err(why) => {
+                unsafe{alert(why)};
+                false
+            }
+        }
+    }
+
+    fn switch_profile(&mut self, name: String) -> bool {
+        if let Err(why) = ChipLibrary::get_instance().switch_profile(&name) {
+            unsafe{alert(why)};
+            return false;
+        }
+        self.modal_status = ModalStatus::Closed;
+        self.data_revision = self.data_revision.wrapping_add(1);
+        self.group_folder.send(GroupFldrAgentReq::UpdateFolder);
+        self.set_message(String::from("Switched to ") + &name)
+    }
+
     fn modal_ok(&mut self) -> bool {
         match self.modal_status {
             ModalStatus::EraseData => {
@@ -522,7 +1115,7 @@ impl App {
                 }
                 return false;
             }
-            ModalStatus::Closed | ModalStatus::JoinGroup => {
+            ModalStatus::Closed | ModalStatus::JoinGroup | ModalStatus::PasteText | ModalStatus::ImportPreview | ModalStatus::Profiles | ModalStatus::CustomChips | ModalStatus::Sources | ModalStatus::Settings | ModalStatus::WhatsNew => {
                 unreachable!();
             }
         }
@@ -531,29 +1124,76 @@ impl App {
         true
     }
 
+    /// read and check the file, it's only imported once the preview is confirmed
     fn load_file(&mut self, json: Vec<u8>) -> bool {
         self.load_file_callback_promise.take();
-            //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str("load file callback was called"));
-            self.modal_status = ModalStatus::Closed;
-            self.active_tab = Tabs::Library;
-            let json = match String::from_utf8(json) {
-                Ok(json) => json,
-                Err(_) => {
-                    unsafe{alert("File was invalid, corrupted maybe?")};
-                    return false;
-                }
-            };
-            match ChipLibrary::get_instance().import_json(json) {
-                Ok(()) => {
-                    self.set_message("chips imported".to_string());
-                }
-                Err(msg) => {
-                    unsafe{alert(msg)};
-                }
+        // so the same file can be picked again
+        if let Some(element) = self.file_input_ref.cast::<web_sys::HtmlInputElement>() {
+            element.set_value("");
+        }
+        self.modal_status = ModalStatus::Closed;
+        let json = match String::from_utf8(json) {
+            Ok(json) => json,
+            Err(_) => {
+                unsafe{alert("File was invalid, corrupted maybe?")};
+                return true;
             }
+        };
+        match SaveData::from_json(&json) {
+            Ok(save) => self.stage_import(save),
+            Err(why) => {
+                unsafe{alert(&why.to_string())};
+            }
+        }
         true
     }
 
+    /// fix up the chip names and show what importing would change
+    fn stage_import(&mut self, mut save: SaveData) {
+        self.skipped_import = ChipLibrary::get_instance().resolve_import(&mut save);
+        self.pending_import = Some(save);
+        self.import_mode = ImportMode::Replace;
+        self.modal_status = ModalStatus::ImportPreview;
+    }
+
+    fn confirm_import(&mut self) -> bool {
+        let incoming = match self.pending_import.take() {
+            Some(incoming) => incoming,
+            None => return false,
+        };
+        self.modal_status = ModalStatus::Closed;
+        if let Err(why) = ChipLibrary::get_instance().import_save(&incoming, self.import_mode) {
+            unsafe{alert(why)};
+            return true;
+        }
+        self.active_tab = Tabs::Library;
+        self.data_revision = self.data_revision.wrapping_add(1);
+        self.group_folder.send(GroupFldrAgentReq::UpdateFolder);
+        self.set_message("chips imported".to_string())
+    }
+
+    /// undo or redo the last change to the pack or folder
+    fn undo_redo(&mut self, undo: bool) -> bool {
+        let library = ChipLibrary::get_instance();
+        let res = if undo {library.undo()} else {library.redo()};
+        let command: Command = match res {
+            Ok(Some(command)) => command,
+            Ok(None) => {
+                let msg = if undo {"Nothing to undo"} else {"Nothing to redo"};
+                return self.set_message(msg.to_owned());
+            }
+            Err(why) => {
+                unsafe{alert(why)};
+                return false;
+            }
+        };
+        let mut msg = String::from(if undo {"Undid "} else {"Redid "});
+        msg.push_str(&command.describe());
+        self.data_revision = self.data_revision.wrapping_add(1);
+        self.group_folder.send(GroupFldrAgentReq::UpdateFolder);
+        self.set_message(msg)
+    }
+
     fn file_selected(&mut self, file: web_sys::File) -> bool {
         let callback = self.link.callback(|e: FileData|{
             TopLevelMsg::LoadFile(e.content)
@@ -633,6 +1273,17 @@ impl Component for App {
             TopLevelMsg::SetMsg(String::new())
         });
 
+        let _keydown_handle = web_sys::window().map(|window| {
+            KeyboardService::register_key_down(&window, link.callback(keydown_callback))
+        });
+
+        // show what changed in the library since the last visit, if anything did
+        let modal_status = if ChipLibrary::get_instance().library_diff.is_some() {
+            ModalStatus::WhatsNew
+        } else {
+            ModalStatus::Closed
+        };
+
         App {
             active_tab: Tabs::Library,
             message_txt: String::new(),
@@ -640,13 +1291,22 @@ impl Component for App {
             link,
             _producer,
             load_file_callback,
-            modal_status: ModalStatus::Closed,
+            modal_status,
             player_name: None,
             load_file_callback_promise: None,
             file_input_ref: NodeRef::default(),
+            profile_name_ref: NodeRef::default(),
+            paste_text_ref: NodeRef::default(),
+            pending_import: None,
+            skipped_import: Vec::new(),
+            import_mode: ImportMode::Replace,
             _save_interval_handle,
             group_folder,
             message_clear_callback,
+            _keydown_handle,
+            data_revision: 0,
+            reconnecting: false,
+            group_tabs: Vec::new(),
         }
     }
 
@@ -670,13 +1330,79 @@ impl Component for App {
                 self.modal_status = ModalStatus::ImportData;
                 true
             }
+            TopLevelMsg::ImportText => {
+                self.modal_status = ModalStatus::PasteText;
+                true
+            }
+            TopLevelMsg::PreviewText => self.preview_text(),
             TopLevelMsg::CancelModal => {
                 self.modal_status = ModalStatus::Closed;
+                self.pending_import.take();
                 true
             }
             TopLevelMsg::ModalOk => {
                 self.modal_ok()
             }
+            TopLevelMsg::ManageProfiles => {
+                self.modal_status = ModalStatus::Profiles;
+                true
+            }
+            TopLevelMsg::ManageCustomChips => {
+                self.modal_status = ModalStatus::CustomChips;
+                true
+            }
+            TopLevelMsg::CustomChipsChanged(msg) => {
+                self.data_revision = self.data_revision.wrapping_add(1);
+                self.group_folder.send(GroupFldrAgentReq::UpdateFolder);
+                self.set_message(msg)
+            }
+            TopLevelMsg::ManageSources => {
+                self.modal_status = ModalStatus::Sources;
+                true
+            }
+            TopLevelMsg::ToggleSource(namespace, enabled) => self.toggle_source(namespace, enabled),
+            TopLevelMsg::ManageSettings => {
+                self.modal_status = ModalStatus::Settings;
+                true
+            }
+            TopLevelMsg::SettingsSaved(msg) => {
+                self.modal_status = ModalStatus::Closed;
+                self.set_message(msg);
+                true
+            }
+            TopLevelMsg::CreateProfile => {
+                let name = self.profile_name_input();
+                let res = ChipLibrary::get_instance().create_profile(&name);
+                self.change_profiles(res, String::from("Created ") + &name)
+            }
+            TopLevelMsg::RenameProfile(name) => {
+                let new_name = self.profile_name_input();
+                let res = ChipLibrary::get_instance().rename_profile(&name, &new_name);
+                self.change_profiles(res, name + " renamed to " + &new_name)
+            }
+            TopLevelMsg::DeleteProfile(name) => {
+                let msg = String::from("This will erase everything saved for ") + &name + ", are you sure?";
+                if !confirm(&msg) {
+                    return false;
+                }
+                let res = ChipLibrary::get_instance().delete_profile(&name);
+                self.change_profiles(res, String::from("Deleted ") + &name)
+            }
+            TopLevelMsg::SwitchProfile(name) => self.switch_profile(name),
+            TopLevelMsg::ExportProfile(name) => {
+                match ChipLibrary::get_instance().export_profile_json(&name) {
+                    Ok(json) => save_json(json),
+                    Err(why) => unsafe{alert(why)},
+                }
+                false
+            }
+            TopLevelMsg::SetImportMode(mode) => {
+                self.import_mode = mode;
+                true
+            }
+            TopLevelMsg::ConfirmImport => self.confirm_import(),
+            TopLevelMsg::Undo => self.undo_redo(true),
+            TopLevelMsg::Redo => self.undo_redo(false),
             TopLevelMsg::JoinGroupData{group_name, player_name, spectator} => {
                 self.modal_status = ModalStatus::Closed;
                 let group_name = group_name.trim().to_owned();
@@ -695,19 +1421,29 @@ impl Component for App {
             }
             TopLevelMsg::LoadFile(json) => self.load_file(json),
             TopLevelMsg::FileSelected(file) => self.file_selected(file),
+            TopLevelMsg::JoinedGroup => {
+                let was_reconnecting = self.reconnecting;
+                self.reconnecting = false;
+                was_reconnecting
+            }
             TopLevelMsg::LeftGroup => {
                 self.player_name.take();
+                self.reconnecting = false;
                 true
             },
-            TopLevelMsg::GroupsUpdated => {
-                if let Tabs::GroupFolder(name) = &self.active_tab {
-                    if ChipLibrary::get_instance().not_in_group_or_empty_fldr(name) {
-                        // player left
-                        self.active_tab = Tabs::Library;
-                    }
+            TopLevelMsg::Reconnecting => {
+                let was_reconnecting = self.reconnecting;
+                self.reconnecting = true;
+                !was_reconnecting
+            }
+            TopLevelMsg::GroupsUpdated => self.groups_updated(),
+            TopLevelMsg::PlayerFolderUpdated => {
+                // the folder re-renders itself, the tabs only change when one is emptied or filled
+                if players_with_chips() == self.group_tabs {
+                    return false;
                 }
-                true
-            },
+                self.groups_updated()
+            }
             TopLevelMsg::DoNothing => false,
         }
     }
@@ -721,6 +1457,11 @@ impl Component for App {
                     <span style="padding-left: 5px">
                         {self.active_tab.to_display_text()}
                     </span>
+                    <span class="profileIndicator" onclick=self.link.callback(|_: MouseEvent| TopLevelMsg::ManageProfiles)>
+                        {ChipLibrary::get_instance().active_profile()}
+                    </span>
+                    {cached_library_indicator()}
+                    {self.reconnecting_indicator()}
                     <span style="float: right; color: red">
                         {&self.message_txt}
                     </span>
@@ -728,9 +1469,10 @@ impl Component for App {
                 <div style="background-color: #4abdb5; padding: 10px;">
                     {self.gen_nav_tabs()}
                     <div class="main-container">
-                        <Folder active={self.active_tab == Tabs::Folder} in_folder_group={self.player_name.is_some()} key="Folder".to_owned()/>
-                        <Pack active={self.active_tab == Tabs::Pack} key="Pack".to_owned()/>
-                        <Library active={self.active_tab == Tabs::Library} key="Library".to_owned()/>
+                        <Folder active={self.active_tab == Tabs::Folder} in_folder_group={self.player_name.is_some()} revision={self.data_revision} key="Folder".to_owned()/>
+                        <Pack active={self.active_tab == Tabs::Pack} revision={self.data_revision} key="Pack".to_owned()/>
+                        <Library active={self.active_tab == Tabs::Library} revision={self.data_revision} key="Library".to_owned()/>
+                        <CombatLog active={self.active_tab == Tabs::Log} key="Log".to_owned()/>
                         {self.gen_group_folders()}
                         <ChipDescBox key="ChipDescBox".to_owned()/>
                     </div>
@@ -1,27 +1,37 @@
 use crate::chip_library::{elements::Elements, skills::Skills, chip_type::{ChipClass, ChipType}, ranges::Ranges};
-use serde::Deserialize;
-use unchecked_unwrap::UncheckedUnwrap;
+use crate::chip_library::dice::{DiceExpr, DiceError, DamageStats, DEFAULT_SKILL_RANK};
+use crate::chip_library::roller::{parse_hits, MAX_HITS};
+use crate::chip_library::sources::CUSTOM_SOURCE;
+use serde::{Deserialize, Serialize};
 use std::cell::UnsafeCell;
 use std::cmp::{Ord, Ordering};
 use yew::prelude::*;
 
-#[derive(Deserialize)]
-#[serde(rename_all(deserialize = "PascalCase"))]
+/// written out the same way chips.json has them, so custom chips can be saved and shared
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
 pub(crate) struct BattleChip {
     pub name: String,
     pub element: Vec<Elements>,
     pub skills: Vec<Skills>,
     pub damage: String,
     pub class: ChipClass,
-    #[serde(rename(deserialize = "Type"))]
+    #[serde(rename = "Type")]
     pub kind: ChipType,
     pub range: Ranges,
     pub hits: String,
     pub description: String,
-    #[serde(skip, default = "default_dmg_cell_float")]
-    avg_dmg: UnsafeCell<f32>,
-    #[serde(skip, default = "default_dmg_cell_int")]
-    max_dmg: UnsafeCell<i32>,
+    /// names the chip went by in earlier versions of the library
+    #[serde(default, alias = "Formerly")]
+    pub aliases: Vec<String>,
+    /// made by a player rather than coming from chips.json
+    #[serde(skip)]
+    pub custom: bool,
+    /// the namespace of the source it was loaded from
+    #[serde(skip)]
+    pub source: String,
+    #[serde(skip, default = "default_dmg_cell")]
+    dmg_stats: UnsafeCell<Option<Result<DamageStats, DiceError>>>,
 }
 
 // using Unsafe cell because shouldn't need to be a mutex
@@ -37,8 +47,10 @@ impl Clone for BattleChip {
             range: self.range.clone(),
             hits: self.hits.clone(),
             description: self.description.clone(),
-            avg_dmg: UnsafeCell::new(f32::NAN),
-            max_dmg: UnsafeCell::new(-1),
+            aliases: self.aliases.clone(),
+            custom: self.custom,
+            source: self.source.clone(),
+            dmg_stats: default_dmg_cell(),
         }
     }
 }
@@ -70,53 +82,54 @@ impl BattleChip {
         if self.skills.len() > 1 {
             return Skills::Varies;
         }
-        unsafe{self.skills.get(0).unchecked_unwrap()}.to_owned()
+        self.skills.first().copied().unwrap_or(Skills::None)
     }
 
+    /// average damage, chips whose damage couldn't be read count as 0
     pub(crate) fn avg_dmg(&self) -> f32 {
-        let val = unsafe {*self.avg_dmg.get()};
-        if !val.is_nan() {
-            return val;
-        }
-        //else
-        self.load_dmg().0
+        self.damage_stats().map_or(0.0, |stats| stats.avg as f32)
     }
 
+    /// max damage, chips whose damage couldn't be read count as 0
     pub(crate) fn max_dmg(&self) -> i32 {
-        let val = unsafe {*self.max_dmg.get()};
-        if val != -1 {
-            return val;
-        }
-        //else
-        self.load_dmg().1
+        self.damage_stats().map_or(0, |stats| stats.max as i32)
     }
 
-    fn load_dmg(&self) -> (f32, i32) {
-        
-        let res = self.damage.split('d').collect::<Vec<&str>>();
-        let (max, avg) = if res.len() != 2 {
-            (0, 0.0)
-        } else {
+    /// chips without damage are written as "--"
+    pub(crate) fn has_damage(&self) -> bool {
+        let damage = self.damage.trim();
+        !(damage.is_empty() || damage == "--")
+    }
+
+    pub(crate) fn damage_expr(&self) -> Result<DiceExpr, DiceError> {
+        DiceExpr::parse(&self.damage)
+    }
 
-            let dice_res: Option<(i32, i32)> = try {
-                let num_dice = res.get(0)?.parse::<i32>().ok()?;
-                let die_size = res.get(1)?.parse::<i32>().ok()?;
-                (num_dice, die_size)
-            };
+    /// min, max, and average damage, "per Skill" damage is counted at the default rank
+    pub(crate) fn damage_stats(&self) -> Result<DamageStats, DiceError> {
+        if let Some(stats) = unsafe{&*self.dmg_stats.get()} {
+            return stats.clone();
+        }
+        //else
+        self.load_dmg()
+    }
 
-            let (num_dice, die_size) = dice_res.unwrap_or((0,0));
+    fn load_dmg(&self) -> Result<DamageStats, DiceError> {
 
-            let avg = ((die_size as f32 / 2.0) + 0.5) * (num_dice as f32);
-            let max = num_dice * die_size;
-            (max, avg)
+        let stats = if self.has_damage() {
+            self.damage_expr().and_then(|expr| expr.stats(DEFAULT_SKILL_RANK))
+        } else {
+            Ok(DamageStats::NONE)
         };
 
-        let (avg_ptr, max_ptr) = unsafe{(&mut *self.avg_dmg.get(), &mut *self.max_dmg.get())};
+        if let Err(why) = &stats {
+            log::warn!("Could not read the damage of {}: {}", self.name, why);
+        }
 
-        *avg_ptr = avg;
-        *max_ptr = max;
+        let stats_ptr = unsafe{&mut *self.dmg_stats.get()};
+        *stats_ptr = Some(stats.clone());
 
-        (avg, max)
+        stats
     }
 
     pub(crate) fn unknown_chip(name: &str) -> BattleChip {
@@ -130,11 +143,54 @@ impl BattleChip {
             range: Ranges::Itself,
             hits: "--".to_string(),
             description: "Unknown Chip".to_owned(),
-            avg_dmg: UnsafeCell::new(f32::NAN),
-            max_dmg: UnsafeCell::new(-1),
+            aliases: Vec::new(),
+            custom: false,
+            source: String::new(),
+            dmg_stats: default_dmg_cell(),
+        }
+    }
+
+    /// an empty chip to fill in with the custom chip editor
+    pub(crate) fn blank_custom() -> BattleChip {
+        BattleChip {
+            description: String::new(),
+            class: ChipClass::Standard,
+            kind: ChipType::Projectile,
+            range: Ranges::Far,
+            hits: "1".to_owned(),
+            custom: true,
+            ..BattleChip::unknown_chip("")
         }
     }
 
+    /// a copy of this chip marked as custom
+    pub(crate) fn as_custom(&self) -> BattleChip {
+        BattleChip {
+            custom: true,
+            ..self.clone()
+        }
+    }
+
+    /// a custom chip needs a name, at least one element and skill, damage that can be read, and no more than `MAX_HITS` hits
+    pub(crate) fn check_custom(&self) -> Result<(), &'static str> {
+        if self.name.trim().is_empty() {
+            return Err("Custom chips need a name");
+        }
+        if self.element.is_empty() {
+            return Err("Custom chips need at least one element");
+        }
+        if self.skills.is_empty() {
+            return Err("Custom chips need at least one skill, pick None if it doesn't use one");
+        }
+        if self.has_damage() && self.damage_expr().is_err() {
+            return Err("The damage couldn't be read, write it like 2d6 or 1d6 + 1d4 per Tech, or -- for none");
+        }
+        if matches!(parse_hits(&self.hits), Some((_, high)) if high > MAX_HITS) {
+            return Err("Custom chips can have at most 100 hits");
+        }
+        Ok(())
+    }
+
     /*
     pub(crate) fn damage_span(&self) -> Html {
         if self.damage == "--" {
@@ -147,6 +203,24 @@ impl BattleChip {
     }
     */
 
+    /// where the chip came from, shown with its description
+    pub(crate) fn provenance(&self) -> &str {
+        if self.custom {
+            CUSTOM_SOURCE
+        } else {
+            &self.source
+        }
+    }
+
+    /// marks chips a GM made up so they aren't mistaken for ones in the library
+    pub(crate) fn custom_tag(&self) -> Html {
+        if self.custom {
+            html!{<span class="customChipTag">{"Custom"}</span>}
+        } else {
+            html!{}
+        }
+    }
+
     pub(crate) fn gen_desc_top_row(&self) -> Html {
         match self.hits_span() {
             Some(hits) => {
@@ -191,10 +265,6 @@ impl BattleChip {
 
 }
 
-fn default_dmg_cell_float() -> UnsafeCell<f32> {
-    UnsafeCell::new(f32::NAN)
-}
-
-fn default_dmg_cell_int() -> UnsafeCell<i32> {
-    UnsafeCell::new(-1)
+fn default_dmg_cell() -> UnsafeCell<Option<Result<DamageStats, DiceError>>> {
+    UnsafeCell::new(None)
 }
\ No newline at end of file
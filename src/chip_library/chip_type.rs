@@ -1,6 +1,6 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::cmp::{Ord, Ordering};
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub(crate) enum ChipClass {
     Standard,
     Mega,
@@ -46,6 +46,34 @@ impl ChipClass {
         }
     }
 
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ChipClass::Standard => "Standard",
+            ChipClass::Mega => "Mega",
+            ChipClass::Giga => "Giga",
+            ChipClass::Dark => "Dark",
+            ChipClass::Support => "Support",
+        }
+    }
+
+    /// look up a class by name, ignoring case
+    pub(crate) fn from_name(name: &str) -> Option<ChipClass> {
+        let class = match name.to_ascii_lowercase().as_str() {
+            "standard" | "std" => ChipClass::Standard,
+            "mega" => ChipClass::Mega,
+            "giga" => ChipClass::Giga,
+            "dark" => ChipClass::Dark,
+            "support" => ChipClass::Support,
+            _ => return None,
+        };
+        Some(class)
+    }
+
+    /// the same class, unlike == which treats Standard and Support as equal for sorting
+    pub(crate) fn is(&self, other: ChipClass) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(&other)
+    }
+
     pub(crate) fn max_in_folder(&self) -> u8 {
         match self {
             ChipClass::Standard | ChipClass::Support => 3,
@@ -77,7 +105,7 @@ impl PartialEq for ChipClass {
 
 impl Eq for ChipClass {}
 
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord)]
 pub(crate) enum ChipType {
     Burst,
     Construct,
@@ -104,4 +132,21 @@ impl ChipType {
             ChipType::Trap => "TRP",
         }
     }
+
+    /// look up a type by its full name or its abbreviation, ignoring case
+    pub(crate) fn from_name(name: &str) -> Option<ChipType> {
+        let kind = match name.to_ascii_lowercase().as_str() {
+            "burst" | "bst" => ChipType::Burst,
+            "construct" | "cns" => ChipType::Construct,
+            "melee" | "mle" => ChipType::Melee,
+            "projectile" | "prj" => ChipType::Projectile,
+            "wave" | "wve" => ChipType::Wave,
+            "recovery" | "rcv" => ChipType::Recovery,
+            "summon" | "sum" => ChipType::Summon,
+            "support" | "spt" => ChipType::Support,
+            "trap" | "trp" => ChipType::Trap,
+            _ => return None,
+        };
+        Some(kind)
+    }
 }
\ No newline at end of file
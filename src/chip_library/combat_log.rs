@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// Where the combat log gets its timestamps from
+///
+/// the webapp uses `Date.now()`
+pub(crate) trait Clock {
+    /// milliseconds since the unix epoch
+    fn now(&self) -> f64;
+}
+
+/// Something that happened to a chip during a session
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind")]
+pub(crate) enum LogEvent {
+    FolderChipUsed{name: String},
+    FolderChipUnused{name: String},
+    /// a used chip went back to the pack, used is the new number of used copies
+    PackCopyUsed{name: String, used: u32},
+    PackCopyUnused{name: String, used: u32},
+    Rolled{name: String, hits: Vec<i64>, total: i64},
+    /// count is the number of chips that were marked unused
+    JackOut{count: u32},
+}
+
+impl LogEvent {
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            LogEvent::FolderChipUsed{name} => String::from(name) + " was used",
+            LogEvent::FolderChipUnused{name} => String::from(name) + " was marked unused",
+            LogEvent::PackCopyUsed{name, used} => {
+                String::from("A used copy of ") + name + " was returned to the pack (" + &used.to_string() + " used)"
+            }
+            LogEvent::PackCopyUnused{name, used} => {
+                String::from("A copy of ") + name + " in the pack was marked unused (" + &used.to_string() + " used)"
+            }
+            LogEvent::Rolled{name, hits, total} => {
+                let hits = hits.iter().map(|hit| hit.to_string()).collect::<Vec<String>>().join(", ");
+                String::from(name) + " rolled " + &total.to_string() + " [" + &hits + "]"
+            }
+            LogEvent::JackOut{count} => {
+                String::from("Jacked out, ") + &count.to_string() + " chips were marked unused"
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct LogEntry {
+    pub time: f64,
+    #[serde(flatten)]
+    pub event: LogEvent,
+}
+
+/// Every chip used this session, in order
+#[derive(Default)]
+pub(crate) struct CombatLog {
+    entries: Vec<LogEntry>,
+}
+
+impl CombatLog {
+    pub(crate) fn push(&mut self, time: f64, event: LogEvent) {
+        self.entries.push(LogEntry{time, event});
+    }
+
+    pub(crate) fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
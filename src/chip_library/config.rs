@@ -0,0 +1,240 @@
+use crate::chip_library::storage::DataStore;
+use group_protocol::Encoding;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+
+/// where a profile's own settings are kept
+pub(crate) const CONFIG_KEY: &str = "config";
+
+/// where the group servers found to only speak the protocol from before versioning are kept, shared by every profile
+pub(crate) const LEGACY_SERVERS_KEY: &str = "legacy_group_servers";
+
+const DEFAULT_GROUP_SERVER: &str = "wss://spartan364.hopto.org/manager/api/join/";
+const DEFAULT_LIBRARY_URL: &str = "https://spartan364.hopto.org/manager/chips.json";
+
+/// how long to wait between sending folder changes, in milliseconds
+const DEFAULT_FOLDER_UPDATE_DELAY: u32 = 1000;
+const MIN_FOLDER_UPDATE_DELAY: u32 = 100;
+const MAX_FOLDER_UPDATE_DELAY: u32 = 60_000;
+
+/// How the app talks to the group server
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ProtocolOptions {
+    /// folder changes are sent at most this often, in milliseconds
+    pub folder_update_delay: u32,
+    /// JSON is easier to read along with while debugging a server
+    pub encoding: Encoding,
+}
+
+/// The settings in use, everything that can override them already applied
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct AppConfig {
+    /// the websocket address of the group server
+    pub group_server: String,
+    /// where chips.json is downloaded from
+    pub library_url: String,
+    pub protocol: ProtocolOptions,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            group_server: DEFAULT_GROUP_SERVER.to_owned(),
+            library_url: DEFAULT_LIBRARY_URL.to_owned(),
+            protocol: ProtocolOptions {
+                folder_update_delay: DEFAULT_FOLDER_UPDATE_DELAY,
+                encoding: Encoding::Binary,
+            },
+        }
+    }
+}
+
+impl AppConfig {
+    /// these settings with whatever the overrides set replacing them
+    pub(crate) fn with(mut self, overrides: &ConfigOverrides) -> AppConfig {
+        if let Some(group_server) = &overrides.group_server {
+            self.group_server = group_server.clone();
+        }
+        if let Some(library_url) = &overrides.library_url {
+            self.library_url = library_url.clone();
+        }
+        if let Some(delay) = overrides.folder_update_delay {
+            self.protocol.folder_update_delay = delay;
+        }
+        if let Some(encoding) = overrides.protocol_encoding {
+            self.protocol.encoding = encoding;
+        }
+        self
+    }
+}
+
+/// Settings that replace the defaults, anything left as None is kept as it was
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub(crate) struct ConfigOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_server: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder_update_delay: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_encoding: Option<Encoding>,
+}
+
+impl ConfigOverrides {
+    /// read `?server=...&library=...&update_delay=...&protocol=json` from the page's address, anything that doesn't check out is ignored
+    pub(crate) fn from_query(query: &str) -> ConfigOverrides {
+        let mut overrides = ConfigOverrides::default();
+        for pair in query.trim_start_matches('?').split('&') {
+            let (key, value) = match pair.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let value = percent_decode_str(value).decode_utf8_lossy().trim().to_owned();
+            let mut single = ConfigOverrides::default();
+            match key {
+                "server" => single.group_server = Some(value),
+                "library" => single.library_url = Some(value),
+                "update_delay" => single.folder_update_delay = value.parse().ok(),
+                "protocol" => single.protocol_encoding = match value.as_str() {
+                    "json" => Some(Encoding::Json),
+                    "binary" => Some(Encoding::Binary),
+                    _ => None,
+                },
+                _ => continue,
+            }
+            if single.check().is_ok() {
+                overrides = single.or(overrides);
+            }
+        }
+        overrides
+    }
+
+    /// read what the app was started with, None if it isn't valid
+    pub(crate) fn from_json(text: &str) -> Option<ConfigOverrides> {
+        let overrides = serde_json::from_str::<ConfigOverrides>(text).ok()?;
+        overrides.check().ok()?;
+        Some(overrides)
+    }
+
+    /// these overrides, with other filling in anything they leave alone
+    fn or(self, other: ConfigOverrides) -> ConfigOverrides {
+        ConfigOverrides {
+            group_server: self.group_server.or(other.group_server),
+            library_url: self.library_url.or(other.library_url),
+            folder_update_delay: self.folder_update_delay.or(other.folder_update_delay),
+            protocol_encoding: self.protocol_encoding.or(other.protocol_encoding),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == ConfigOverrides::default()
+    }
+
+    /// make sure the urls at least look like urls and the delay is sensible
+    pub(crate) fn check(&self) -> Result<(), &'static str> {
+        if let Some(server) = &self.group_server {
+            if !(server.starts_with("ws://") || server.starts_with("wss://")) {
+                return Err("The group server needs to start with ws:// or wss://");
+            }
+        }
+        if let Some(library) = &self.library_url {
+            if library.is_empty() || library.contains(char::is_whitespace) {
+                return Err("The library url can't be empty or have spaces in it");
+            }
+        }
+        if let Some(delay) = self.folder_update_delay {
+            if !(MIN_FOLDER_UPDATE_DELAY..=MAX_FOLDER_UPDATE_DELAY).contains(&delay) {
+                return Err("The folder update delay needs to be between 100 and 60000 milliseconds");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// true if the group server is known to only speak the protocol from before versioning
+///
+/// the default server hasn't been updated yet, any other is found out about the first time it's joined
+pub(crate) fn is_legacy_server(storage: &dyn DataStore, server: &str) -> bool {
+    server == DEFAULT_GROUP_SERVER || legacy_servers(storage).iter().any(|other| other == server)
+}
+
+/// remember that a group server only speaks the protocol from before versioning, so it's spoken to that way straight off
+pub(crate) fn mark_legacy_server(storage: &dyn DataStore, server: &str) -> Result<(), &'static str> {
+    let mut servers = legacy_servers(storage);
+    if server == DEFAULT_GROUP_SERVER || servers.iter().any(|other| other == server) {
+        return Ok(());
+    }
+    servers.push(server.to_owned());
+    let text = serde_json::to_string(&servers).map_err(|_| "Failed to serialize group servers, inform Major")?;
+    storage.set_item(LEGACY_SERVERS_KEY, &text)
+}
+
+fn legacy_servers(storage: &dyn DataStore) -> Vec<String> {
+    storage.get_item(LEGACY_SERVERS_KEY)
+        .and_then(|text| serde_json::from_str::<Vec<String>>(&text).ok())
+        .unwrap_or_default()
+}
+
+/// The settings that come from outside of the profile
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct RuntimeConfig {
+    /// what the app was started with, a profile's own settings replace these
+    pub base: ConfigOverrides,
+    /// from the page's address, these replace everything else but aren't saved
+    pub query: ConfigOverrides,
+}
+
+impl RuntimeConfig {
+    /// the settings in use with a profile's own settings
+    pub(crate) fn resolve(&self, profile: &ConfigOverrides) -> AppConfig {
+        AppConfig::default().with(&self.base).with(profile).with(&self.query)
+    }
+
+    /// the settings a profile falls back on when it leaves something unset
+    pub(crate) fn defaults(&self) -> AppConfig {
+        AppConfig::default().with(&self.base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_apply_in_order() {
+        let runtime = RuntimeConfig {
+            base: ConfigOverrides::from_json(r#"{"GroupServer": "wss://example.com/join", "FolderUpdateDelay": 500}"#).unwrap(),
+            query: ConfigOverrides::from_query("?library=http%3A%2F%2Flocalhost%3A8080%2Fchips.json&update_delay=5&protocol=json&other=1"),
+        };
+        let profile = ConfigOverrides{folder_update_delay: Some(2000), ..ConfigOverrides::default()};
+        let config = runtime.resolve(&profile);
+        assert_eq!(config.group_server, "wss://example.com/join");
+        assert_eq!(config.library_url, "http://localhost:8080/chips.json");
+        // 5ms is too short, so the query's delay is ignored
+        assert_eq!(config.protocol.folder_update_delay, 2000);
+        assert_eq!(config.protocol.encoding, Encoding::Json);
+        assert_eq!(AppConfig::default().protocol.encoding, Encoding::Binary);
+    }
+
+    #[test]
+    fn bad_settings_are_rejected() {
+        assert!(ConfigOverrides{group_server: Some(String::from("https://example.com")), ..ConfigOverrides::default()}.check().is_err());
+        assert!(ConfigOverrides{library_url: Some(String::new()), ..ConfigOverrides::default()}.check().is_err());
+        assert!(ConfigOverrides::from_json(r#"{"FolderUpdateDelay": 0}"#).is_none());
+        assert!(ConfigOverrides::from_query("server=http://example.com").is_empty());
+        assert_eq!(serde_json::to_string(&ConfigOverrides::default()).unwrap(), "{}");
+    }
+
+    #[test]
+    fn old_servers_are_remembered() {
+        let storage = crate::chip_library::storage::MemoryStore::default();
+        assert!(is_legacy_server(&storage, DEFAULT_GROUP_SERVER));
+        assert!(!is_legacy_server(&storage, "ws://localhost:8080"));
+        mark_legacy_server(&storage, "ws://localhost:8080").unwrap();
+        mark_legacy_server(&storage, "ws://localhost:8080").unwrap();
+        assert!(is_legacy_server(&storage, "ws://localhost:8080"));
+        assert_eq!(legacy_servers(&storage).len(), 1);
+    }
+}
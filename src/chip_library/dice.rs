@@ -0,0 +1,606 @@
+use crate::chip_library::skills::Skills;
+use crate::chip_library::roller::{Rng, MAX_HITS};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// the most dice that can be rolled in a single `NdM` term
+const MAX_DICE: u32 = 100;
+
+/// the largest die allowed
+const MAX_SIDES: u32 = 1000;
+
+/// the most distinct outcomes a distribution may have before giving up
+const MAX_OUTCOMES: usize = 100_000;
+
+/// the most pairs of outcomes combined in one step, checked before any work is done so a bad chip can't freeze the page
+const MAX_PAIRS: usize = 10_000_000;
+
+/// the skill rank used for "per Skill" damage when sorting and showing stats
+pub(crate) const DEFAULT_SKILL_RANK: i64 = 1;
+
+/// A parsed damage expression, e.g. `2d6+3` or `1d6 per Tech`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum DiceExpr {
+    Constant(i64),
+    Dice{count: u32, sides: u32},
+    Add(Box<DiceExpr>, Box<DiceExpr>),
+    Sub(Box<DiceExpr>, Box<DiceExpr>),
+    Mul(Box<DiceExpr>, Box<DiceExpr>),
+    /// integer division, rounding down
+    Div(Box<DiceExpr>, Box<DiceExpr>),
+    /// multiplied by the rank of a skill
+    PerSkill(Box<DiceExpr>, Skills),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum DiceError {
+    Empty,
+    UnexpectedChar{pos: usize, found: char},
+    UnexpectedToken{pos: usize, found: String},
+    UnexpectedEnd,
+    UnknownSkill(String),
+    NumberTooLarge(usize),
+    ZeroSides,
+    TooManyDice,
+    DivideByZero,
+    TooManyOutcomes,
+    Overflow,
+    TooManyHits,
+}
+
+impl fmt::Display for DiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiceError::Empty => write!(f, "no damage given"),
+            DiceError::UnexpectedChar{pos, found} => write!(f, "unexpected '{}' at position {}", found, pos + 1),
+            DiceError::UnexpectedToken{pos, found} => write!(f, "unexpected \"{}\" at position {}", found, pos + 1),
+            DiceError::UnexpectedEnd => write!(f, "damage ended unexpectedly"),
+            DiceError::UnknownSkill(name) => write!(f, "\"{}\" is not a skill", name),
+            DiceError::NumberTooLarge(pos) => write!(f, "number at position {} is too large", pos + 1),
+            DiceError::ZeroSides => write!(f, "dice must have at least one side"),
+            DiceError::TooManyDice => write!(f, "at most {} dice of up to {} sides can be rolled at once", MAX_DICE, MAX_SIDES),
+            DiceError::DivideByZero => write!(f, "damage can be divided by zero"),
+            DiceError::TooManyOutcomes => write!(f, "damage has too many possible outcomes"),
+            DiceError::Overflow => write!(f, "damage is too large to work out"),
+            DiceError::TooManyHits => write!(f, "at most {} hits can be rolled at once", MAX_HITS),
+        }
+    }
+}
+
+/// The smallest, largest, and average result of a damage expression
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct DamageStats {
+    pub min: i64,
+    pub max: i64,
+    pub avg: f64,
+}
+
+impl DamageStats {
+    /// used for chips that don't do damage
+    pub(crate) const NONE: DamageStats = DamageStats{min: 0, max: 0, avg: 0.0};
+}
+
+/// A single die that was rolled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct DieRoll {
+    pub sides: u32,
+    pub value: u32,
+}
+
+/// Every die rolled for one evaluation of a damage expression, and the final result
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RollResult {
+    pub dice: Vec<DieRoll>,
+    pub total: i64,
+}
+
+/// The probability of every possible result of a damage expression
+#[derive(Clone, Debug)]
+pub(crate) struct Distribution {
+    /// the value of the first entry in probs
+    offset: i64,
+    probs: Vec<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(u32),
+    D,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LParen,
+    RParen,
+    Word(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(num) => write!(f, "{}", num),
+            Token::D => f.write_str("d"),
+            Token::Plus => f.write_str("+"),
+            Token::Minus => f.write_str("-"),
+            Token::Star => f.write_str("*"),
+            Token::Slash => f.write_str("/"),
+            Token::LParen => f.write_str("("),
+            Token::RParen => f.write_str(")"),
+            Token::Word(word) => f.write_str(word),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, DiceError> {
+    let chars = text.char_indices().collect::<Vec<(usize, char)>>();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (pos, ch) = chars[idx];
+        if ch.is_whitespace() {
+            idx += 1;
+            continue;
+        }
+        if ch.is_ascii_digit() {
+            let mut val: u32 = 0;
+            while idx < chars.len() && chars[idx].1.is_ascii_digit() {
+                let digit = chars[idx].1 as u32 - '0' as u32;
+                val = val.checked_mul(10).and_then(|val| val.checked_add(digit)).ok_or(DiceError::NumberTooLarge(pos))?;
+                idx += 1;
+            }
+            tokens.push((pos, Token::Num(val)));
+            continue;
+        }
+
+        // a 'd' followed by a number is a die, not the start of a word
+        let next_is_digit = chars.get(idx + 1).map_or(false, |(_, next)| next.is_ascii_digit());
+        if (ch == 'd' || ch == 'D') && next_is_digit {
+            tokens.push((pos, Token::D));
+            idx += 1;
+            continue;
+        }
+
+        if ch.is_alphabetic() {
+            let mut word = String::new();
+            while idx < chars.len() && chars[idx].1.is_alphabetic() {
+                word.push(chars[idx].1);
+                idx += 1;
+            }
+            tokens.push((pos, Token::Word(word)));
+            continue;
+        }
+
+        let token = match ch {
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' | '×' => Token::Star,
+            '/' => Token::Slash,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            _ => return Err(DiceError::UnexpectedChar{pos, found: ch}),
+        };
+        tokens.push((pos, token));
+        idx += 1;
+    }
+    Ok(tokens)
+}
+
+/// recursive descent parser for damage expressions
+///
+/// expr   := term (('+' | '-') term)*
+/// term   := factor (('*' | '/') factor)*
+/// factor := unary ("per" skill)?
+/// unary  := '-' unary | atom
+/// atom   := number | number? 'd' number | '(' expr ')'
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    idx: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.idx).cloned();
+        self.idx += 1;
+        token
+    }
+
+    fn unexpected(&self) -> DiceError {
+        match self.tokens.get(self.idx) {
+            Some((pos, token)) => DiceError::UnexpectedToken{pos: *pos, found: token.to_string()},
+            None => DiceError::UnexpectedEnd,
+        }
+    }
+
+    fn expr(&mut self) -> Result<DiceExpr, DiceError> {
+        let mut lhs = self.term()?;
+        loop {
+            match self.peek() {
+                Some(Token::Plus) => {
+                    self.idx += 1;
+                    lhs = DiceExpr::Add(Box::new(lhs), Box::new(self.term()?));
+                }
+                Some(Token::Minus) => {
+                    self.idx += 1;
+                    lhs = DiceExpr::Sub(Box::new(lhs), Box::new(self.term()?));
+                }
+                _ => return Ok(lhs),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<DiceExpr, DiceError> {
+        let mut lhs = self.factor()?;
+        loop {
+            match self.peek() {
+                Some(Token::Star) => {
+                    self.idx += 1;
+                    lhs = DiceExpr::Mul(Box::new(lhs), Box::new(self.factor()?));
+                }
+                Some(Token::Slash) => {
+                    self.idx += 1;
+                    lhs = DiceExpr::Div(Box::new(lhs), Box::new(self.factor()?));
+                }
+                _ => return Ok(lhs),
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Result<DiceExpr, DiceError> {
+        let val = self.unary()?;
+        let is_per = match self.peek() {
+            Some(Token::Word(word)) => word.eq_ignore_ascii_case("per"),
+            _ => false,
+        };
+        if !is_per {
+            return Ok(val);
+        }
+        self.idx += 1;
+        match self.next() {
+            Some((_, Token::Word(word))) => {
+                let skill = Skills::from_name(&word).ok_or(DiceError::UnknownSkill(word))?;
+                Ok(DiceExpr::PerSkill(Box::new(val), skill))
+            }
+            Some(_) => {
+                self.idx -= 1;
+                Err(self.unexpected())
+            }
+            None => Err(DiceError::UnexpectedEnd),
+        }
+    }
+
+    fn unary(&mut self) -> Result<DiceExpr, DiceError> {
+        if let Some(Token::Minus) = self.peek() {
+            self.idx += 1;
+            let val = self.unary()?;
+            return Ok(DiceExpr::Sub(Box::new(DiceExpr::Constant(0)), Box::new(val)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<DiceExpr, DiceError> {
+        match self.peek() {
+            Some(Token::Num(num)) => {
+                let num = *num;
+                self.idx += 1;
+                if let Some(Token::D) = self.peek() {
+                    self.idx += 1;
+                    let sides = self.die_sides()?;
+                    return dice(num, sides);
+                }
+                Ok(DiceExpr::Constant(num as i64))
+            }
+            Some(Token::D) => {
+                self.idx += 1;
+                let sides = self.die_sides()?;
+                dice(1, sides)
+            }
+            Some(Token::LParen) => {
+                self.idx += 1;
+                let val = self.expr()?;
+                match self.peek() {
+                    Some(Token::RParen) => {
+                        self.idx += 1;
+                        Ok(val)
+                    }
+                    _ => Err(self.unexpected()),
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn die_sides(&mut self) -> Result<u32, DiceError> {
+        match self.peek() {
+            Some(Token::Num(sides)) => {
+                let sides = *sides;
+                self.idx += 1;
+                Ok(sides)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+fn dice(count: u32, sides: u32) -> Result<DiceExpr, DiceError> {
+    if sides == 0 {
+        return Err(DiceError::ZeroSides);
+    }
+    if count > MAX_DICE || sides > MAX_SIDES {
+        return Err(DiceError::TooManyDice);
+    }
+    Ok(DiceExpr::Dice{count, sides})
+}
+
+impl DiceExpr {
+
+    pub(crate) fn parse(text: &str) -> Result<DiceExpr, DiceError> {
+        let tokens = tokenize(text)?;
+        if tokens.is_empty() {
+            return Err(DiceError::Empty);
+        }
+        let mut parser = Parser{tokens, idx: 0};
+        let expr = parser.expr()?;
+        if parser.idx < parser.tokens.len() {
+            return Err(parser.unexpected());
+        }
+        Ok(expr)
+    }
+
+    /// every possible result and how likely it is, "per Skill" terms use the given rank
+    pub(crate) fn distribution(&self, skill_rank: i64) -> Result<Distribution, DiceError> {
+        match self {
+            DiceExpr::Constant(val) => Ok(Distribution::constant(*val)),
+            DiceExpr::Dice{count, sides} => Distribution::dice(*count, *sides),
+            DiceExpr::Add(lhs, rhs) => lhs.distribution(skill_rank)?.add(&rhs.distribution(skill_rank)?),
+            DiceExpr::Sub(lhs, rhs) => lhs.distribution(skill_rank)?.add(&rhs.distribution(skill_rank)?.negate()?),
+            DiceExpr::Mul(lhs, rhs) => {
+                let lhs = lhs.distribution(skill_rank)?;
+                let rhs = rhs.distribution(skill_rank)?;
+                lhs.combine(&rhs, i64::checked_mul)
+            }
+            DiceExpr::Div(lhs, rhs) => {
+                let lhs = lhs.distribution(skill_rank)?;
+                let rhs = rhs.distribution(skill_rank)?;
+                if rhs.probability(0) > 0.0 {
+                    return Err(DiceError::DivideByZero);
+                }
+                lhs.combine(&rhs, floor_div)
+            }
+            DiceExpr::PerSkill(val, _) => {
+                let val = val.distribution(skill_rank)?;
+                val.combine(&Distribution::constant(skill_rank), i64::checked_mul)
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self, skill_rank: i64) -> Result<DamageStats, DiceError> {
+        Ok(self.distribution(skill_rank)?.stats())
+    }
+
+    /// roll every die in the expression once, "per Skill" terms use the given rank
+    pub(crate) fn roll(&self, rng: &mut Rng, skill_rank: i64) -> Result<RollResult, DiceError> {
+        let mut dice = Vec::new();
+        let total = self.roll_into(rng, skill_rank, &mut dice)?;
+        Ok(RollResult{dice, total})
+    }
+
+    fn roll_into(&self, rng: &mut Rng, skill_rank: i64, dice: &mut Vec<DieRoll>) -> Result<i64, DiceError> {
+        let total = match self {
+            DiceExpr::Constant(val) => Some(*val),
+            DiceExpr::Dice{count, sides} => {
+                let mut total = 0;
+                for _ in 0..*count {
+                    let value = rng.range(1, *sides);
+                    dice.push(DieRoll{sides: *sides, value});
+                    total += value as i64;
+                }
+                Some(total)
+            }
+            DiceExpr::Add(lhs, rhs) => lhs.roll_into(rng, skill_rank, dice)?.checked_add(rhs.roll_into(rng, skill_rank, dice)?),
+            DiceExpr::Sub(lhs, rhs) => lhs.roll_into(rng, skill_rank, dice)?.checked_sub(rhs.roll_into(rng, skill_rank, dice)?),
+            DiceExpr::Mul(lhs, rhs) => lhs.roll_into(rng, skill_rank, dice)?.checked_mul(rhs.roll_into(rng, skill_rank, dice)?),
+            DiceExpr::Div(lhs, rhs) => {
+                let lhs = lhs.roll_into(rng, skill_rank, dice)?;
+                let rhs = rhs.roll_into(rng, skill_rank, dice)?;
+                if rhs == 0 {
+                    return Err(DiceError::DivideByZero);
+                }
+                floor_div(lhs, rhs)
+            }
+            DiceExpr::PerSkill(val, _) => val.roll_into(rng, skill_rank, dice)?.checked_mul(skill_rank),
+        };
+        total.ok_or(DiceError::Overflow)
+    }
+}
+
+/// None if rhs is zero or the result doesn't fit
+fn floor_div(lhs: i64, rhs: i64) -> Option<i64> {
+    let quot = lhs.checked_div(rhs)?;
+    if (lhs % rhs != 0) && ((lhs < 0) != (rhs < 0)) {
+        Some(quot - 1)
+    } else {
+        Some(quot)
+    }
+}
+
+impl Distribution {
+    fn constant(val: i64) -> Distribution {
+        Distribution{offset: val, probs: vec![1.0]}
+    }
+
+    fn dice(count: u32, sides: u32) -> Result<Distribution, DiceError> {
+        if (count as usize).saturating_mul(sides as usize) > MAX_OUTCOMES {
+            return Err(DiceError::TooManyOutcomes);
+        }
+        let mut total = Distribution::constant(0);
+        for _ in 0..count {
+            total = total.add_die(sides);
+        }
+        Ok(total)
+    }
+
+    /// add one die, each new probability is the average of the sides window below it so this stays linear
+    fn add_die(&self, sides: u32) -> Distribution {
+        let sides = sides as usize;
+        let mut probs = Vec::with_capacity(self.probs.len() + sides - 1);
+        let mut window = 0.0;
+        for idx in 0..self.probs.len() + sides - 1 {
+            window += self.probs.get(idx).copied().unwrap_or(0.0);
+            if idx >= sides {
+                window -= self.probs[idx - sides];
+            }
+            probs.push(window / sides as f64);
+        }
+        Distribution{offset: self.offset + 1, probs}
+    }
+
+    fn add(&self, other: &Distribution) -> Result<Distribution, DiceError> {
+        let len = self.probs.len() + other.probs.len() - 1;
+        if len > MAX_OUTCOMES || self.probs.len().saturating_mul(other.probs.len()) > MAX_PAIRS {
+            return Err(DiceError::TooManyOutcomes);
+        }
+        let offset = self.offset.checked_add(other.offset).ok_or(DiceError::Overflow)?;
+        let mut probs = vec![0.0; len];
+        for (i, a) in self.probs.iter().enumerate() {
+            if *a == 0.0 {
+                continue;
+            }
+            for (j, b) in other.probs.iter().enumerate() {
+                probs[i + j] += a * b;
+            }
+        }
+        Ok(Distribution{offset, probs})
+    }
+
+    fn negate(&self) -> Result<Distribution, DiceError> {
+        let mut probs = self.probs.clone();
+        probs.reverse();
+        let max = self.offset.checked_add(self.probs.len() as i64 - 1).ok_or(DiceError::Overflow)?;
+        let offset = max.checked_neg().ok_or(DiceError::Overflow)?;
+        Ok(Distribution{offset, probs})
+    }
+
+    /// combine every pair of outcomes with an arbitrary operation, which gives None when the result doesn't fit
+    fn combine<F: Fn(i64, i64) -> Option<i64>>(&self, other: &Distribution, op: F) -> Result<Distribution, DiceError> {
+        if self.probs.len().saturating_mul(other.probs.len()) > MAX_PAIRS {
+            return Err(DiceError::TooManyOutcomes);
+        }
+        let mut outcomes: BTreeMap<i64, f64> = BTreeMap::new();
+        for (lhs, a) in self.iter() {
+            for (rhs, b) in other.iter() {
+                let val = op(lhs, rhs).ok_or(DiceError::Overflow)?;
+                *outcomes.entry(val).or_insert(0.0) += a * b;
+            }
+        }
+        // every distribution has at least one outcome
+        let min = outcomes.keys().next().copied().unwrap_or(0);
+        let max = outcomes.keys().next_back().copied().unwrap_or(0);
+        let len = max.checked_sub(min).map_or(usize::MAX, |span| (span as u64).saturating_add(1) as usize);
+        if len > MAX_OUTCOMES {
+            return Err(DiceError::TooManyOutcomes);
+        }
+        let mut probs = vec![0.0; len];
+        for (val, prob) in outcomes {
+            probs[(val - min) as usize] = prob;
+        }
+        Ok(Distribution{offset: min, probs})
+    }
+
+    /// each possible value with its probability, skipping impossible values
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        let offset = self.offset;
+        self.probs.iter().enumerate().filter(|(_, prob)| **prob > 0.0).map(move |(idx, prob)| (offset + idx as i64, *prob))
+    }
+
+    pub(crate) fn probability(&self, val: i64) -> f64 {
+        if val < self.offset {
+            return 0.0;
+        }
+        self.probs.get((val - self.offset) as usize).copied().unwrap_or(0.0)
+    }
+
+    pub(crate) fn stats(&self) -> DamageStats {
+        let mut values = self.iter();
+        let (min, _) = match values.next() {
+            Some(first) => first,
+            None => return DamageStats::NONE,
+        };
+        let max = self.iter().last().map_or(min, |(val, _)| val);
+        let avg = self.iter().map(|(val, prob)| val as f64 * prob).sum();
+        DamageStats{min, max, avg}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_stats(text: &str, min: i64, max: i64, avg: f64) {
+        let stats = DiceExpr::parse(text).unwrap().stats(DEFAULT_SKILL_RANK).unwrap();
+        assert_eq!((stats.min, stats.max), (min, max), "{}", text);
+        assert!((stats.avg - avg).abs() < 1e-9, "{} averaged {}", text, stats.avg);
+    }
+
+    #[test]
+    fn plain_dice() {
+        assert_stats("2d6", 2, 12, 7.0);
+        assert_stats("d8", 1, 8, 4.5);
+        assert_stats("5", 5, 5, 5.0);
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_stats("2d6+3", 5, 15, 10.0);
+        assert_stats("1d8 + 1d4", 2, 12, 7.0);
+        assert_stats("(1d6-1)*2", 0, 10, 5.0);
+        assert_stats("2d2/2", 1, 2, 1.25);
+        assert_stats("-1d4+5", 1, 4, 2.5);
+    }
+
+    #[test]
+    fn per_skill() {
+        let expr = DiceExpr::parse("1d6 per Tech").unwrap();
+        assert_eq!(expr, DiceExpr::PerSkill(Box::new(DiceExpr::Dice{count: 1, sides: 6}), Skills::Tech));
+        let stats = expr.stats(3).unwrap();
+        assert_eq!((stats.min, stats.max), (3, 18));
+        assert!(DiceExpr::parse("2d6 + 1d4 PER tch").is_ok());
+    }
+
+    #[test]
+    fn distribution_sums_to_one() {
+        let dist = DiceExpr::parse("3d6").unwrap().distribution(DEFAULT_SKILL_RANK).unwrap();
+        let total: f64 = dist.iter().map(|(_, prob)| prob).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!((dist.probability(3) - 1.0 / 216.0).abs() < 1e-12);
+        assert_eq!(dist.probability(2), 0.0);
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert_eq!(DiceExpr::parse(""), Err(DiceError::Empty));
+        assert_eq!(DiceExpr::parse("2d6 +"), Err(DiceError::UnexpectedEnd));
+        assert_eq!(DiceExpr::parse("2d6 + ?"), Err(DiceError::UnexpectedChar{pos: 6, found: '?'}));
+        assert_eq!(DiceExpr::parse("1d6 per Luck"), Err(DiceError::UnknownSkill("Luck".to_owned())));
+        assert_eq!(DiceExpr::parse("2d0"), Err(DiceError::ZeroSides));
+        assert!(DiceExpr::parse("2d").is_err());
+        assert!(DiceExpr::parse("2d6 3").is_err());
+        assert_eq!(DiceExpr::parse("1d6/(1d2-1)").unwrap().stats(DEFAULT_SKILL_RANK), Err(DiceError::DivideByZero));
+    }
+
+    #[test]
+    fn huge_damage_is_turned_down_quickly() {
+        let most = DiceExpr::parse("100d1000").unwrap().stats(DEFAULT_SKILL_RANK).unwrap();
+        assert_eq!((most.min, most.max), (100, 100_000));
+        assert!((most.avg - 50_050.0).abs() < 1e-3);
+        assert_eq!(DiceExpr::parse("100d1000*100d1000").unwrap().stats(DEFAULT_SKILL_RANK), Err(DiceError::TooManyOutcomes));
+        assert_eq!(DiceExpr::parse("100d1000+100d1000").unwrap().stats(DEFAULT_SKILL_RANK), Err(DiceError::TooManyOutcomes));
+
+        let overflow = DiceExpr::parse("4000000000*4000000000*4000000000").unwrap();
+        assert_eq!(overflow.stats(DEFAULT_SKILL_RANK), Err(DiceError::Overflow));
+        assert_eq!(overflow.roll(&mut Rng::new(1), DEFAULT_SKILL_RANK), Err(DiceError::Overflow));
+    }
+}
@@ -1,6 +1,6 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Eq, PartialEq, Clone, Copy, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord)]
 pub(crate) enum Elements {
     Fire,
     Aqua,
@@ -34,6 +34,26 @@ const NULL_URL: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAA4AAAAOCA
 */
 
 impl Elements {
+    /// look up an element by name, ignoring case
+    pub(crate) fn from_name(name: &str) -> Option<Elements> {
+        let element = match name.to_ascii_lowercase().as_str() {
+            "fire" => Elements::Fire,
+            "aqua" => Elements::Aqua,
+            "elec" => Elements::Elec,
+            "wood" => Elements::Wood,
+            "wind" => Elements::Wind,
+            "sword" => Elements::Sword,
+            "break" => Elements::Break,
+            "cursor" => Elements::Cursor,
+            "recovery" | "recov" => Elements::Recovery,
+            "invis" => Elements::Invis,
+            "object" => Elements::Object,
+            "null" => Elements::Null,
+            _ => return None,
+        };
+        Some(element)
+    }
+
     /*
     pub(crate) fn to_img_url(&self) -> &'static str {
         match self {
@@ -0,0 +1,271 @@
+use crate::chip_library::{
+    battle_chip::BattleChip,
+    chip_type::{ChipClass, ChipType},
+    elements::Elements,
+    ranges::Ranges,
+    skills::Skills,
+};
+
+const ELEMENTS: [Elements; 12] = [
+    Elements::Fire, Elements::Aqua, Elements::Elec, Elements::Wood, Elements::Wind, Elements::Sword,
+    Elements::Break, Elements::Cursor, Elements::Recovery, Elements::Invis, Elements::Object, Elements::Null,
+];
+
+const CLASSES: [ChipClass; 5] = [ChipClass::Standard, ChipClass::Mega, ChipClass::Giga, ChipClass::Dark, ChipClass::Support];
+
+const TYPES: [ChipType; 9] = [
+    ChipType::Burst, ChipType::Construct, ChipType::Melee, ChipType::Projectile, ChipType::Wave,
+    ChipType::Recovery, ChipType::Summon, ChipType::Support, ChipType::Trap,
+];
+
+/// Varies is left out, it's what chips with more than one skill sort as rather than a skill of its own
+const SKILLS: [Skills; 10] = [
+    Skills::Perception, Skills::Info, Skills::Tech, Skills::Strength, Skills::Agility,
+    Skills::Endurance, Skills::Charm, Skills::Valor, Skills::Affinity, Skills::None,
+];
+
+const RANGES: [Ranges; 5] = [Ranges::Itself, Ranges::Close, Ranges::Near, Ranges::Far, Ranges::Varies];
+
+/// One toggle in the filter panel
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Facet {
+    Element(Elements),
+    Class(ChipClass),
+    Kind(ChipType),
+    Skill(Skills),
+    Range(Ranges),
+}
+
+// ChipClass's == treats Standard and Support as the same for sorting, they're different facets
+impl PartialEq for Facet {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Facet::Element(lhs), Facet::Element(rhs)) => lhs == rhs,
+            (Facet::Class(lhs), Facet::Class(rhs)) => lhs.is(*rhs),
+            (Facet::Kind(lhs), Facet::Kind(rhs)) => lhs == rhs,
+            (Facet::Skill(lhs), Facet::Skill(rhs)) => lhs == rhs,
+            (Facet::Range(lhs), Facet::Range(rhs)) => lhs == rhs,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum FacetGroup {
+    Element,
+    Class,
+    Kind,
+    Skill,
+    Range,
+}
+
+impl FacetGroup {
+    pub(crate) const ALL: [FacetGroup; 5] = [FacetGroup::Element, FacetGroup::Class, FacetGroup::Kind, FacetGroup::Skill, FacetGroup::Range];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            FacetGroup::Element => "Element",
+            FacetGroup::Class => "Class",
+            FacetGroup::Kind => "Type",
+            FacetGroup::Skill => "Skill",
+            FacetGroup::Range => "Range",
+        }
+    }
+
+    pub(crate) fn facets(&self) -> Vec<Facet> {
+        match self {
+            FacetGroup::Element => ELEMENTS.iter().map(|element| Facet::Element(*element)).collect(),
+            FacetGroup::Class => CLASSES.iter().map(|class| Facet::Class(*class)).collect(),
+            FacetGroup::Kind => TYPES.iter().map(|kind| Facet::Kind(*kind)).collect(),
+            FacetGroup::Skill => SKILLS.iter().map(|skill| Facet::Skill(*skill)).collect(),
+            FacetGroup::Range => RANGES.iter().map(|range| Facet::Range(*range)).collect(),
+        }
+    }
+}
+
+impl Facet {
+    pub(crate) fn group(&self) -> FacetGroup {
+        match self {
+            Facet::Element(_) => FacetGroup::Element,
+            Facet::Class(_) => FacetGroup::Class,
+            Facet::Kind(_) => FacetGroup::Kind,
+            Facet::Skill(_) => FacetGroup::Skill,
+            Facet::Range(_) => FacetGroup::Range,
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Facet::Element(element) => match element {
+                Elements::Fire => "Fire",
+                Elements::Aqua => "Aqua",
+                Elements::Elec => "Elec",
+                Elements::Wood => "Wood",
+                Elements::Wind => "Wind",
+                Elements::Sword => "Sword",
+                Elements::Break => "Break",
+                Elements::Cursor => "Cursor",
+                Elements::Recovery => "Recovery",
+                Elements::Invis => "Invis",
+                Elements::Object => "Object",
+                Elements::Null => "Null",
+            },
+            Facet::Class(class) => class.as_str(),
+            Facet::Kind(kind) => match kind {
+                ChipType::Burst => "Burst",
+                ChipType::Construct => "Construct",
+                ChipType::Melee => "Melee",
+                ChipType::Projectile => "Projectile",
+                ChipType::Wave => "Wave",
+                ChipType::Recovery => "Recovery",
+                ChipType::Summon => "Summon",
+                ChipType::Support => "Support",
+                ChipType::Trap => "Trap",
+            },
+            Facet::Skill(skill) => match skill {
+                Skills::Perception => "Perception",
+                Skills::Info => "Info",
+                Skills::Tech => "Tech",
+                Skills::Strength => "Strength",
+                Skills::Agility => "Agility",
+                Skills::Endurance => "Endurance",
+                Skills::Charm => "Charm",
+                Skills::Valor => "Valor",
+                Skills::Affinity => "Affinity",
+                Skills::None => "None",
+                Skills::Varies => "Varies",
+            },
+            Facet::Range(range) => match range {
+                Ranges::Itself => "Self",
+                Ranges::Close => "Close",
+                Ranges::Near => "Near",
+                Ranges::Far => "Far",
+                Ranges::Varies => "Varies",
+            },
+        }
+    }
+
+    pub(crate) fn matches(&self, chip: &BattleChip) -> bool {
+        match self {
+            Facet::Element(element) => chip.element.contains(element),
+            Facet::Class(class) => chip.class.is(*class),
+            Facet::Kind(kind) => chip.kind == *kind,
+            Facet::Skill(skill) => chip.skills.contains(skill),
+            Facet::Range(range) => chip.range == *range,
+        }
+    }
+}
+
+/// How many chips a facet would show, given everything else that's selected
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FacetCount {
+    pub facet: Facet,
+    pub count: usize,
+    pub selected: bool,
+}
+
+/// The facets toggled on, facets in the same group are OR'd and groups are AND'd
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct FacetFilter {
+    selected: Vec<Facet>,
+}
+
+impl FacetFilter {
+    pub(crate) fn toggle(&mut self, facet: Facet) {
+        match self.selected.iter().position(|selected| *selected == facet) {
+            Some(idx) => {
+                self.selected.remove(idx);
+            }
+            None => self.selected.push(facet),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.selected.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.selected.is_empty()
+    }
+
+    pub(crate) fn is_selected(&self, facet: Facet) -> bool {
+        self.selected.contains(&facet)
+    }
+
+    pub(crate) fn matches(&self, chip: &BattleChip) -> bool {
+        FacetGroup::ALL.iter().all(|group| self.group_matches(*group, chip))
+    }
+
+    fn group_matches(&self, group: FacetGroup, chip: &BattleChip) -> bool {
+        let mut in_group = self.selected.iter().filter(|facet| facet.group() == group).peekable();
+        in_group.peek().is_none() || in_group.any(|facet| facet.matches(chip))
+    }
+
+    /// counts for every facet, each as if it were the only one selected in its group
+    pub(crate) fn counts(&self, chips: &[&BattleChip]) -> Vec<FacetCount> {
+        let mut to_ret = Vec::new();
+        for group in FacetGroup::ALL.iter() {
+            // chips that pass the other groups
+            let others = chips.iter().filter(|chip| {
+                FacetGroup::ALL.iter().filter(|other| *other != group).all(|other| self.group_matches(*other, chip))
+            }).collect::<Vec<_>>();
+            for facet in group.facets() {
+                let count = others.iter().filter(|chip| facet.matches(chip)).count();
+                to_ret.push(FacetCount{facet, count, selected: self.is_selected(facet)});
+            }
+        }
+        to_ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chips() -> Vec<BattleChip> {
+        serde_json::from_str(r#"[
+            {"Name": "Bubbler", "Element": ["Aqua"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Close", "Hits": "1", "Description": ""},
+            {"Name": "AquaSword", "Element": ["Aqua", "Sword"], "Skills": ["Strength"], "Damage": "3d6", "Class": "Mega", "Type": "Melee", "Range": "Close", "Hits": "1", "Description": ""},
+            {"Name": "HeatShot", "Element": ["Fire"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": ""},
+            {"Name": "Barrier", "Element": ["Null"], "Skills": ["None"], "Damage": "--", "Class": "Support", "Type": "Support", "Range": "Self", "Hits": "0", "Description": ""}
+        ]"#).unwrap()
+    }
+
+    fn count(counts: &[FacetCount], facet: Facet) -> usize {
+        counts.iter().find(|count| count.facet == facet).unwrap().count
+    }
+
+    #[test]
+    fn groups_are_anded_and_facets_ored() {
+        let chips = chips();
+        let mut filter = FacetFilter::default();
+        filter.toggle(Facet::Element(Elements::Aqua));
+        filter.toggle(Facet::Range(Ranges::Close));
+        let names = chips.iter().filter(|chip| filter.matches(chip)).map(|chip| chip.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Bubbler", "AquaSword"]);
+
+        filter.toggle(Facet::Element(Elements::Fire));
+        filter.toggle(Facet::Range(Ranges::Far));
+        assert_eq!(chips.iter().filter(|chip| filter.matches(chip)).count(), 3);
+
+        filter.toggle(Facet::Range(Ranges::Far));
+        assert!(!filter.is_selected(Facet::Range(Ranges::Far)));
+    }
+
+    #[test]
+    fn counts_ignore_their_own_group() {
+        let chips = chips();
+        let refs = chips.iter().collect::<Vec<&BattleChip>>();
+        let mut filter = FacetFilter::default();
+        filter.toggle(Facet::Element(Elements::Aqua));
+
+        let counts = filter.counts(&refs);
+        // other elements still show what picking them would add
+        assert_eq!(count(&counts, Facet::Element(Elements::Fire)), 1);
+        assert_eq!(count(&counts, Facet::Range(Ranges::Close)), 2);
+        assert_eq!(count(&counts, Facet::Range(Ranges::Far)), 0);
+        // standard and support are separate facets
+        assert_eq!(count(&counts, Facet::Class(ChipClass::Standard)), 1);
+        assert_eq!(count(&counts, Facet::Class(ChipClass::Support)), 0);
+    }
+}
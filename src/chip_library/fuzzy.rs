@@ -0,0 +1,131 @@
+/// scores for each way a name can match, lower is a better match
+const EXACT: u32 = 0;
+const PREFIX: u32 = 1;
+const SUBSTRING: u32 = 10;
+const SUBSEQUENCE: u32 = 100;
+const TYPO: u32 = 1000;
+
+/// a letter or two in order is in nearly every name, so shorter searches have to appear as written
+const MIN_SUBSEQUENCE_LEN: usize = 3;
+
+/// lowercased letters and digits only, so "Heat Shot" and "heatshot" are the same name
+fn normalize(name: &str) -> Vec<char> {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// how many typos a name of this length is allowed before it stops being the same name
+fn max_typos(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=5 => 1,
+        6..=9 => 2,
+        _ => 3,
+    }
+}
+
+/// edits (insert, delete, substitute or swap two neighbours) needed to turn a into b
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] {0} else {1};
+            row[j] = (rows[i - 1][j] + 1).min(row[j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// the gaps left between query's letters if they all appear in order in name
+fn subsequence_gaps(query: &[char], name: &[char]) -> Option<u32> {
+    let mut gaps = 0;
+    let mut last = None;
+    let mut chars = name.iter().enumerate();
+    for c in query {
+        let (idx, _) = chars.find(|(_, name_c)| *name_c == c)?;
+        if let Some(last) = last {
+            gaps += (idx - last - 1) as u32;
+        }
+        last = Some(idx);
+    }
+    Some(gaps)
+}
+
+/// how well a search matches a chip name, None if it doesn't
+///
+/// names that contain the search beat ones that only have its letters in order,
+/// which beat ones that are a few typos away
+pub(crate) fn score(query: &str, name: &str) -> Option<u32> {
+    let query = normalize(query);
+    let name = normalize(name);
+    if query == name {
+        return Some(EXACT);
+    }
+    if name.starts_with(&query) {
+        return Some(PREFIX);
+    }
+    if let Some(idx) = name.windows(query.len().max(1)).position(|window| window == &query[..]) {
+        return Some(SUBSTRING + idx as u32);
+    }
+    if query.len() >= MIN_SUBSEQUENCE_LEN {
+        if let Some(gaps) = subsequence_gaps(&query, &name) {
+            return Some(SUBSEQUENCE + gaps);
+        }
+    }
+    // typing the start of a name counts, so compare against the same length of it too
+    let prefix = &name[..query.len().min(name.len())];
+    let typos = edit_distance(&query, &name).min(edit_distance(&query, prefix));
+    if typos > 0 && typos <= max_typos(query.len()) {
+        return Some(TYPO + typos as u32);
+    }
+    None
+}
+
+/// the name the misspelled one was most likely meant to be, if any is close enough
+///
+/// only whole names count here, a search can get away with less
+pub(crate) fn suggest<'a, I>(name: &str, names: I) -> Option<&'a str> where I: IntoIterator<Item = &'a String> {
+    let normalized = normalize(name);
+    let allowed = max_typos(normalized.len());
+    names.into_iter()
+        .map(|candidate| (edit_distance(&normalized, &normalize(candidate)), candidate.as_str()))
+        .filter(|(typos, _)| *typos <= allowed)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_are_ranked() {
+        assert_eq!(score("cannon", "Cannon"), Some(EXACT));
+        assert_eq!(score("Heat Shot", "HeatShot"), Some(EXACT));
+        assert_eq!(score("can", "Cannon"), Some(PREFIX));
+        assert_eq!(score("shot", "HeatShot"), Some(SUBSTRING + 4));
+        assert_eq!(score("htsht", "HeatShot"), Some(SUBSEQUENCE + 3));
+        assert_eq!(score("aquaswrod", "AquaSword"), Some(TYPO + 1));
+        assert_eq!(score("cannn", "Cannon"), Some(SUBSEQUENCE + 1));
+        // still typing, but a typo in what's there so far
+        assert_eq!(score("bubbel", "Bubbler"), Some(TYPO + 1));
+        assert_eq!(score("zap", "Cannon"), None);
+        assert_eq!(score("xyz", "Cannon"), None);
+        assert_eq!(score("cn", "Cannon"), None);
+        assert_eq!(score("no", "Cannon"), Some(SUBSTRING + 3));
+    }
+
+    #[test]
+    fn suggestions_need_to_be_close() {
+        let names = vec![String::from("Cannon"), String::from("HiCannon"), String::from("AquaSword"), String::from("Bubbler")];
+        assert_eq!(suggest("Canon", &names), Some("Cannon"));
+        assert_eq!(suggest("aqua sword", &names), Some("AquaSword"));
+        assert_eq!(suggest("AquaSowrd", &names), Some("AquaSword"));
+        assert_eq!(suggest("Bublr", &names), None);
+        assert_eq!(suggest("Zap", &names), None);
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+/// how many changes can be undone
+const MAX_UNDO: usize = 50;
+
+/// A change to the pack, folder, or chip limit that can be undone
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Command {
+    AddCopyToPack(String),
+    MoveToFolder(String),
+    ReturnToPack(String),
+    RemoveFromPack(String),
+    MarkPackCopyUnused(String),
+    /// a folder chip was marked used, or unused if false
+    MarkFolderChip(String, bool),
+    ClearFolder,
+    JackOut,
+    UpdateChipLimit(usize),
+    SwitchPreset(String),
+}
+
+impl Command {
+    /// what the command did, for the header message
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Command::AddCopyToPack(name) => String::from("adding a copy of ") + name + " to your pack",
+            Command::MoveToFolder(name) => String::from("moving ") + name + " to your folder",
+            Command::ReturnToPack(name) => String::from("returning ") + name + " to your pack",
+            Command::RemoveFromPack(name) => String::from("removing a copy of ") + name + " from your pack",
+            Command::MarkPackCopyUnused(name) => String::from("marking a copy of ") + name + " unused",
+            Command::MarkFolderChip(name, true) => String::from("marking ") + name + " in your folder used",
+            Command::MarkFolderChip(name, false) => String::from("marking ") + name + " in your folder unused",
+            Command::ClearFolder => String::from("clearing your folder"),
+            Command::JackOut => String::from("jacking out"),
+            Command::UpdateChipLimit(limit) => String::from("setting the chip limit to ") + &limit.to_string(),
+            Command::SwitchPreset(name) => String::from("switching to the ") + name + " preset",
+        }
+    }
+}
+
+/// The pack, folder and chip limit at some point in time
+///
+/// chips are stored by name, they're looked back up in the library when restored
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Snapshot {
+    /// name, owned, used
+    pub pack: Vec<(String, u32, u32)>,
+    /// name, used, in folder order
+    pub folder: Vec<(String, bool)>,
+    pub limit: usize,
+}
+
+impl Snapshot {
+    /// true if both hold the same chips, the folder's order doesn't count since viewing it sorted can change it
+    fn same_chips(&self, other: &Snapshot) -> bool {
+        let sorted = |folder: &[(String, bool)]| {
+            let mut folder = folder.to_vec();
+            folder.sort_unstable();
+            folder
+        };
+        self.pack == other.pack && self.limit == other.limit && sorted(&self.folder) == sorted(&other.folder)
+    }
+}
+
+struct HistoryEntry {
+    command: Command,
+    before: Snapshot,
+    after: Snapshot,
+}
+
+/// Bounded undo and redo stacks
+#[derive(Default)]
+pub(crate) struct History {
+    undo: VecDeque<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// record a command along with the state from before and after it ran, clears the redo stack
+    pub(crate) fn record(&mut self, command: Command, before: Snapshot, after: Snapshot) {
+        if self.undo.len() >= MAX_UNDO {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(HistoryEntry{command, before, after});
+        self.redo.clear();
+    }
+
+    /// pops the most recent command, returns it and the state to restore
+    ///
+    /// if current isn't the state the command left behind, something changed that wasn't recorded,
+    /// undoing would silently revert that too so the whole history is dropped instead
+    pub(crate) fn undo(&mut self, current: &Snapshot) -> Option<(Command, Snapshot)> {
+        if !self.undo.back()?.after.same_chips(current) {
+            self.clear();
+            return None;
+        }
+        let entry = self.undo.pop_back()?;
+        let undone = (entry.command.clone(), entry.before.clone());
+        self.redo.push(entry);
+        Some(undone)
+    }
+
+    /// pops the most recently undone command, returns it and the state to restore
+    ///
+    /// the history is dropped if current isn't the state the command was undone to, like with undo
+    pub(crate) fn redo(&mut self, current: &Snapshot) -> Option<(Command, Snapshot)> {
+        if !self.redo.last()?.before.same_chips(current) {
+            self.clear();
+            return None;
+        }
+        let entry = self.redo.pop()?;
+        let redone = (entry.command.clone(), entry.after.clone());
+        // redo is bounded by undo, nothing can be pushed to it without popping from undo
+        self.undo.push_back(entry);
+        Some(redone)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(limit: usize) -> Snapshot {
+        Snapshot{pack: Vec::new(), folder: Vec::new(), limit}
+    }
+
+    #[test]
+    fn undo_then_redo() {
+        let mut history = History::default();
+        history.record(Command::UpdateChipLimit(13), snapshot(12), snapshot(13));
+        history.record(Command::UpdateChipLimit(14), snapshot(13), snapshot(14));
+
+        assert_eq!(history.undo(&snapshot(14)), Some((Command::UpdateChipLimit(14), snapshot(13))));
+        assert_eq!(history.undo(&snapshot(13)), Some((Command::UpdateChipLimit(13), snapshot(12))));
+        assert!(history.undo(&snapshot(12)).is_none());
+
+        assert_eq!(history.redo(&snapshot(12)), Some((Command::UpdateChipLimit(13), snapshot(13))));
+
+        // a new command means there's nothing left to redo
+        history.record(Command::JackOut, snapshot(13), snapshot(0));
+        assert!(history.redo(&snapshot(0)).is_none());
+        assert_eq!(history.undo(&snapshot(0)), Some((Command::JackOut, snapshot(13))));
+    }
+
+    #[test]
+    fn unrecorded_changes_drop_the_history() {
+        let mut history = History::default();
+        history.record(Command::UpdateChipLimit(13), snapshot(12), snapshot(13));
+        history.record(Command::UpdateChipLimit(14), snapshot(13), snapshot(14));
+        assert!(history.undo(&snapshot(15)).is_none());
+        assert!(history.undo(&snapshot(14)).is_none());
+
+        history.record(Command::UpdateChipLimit(13), snapshot(12), snapshot(13));
+        history.undo(&snapshot(13));
+        assert!(history.redo(&snapshot(20)).is_none());
+        assert!(history.redo(&snapshot(12)).is_none());
+    }
+
+    #[test]
+    fn undo_is_bounded() {
+        let mut history = History::default();
+        for limit in 0..(MAX_UNDO + 10) {
+            history.record(Command::UpdateChipLimit(limit + 1), snapshot(limit), snapshot(limit + 1));
+        }
+        let mut count = 0;
+        while history.undo(&snapshot(MAX_UNDO + 10 - count)).is_some() {
+            count += 1;
+        }
+        assert_eq!(count, MAX_UNDO);
+    }
+}
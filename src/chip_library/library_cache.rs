@@ -0,0 +1,72 @@
+use crate::chip_library::{library_diff::fingerprint, storage::DataStore};
+use serde::{Deserialize, Serialize};
+
+/// where the known good copy of chips.json is kept, shared by every profile
+pub(crate) const LIBRARY_CACHE_KEY: &str = "library_cache";
+
+/// The last chips.json that loaded, kept for when the network doesn't come through
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct CachedLibrary {
+    /// the server's ETag for this copy, sent back so it can say nothing changed
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// a hash of data, a copy that doesn't match it got cut short and isn't used
+    pub hash: String,
+    /// when this version was first saved
+    pub saved: f64,
+    pub data: String,
+}
+
+impl CachedLibrary {
+    /// the saved copy, None if there isn't one or it's been damaged
+    pub(crate) fn load(storage: &dyn DataStore) -> Option<CachedLibrary> {
+        CachedLibrary::load_from(storage, LIBRARY_CACHE_KEY)
+    }
+
+    /// the copy saved under key, chip sources are kept the same way chips.json is
+    pub(crate) fn load_from(storage: &dyn DataStore, key: &str) -> Option<CachedLibrary> {
+        let text = storage.get_item(key)?;
+        let cached = serde_json::from_str::<CachedLibrary>(&text).ok()?;
+        if cached.hash != fingerprint(&cached.data) {
+            return None;
+        }
+        Some(cached)
+    }
+
+    /// the ETag of the saved copy, if the server gave one
+    pub(crate) fn etag(storage: &dyn DataStore) -> Option<String> {
+        CachedLibrary::load(storage).and_then(|cached| cached.etag)
+    }
+
+    /// replace the saved copy with a library that just loaded fine
+    ///
+    /// nothing is written if it's the same version that's already saved
+    pub(crate) fn store(storage: &dyn DataStore, data: &str, etag: Option<&str>, now: f64) {
+        CachedLibrary::store_in(storage, LIBRARY_CACHE_KEY, data, etag, now);
+    }
+
+    /// store, but under key
+    pub(crate) fn store_in(storage: &dyn DataStore, key: &str, data: &str, etag: Option<&str>, now: f64) {
+        let hash = fingerprint(data);
+        let mut saved = now;
+        if let Some(cached) = CachedLibrary::load_from(storage, key) {
+            if cached.hash == hash {
+                if cached.etag.as_deref() == etag {
+                    return;
+                }
+                saved = cached.saved;
+            }
+        }
+        let cached = CachedLibrary {
+            etag: etag.map(str::to_owned),
+            hash,
+            saved,
+            data: data.to_owned(),
+        };
+        if let Ok(text) = serde_json::to_string(&cached) {
+            // a full localStorage just means there's no offline copy, the library itself loaded fine
+            let _ = storage.set_item(key, &text);
+        }
+    }
+}
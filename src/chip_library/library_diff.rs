@@ -0,0 +1,200 @@
+use crate::chip_library::{
+    battle_chip::BattleChip,
+    dice::{DiceExpr, DEFAULT_SKILL_RANK},
+    save_format::ChipRename,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What's remembered about a chip so the next version of the library can be compared against it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ChipSnapshot {
+    pub damage: String,
+    pub class: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+/// The library as it was when it was last loaded
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct LibrarySnapshot {
+    /// a hash of the chips.json text, if it matches nothing changed
+    pub fingerprint: String,
+    pub chips: BTreeMap<String, ChipSnapshot>,
+}
+
+/// 64 bit FNV-1a, plenty to tell two versions of chips.json apart
+pub(crate) fn fingerprint(data: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash.to_string()
+}
+
+impl LibrarySnapshot {
+    pub(crate) fn new<'a, I>(data: &str, chips: I) -> LibrarySnapshot where I: IntoIterator<Item = &'a BattleChip> {
+        let chips = chips.into_iter().map(|chip| {
+            (chip.name.clone(), ChipSnapshot {
+                damage: chip.damage.clone(),
+                class: String::from(chip.class.as_str()),
+                description: chip.description.clone(),
+                aliases: chip.aliases.clone(),
+            })
+        }).collect();
+        LibrarySnapshot {
+            fingerprint: fingerprint(data),
+            chips,
+        }
+    }
+}
+
+/// Whether a chip's damage went up or down, going by its average
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DamageChange {
+    Buffed,
+    Nerfed,
+    Same,
+}
+
+/// One field of a chip that changed
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FieldChange {
+    pub field: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+/// Everything that changed about a chip, under its current name
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ChipDiff {
+    pub name: String,
+    pub fields: Vec<FieldChange>,
+    pub damage: DamageChange,
+}
+
+/// What changed between two versions of the library
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct LibraryDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<ChipRename>,
+    pub changed: Vec<ChipDiff>,
+}
+
+impl LibraryDiff {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// damage that can't be read counts as 0, the same as for sorting
+fn average_damage(damage: &str) -> f64 {
+    DiceExpr::parse(damage).and_then(|expr| expr.stats(DEFAULT_SKILL_RANK)).map_or(0.0, |stats| stats.avg)
+}
+
+fn compare_chip(name: &str, before: &ChipSnapshot, after: &ChipSnapshot) -> Option<ChipDiff> {
+    let mut fields = Vec::new();
+    let pairs = [
+        ("Damage", &before.damage, &after.damage),
+        ("Class", &before.class, &after.class),
+        ("Description", &before.description, &after.description),
+    ];
+    for &(field, before, after) in pairs.iter() {
+        if before != after {
+            fields.push(FieldChange{field, before: before.clone(), after: after.clone()});
+        }
+    }
+    if fields.is_empty() {
+        return None;
+    }
+    let before_avg = average_damage(&before.damage);
+    let after_avg = average_damage(&after.damage);
+    let damage = if after_avg > before_avg {
+        DamageChange::Buffed
+    } else if after_avg < before_avg {
+        DamageChange::Nerfed
+    } else {
+        DamageChange::Same
+    };
+    Some(ChipDiff{name: name.to_owned(), fields, damage})
+}
+
+/// compare the last library seen with the current one
+///
+/// a new chip with a former name that was in the old library counts as a rename, not an add and a remove
+pub(crate) fn diff(old: &LibrarySnapshot, new: &LibrarySnapshot) -> LibraryDiff {
+    let mut to_ret = LibraryDiff::default();
+
+    for (name, chip) in new.chips.iter() {
+        if let Some(old_chip) = old.chips.get(name) {
+            to_ret.changed.extend(compare_chip(name, old_chip, chip));
+            continue;
+        }
+        let former = chip.aliases.iter().find(|alias| old.chips.contains_key(*alias) && !new.chips.contains_key(*alias));
+        match former {
+            Some(former) => {
+                to_ret.renamed.push(ChipRename{from: former.clone(), to: name.clone()});
+                to_ret.changed.extend(compare_chip(name, &old.chips[former], chip));
+            }
+            None => to_ret.added.push(name.clone()),
+        }
+    }
+
+    for name in old.chips.keys() {
+        let renamed = to_ret.renamed.iter().any(|rename| rename.from == *name);
+        if !new.chips.contains_key(name) && !renamed {
+            to_ret.removed.push(name.clone());
+        }
+    }
+    to_ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(data: &str) -> LibrarySnapshot {
+        let chips = serde_json::from_str::<Vec<BattleChip>>(data).unwrap();
+        LibrarySnapshot::new(data, chips.iter())
+    }
+
+    #[test]
+    fn changes_are_found() {
+        let old = snapshot(r#"[
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A cannon"},
+            {"Name": "Blade", "Element": ["Sword"], "Skills": ["Strength"], "Damage": "3d6", "Class": "Standard", "Type": "Melee", "Range": "Close", "Hits": "1", "Description": "A sword"},
+            {"Name": "Bomb", "Element": ["Fire"], "Skills": ["Strength"], "Damage": "4d8", "Class": "Mega", "Type": "Burst", "Range": "Near", "Hits": "1", "Description": "A bomb"}
+        ]"#);
+        let new = snapshot(r#"[
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "3d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A cannon"},
+            {"Name": "Sword", "Formerly": ["Blade"], "Element": ["Sword"], "Skills": ["Strength"], "Damage": "2d6", "Class": "Standard", "Type": "Melee", "Range": "Close", "Hits": "1", "Description": "A sword"},
+            {"Name": "Recov10", "Element": ["Recovery"], "Skills": ["None"], "Damage": "--", "Class": "Support", "Type": "Recovery", "Range": "Self", "Hits": "0", "Description": "Heals"}
+        ]"#);
+        assert_ne!(old.fingerprint, new.fingerprint);
+
+        let diff = diff(&old, &new);
+        assert_eq!(diff.added, vec!["Recov10"]);
+        assert_eq!(diff.removed, vec!["Bomb"]);
+        assert_eq!(diff.renamed, vec![ChipRename{from: String::from("Blade"), to: String::from("Sword")}]);
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].name, "Cannon");
+        assert_eq!(diff.changed[0].damage, DamageChange::Buffed);
+        assert_eq!(diff.changed[0].fields, vec![FieldChange{field: "Damage", before: String::from("2d6"), after: String::from("3d6")}]);
+        assert_eq!(diff.changed[1].name, "Sword");
+        assert_eq!(diff.changed[1].damage, DamageChange::Nerfed);
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let old = snapshot(r#"[{"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A cannon"}]"#);
+        let text = serde_json::to_string(&old).unwrap();
+        let read = serde_json::from_str::<LibrarySnapshot>(&text).unwrap();
+        assert_eq!(read, old);
+        assert!(diff(&old, &read).is_empty());
+    }
+}
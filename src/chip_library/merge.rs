@@ -0,0 +1,206 @@
+use crate::chip_library::save_format::{SaveData, SavedPackChip};
+use std::collections::BTreeMap;
+
+/// How an imported file is combined with what's already saved
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ImportMode {
+    /// throw away the current data and use the file's
+    Replace,
+    /// add the file's copies to the ones already owned
+    MergeAdd,
+    /// keep whichever has more copies of each chip
+    MergeMax,
+}
+
+impl ImportMode {
+    pub(crate) fn describe(&self) -> &'static str {
+        match self {
+            ImportMode::Replace => "Replace",
+            ImportMode::MergeAdd => "Merge (add copies)",
+            ImportMode::MergeMax => "Merge (keep most copies)",
+        }
+    }
+}
+
+/// How many copies of a chip are owned before and after an import, counting the folder
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ChipChange {
+    pub name: String,
+    pub before: u32,
+    pub after: u32,
+}
+
+/// What an import would do, shown before anything is changed
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ImportPreview {
+    pub added: Vec<ChipChange>,
+    pub changed: Vec<ChipChange>,
+    pub removed: Vec<ChipChange>,
+    pub folder_before: usize,
+    pub folder_after: usize,
+    pub limit_before: usize,
+    pub limit_after: usize,
+}
+
+impl ImportPreview {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+            && self.folder_before == self.folder_after && self.limit_before == self.limit_after
+    }
+}
+
+/// combine the current save with an imported one
+///
+/// merging keeps the current folder, the imported folder's chips go into the pack instead
+pub(crate) fn merge(current: &SaveData, incoming: &SaveData, mode: ImportMode) -> SaveData {
+    if mode == ImportMode::Replace {
+        return incoming.clone();
+    }
+
+    let current_folder = folder_counts(current);
+    let incoming_totals = totals(incoming);
+    let mut pack = current.pack.clone();
+
+    for (name, incoming_total) in incoming_totals.iter() {
+        let incoming_used = incoming.pack.get(name).map_or(0, |chip| chip.used);
+        let in_folder = current_folder.get(name).copied().unwrap_or(0);
+        let current_chip = pack.get(name).cloned().unwrap_or(SavedPackChip{owned: 0, used: 0});
+
+        let (owned, used) = match mode {
+            ImportMode::MergeAdd => (current_chip.owned.saturating_add(*incoming_total), current_chip.used.saturating_add(incoming_used)),
+            _ => {
+                // copies in the current folder count towards the total kept
+                let total = current_chip.owned.saturating_add(in_folder).max(*incoming_total);
+                let owned = total - in_folder;
+                (owned, current_chip.used.max(incoming_used).min(owned))
+            }
+        };
+        if owned > 0 {
+            pack.insert(name.clone(), SavedPackChip{owned, used});
+        }
+    }
+
+    let mut presets = current.presets.clone();
+    presets.add_missing(&incoming.presets);
+    let mut renames = current.renames.clone();
+    renames.extend(incoming.renames.iter().filter(|rename| !current.renames.contains(rename)).cloned());
+
+    // a custom chip that's already here keeps the current version of it
+    let mut custom_chips = current.custom_chips.clone();
+    custom_chips.extend(incoming.custom_chips.iter().filter(|chip| !current.custom_chips.contains(chip)).cloned());
+
+    SaveData {
+        version: current.version,
+        folder: current.folder.clone(),
+        pack,
+        limit: current.limit.max(incoming.limit),
+        log: current.log.clone(),
+        presets,
+        renames,
+        custom_chips,
+    }
+}
+
+/// compare the save before and after an import
+pub(crate) fn preview(before: &SaveData, after: &SaveData) -> ImportPreview {
+    let before_totals = totals(before);
+    let after_totals = totals(after);
+    let mut to_ret = ImportPreview {
+        folder_before: before.folder.len(),
+        folder_after: after.folder.len(),
+        limit_before: before.limit,
+        limit_after: after.limit,
+        ..ImportPreview::default()
+    };
+
+    for (name, after_count) in after_totals.iter() {
+        match before_totals.get(name) {
+            None => to_ret.added.push(ChipChange{name: name.clone(), before: 0, after: *after_count}),
+            Some(before_count) if before_count != after_count => {
+                to_ret.changed.push(ChipChange{name: name.clone(), before: *before_count, after: *after_count});
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, before_count) in before_totals.iter() {
+        if !after_totals.contains_key(name) {
+            to_ret.removed.push(ChipChange{name: name.clone(), before: *before_count, after: 0});
+        }
+    }
+    to_ret
+}
+
+fn folder_counts(save: &SaveData) -> BTreeMap<String, u32> {
+    let mut counts = BTreeMap::new();
+    for chip in save.folder.iter() {
+        *counts.entry(chip.name.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// copies owned of each chip, in the pack or the folder
+fn totals(save: &SaveData) -> BTreeMap<String, u32> {
+    let mut counts = folder_counts(save);
+    for (name, chip) in save.pack.iter() {
+        let count = counts.entry(name.clone()).or_insert(0);
+        *count = count.saturating_add(chip.owned);
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save(json: &str) -> SaveData {
+        SaveData::from_json(json).unwrap()
+    }
+
+    fn current() -> SaveData {
+        save(r#"{"Limit": 12, "Folder": [{"name": "Sword", "used": false}], "Pack": {"Sword": {"owned": 1, "used": 0}, "Cannon": {"owned": 2, "used": 1}}}"#)
+    }
+
+    fn incoming() -> SaveData {
+        save(r#"{"Limit": 15, "Pack": {"Sword": {"owned": 3, "used": 0}, "BigBomb": {"owned": 1, "used": 0}}}"#)
+    }
+
+    #[test]
+    fn merge_add_sums_copies() {
+        let merged = merge(&current(), &incoming(), ImportMode::MergeAdd);
+        assert_eq!(merged.pack["Sword"], SavedPackChip{owned: 4, used: 0});
+        assert_eq!(merged.pack["Cannon"], SavedPackChip{owned: 2, used: 1});
+        assert_eq!(merged.pack["BigBomb"], SavedPackChip{owned: 1, used: 0});
+        assert_eq!(merged.folder.len(), 1);
+        assert_eq!(merged.limit, 15);
+    }
+
+    #[test]
+    fn merge_add_stops_at_the_most_copies() {
+        let full = save(r#"{"Limit": 12, "Pack": {"Sword": {"owned": 4294967295, "used": 4294967295}}}"#);
+        let merged = merge(&full, &incoming(), ImportMode::MergeAdd);
+        assert_eq!(merged.pack["Sword"], SavedPackChip{owned: u32::MAX, used: u32::MAX});
+        let merged = merge(&full, &full, ImportMode::MergeMax);
+        assert_eq!(merged.pack["Sword"].owned, u32::MAX);
+    }
+
+    #[test]
+    fn merge_max_counts_the_folder() {
+        let merged = merge(&current(), &incoming(), ImportMode::MergeMax);
+        // 1 in the folder + 2 in the pack makes the 3 the file has
+        assert_eq!(merged.pack["Sword"], SavedPackChip{owned: 2, used: 0});
+        assert_eq!(merged.pack["Cannon"], SavedPackChip{owned: 2, used: 1});
+    }
+
+    #[test]
+    fn preview_lists_changes() {
+        let current = current();
+        let replaced = merge(&current, &incoming(), ImportMode::Replace);
+        let diff = preview(&current, &replaced);
+        assert_eq!(diff.added, vec![ChipChange{name: String::from("BigBomb"), before: 0, after: 1}]);
+        assert_eq!(diff.changed, vec![ChipChange{name: String::from("Sword"), before: 2, after: 3}]);
+        assert_eq!(diff.removed, vec![ChipChange{name: String::from("Cannon"), before: 2, after: 0}]);
+        assert_eq!((diff.folder_before, diff.folder_after), (1, 0));
+
+        assert!(preview(&current, &current).is_empty());
+    }
+}
@@ -3,20 +3,95 @@ mod elements;
 mod skills;
 mod chip_type;
 mod ranges;
-
+mod storage;
+mod dice;
+mod roller;
+mod combat_log;
+mod history;
+mod presets;
+mod profiles;
+mod save_format;
+mod merge;
+mod text_format;
+mod query;
+mod facets;
+mod sorting;
+mod fuzzy;
+mod library_diff;
+mod library_cache;
+mod sources;
+mod validation;
+mod config;
+
+#[cfg(test)]
+mod tests;
 
 pub(crate) use self::battle_chip::BattleChip;
 pub(crate) use self::elements::Elements;
+pub(crate) use self::storage::{DataStore, Notifier, MemoryStore};
+pub(crate) use self::roller::{DiceRoller, ChipRoll};
+pub(crate) use self::combat_log::{Clock, CombatLog, LogEntry, LogEvent};
+pub(crate) use self::history::Command;
+use self::history::{History, Snapshot};
+pub(crate) use self::presets::FolderPresets;
+pub(crate) use self::profiles::Profiles;
+use self::profiles::{profile_key, PROFILES_BACKUP_KEY, PROFILES_KEY};
+pub(crate) use self::save_format::{SaveData, DEFAULT_CHIP_LIMIT};
+use self::save_format::{ChipRename, SavedFolderChip, SavedPackChip, MAX_CHIP_LIMIT};
+pub(crate) use self::merge::{ChipChange, ImportMode, ImportPreview};
+pub(crate) use self::query::Query;
+pub(crate) use self::facets::{Facet, FacetCount, FacetFilter, FacetGroup};
+pub(crate) use self::sorting::{SortKey, SortRow, SortSpec};
+pub(crate) use self::library_diff::{ChipDiff, DamageChange, LibraryDiff};
+use self::library_diff::LibrarySnapshot;
+pub(crate) use self::library_cache::CachedLibrary;
+pub(crate) use self::sources::{ChipSource, SourceConflict, SourceStatus, BASE_SOURCE};
+use self::sources::{MergedSources, Unavailable};
+use self::validation::{LibraryProblem, ValidatedLibrary};
+pub(crate) use self::config::{AppConfig, ConfigOverrides, RuntimeConfig};
+use self::config::{is_legacy_server, mark_legacy_server, CONFIG_KEY};
+
+/// where a profile's save data is kept
+const SAVE_KEY: &str = "save";
+
+/// where save data that couldn't be loaded is kept so the next save doesn't lose it
+const SAVE_BACKUP_KEY: &str = "save_backup";
+
+/// the library as it was last loaded, shared by every profile
+const LIBRARY_SNAPSHOT_KEY: &str = "library_snapshot";
+
+/// each of these had its own key before saves were versioned, oldest first
+const LEGACY_KEYS: [(&str, &str); 4] = [("Pack", "pack"), ("Folder", "folder"), ("Limit", "chip_limit"), ("Presets", "folder_presets")];
+
+/// A profile's save data with its chips looked up in the library
+struct LoadedData {
+    pack: HashMap<String, PackChip>,
+    folder: Vec<FolderChip>,
+    limit: usize,
+    log: Vec<LogEntry>,
+    presets: FolderPresets,
+    renames: Vec<ChipRename>,
+    custom: HashMap<String, Rc<BattleChip>>,
+    held: HeldChips,
+    /// chips were renamed to what the library calls them, so this differs from what was saved
+    renamed: bool,
+}
 
+/// Saved chips whose source is switched off or didn't load, kept as they were saved until it's back
+#[derive(Clone, Default)]
+struct HeldChips {
+    pack: BTreeMap<String, SavedPackChip>,
+    folder: Vec<SavedFolderChip>,
+}
 
-use crate::util;
-use std::{collections::hash_map::HashMap, ptr};
+use std::{collections::{BTreeMap, HashMap}, ptr};
 use std::cell::RefCell;
-use serde::{Serialize, Deserialize};
+use serde::Serialize;
 use unchecked_unwrap::UncheckedUnwrap;
-use serde_json::{Value, json};
+use serde_json::Value;
 use std::sync::atomic::{Ordering, AtomicUsize, AtomicBool};
 use std::rc::Rc;
+use group_protocol::FolderChip as SharedChip;
 
 #[derive(Serialize)]
 pub(crate) struct PackChip {
@@ -34,20 +109,72 @@ pub(crate) struct FolderChip {
     pub chip: Rc<BattleChip>,
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Clone)]
 pub(crate) struct GroupFolderChip {
     pub name: String,
     pub used: bool,
+    /// the whole chip if it's a custom one, so the rest of the group can see what it does
+    pub custom: Option<BattleChip>,
+}
+
+impl From<SharedChip> for GroupFolderChip {
+    /// a custom chip that can't be read is left as just a name
+    fn from(chip: SharedChip) -> Self {
+        GroupFolderChip {
+            name: chip.name,
+            used: chip.used,
+            custom: chip.custom.and_then(|text| serde_json::from_str::<BattleChip>(&text).ok()),
+        }
+    }
 }
 
 pub(crate) struct ChipLibrary {
     pub library: HashMap<String, Rc<BattleChip>>,
+    /// old chip names to the ones they go by now
+    aliases: HashMap<String, String>,
+    /// what changed since the library was last loaded, None if nothing did or it's the first load
+    pub library_diff: Option<LibraryDiff>,
+    /// when the saved copy of the library in use was saved, None if it's the latest one
+    pub cached_library: Option<f64>,
+    /// chips.json followed by every other source, in the order they were laid over it
+    pub sources: Vec<SourceStatus>,
+    /// chip names more than one enabled source has
+    pub source_conflicts: Vec<SourceConflict>,
+    /// chips saves can have that the sources in use don't
+    unavailable: Unavailable,
+    /// settings from outside the profile, the profile's own are kept in storage
+    pub runtime_config: RuntimeConfig,
     pub pack: RefCell<HashMap<String, PackChip>>,
     pub folder: RefCell<Vec<FolderChip>>,
+    /// the active profile's custom chips, looked up alongside the library
+    custom_chips: RefCell<HashMap<String, Rc<BattleChip>>>,
+    /// the active profile's chips that are unavailable, they're written back with the rest of the save
+    held: RefCell<HeldChips>,
+    /// custom chips in the rest of the group's folders
+    shared_chips: RefCell<HashMap<String, Rc<BattleChip>>>,
     pub group_folders: RefCell<HashMap<String, Vec<GroupFolderChip>>>,
     pub chip_limit: AtomicUsize,
     change_since_last_save: AtomicBool,
     change_since_last_group_post: AtomicBool,
+    pub combat_log: RefCell<CombatLog>,
+    pub presets: RefCell<FolderPresets>,
+    /// every chip rename applied to the active profile's data
+    renames: RefCell<Vec<ChipRename>>,
+    pub profiles: RefCell<Profiles>,
+    history: RefCell<History>,
+    storage: Box<dyn DataStore>,
+    notifier: Box<dyn Notifier>,
+    clock: Box<dyn Clock>,
+}
+
+/// The chips.json text that's being used and the chips read from it
+struct PickedLibrary {
+    data: String,
+    library: HashMap<String, Rc<BattleChip>>,
+    /// when the saved copy was saved, if that's what's being used
+    cached_library: Option<f64>,
+    /// what was wrong with the chips that were left out
+    problems: Vec<LibraryProblem>,
 }
 
 unsafe impl Send for ChipLibrary{}
@@ -58,9 +185,15 @@ static mut INSTANCE: *const ChipLibrary = ptr::null();
 
 impl ChipLibrary {
 
-    pub(crate) fn init(data: &str) -> Result<(), String> {
+    /// storage of None means localStorage isn't available, the data will only be kept in memory
+    ///
+    /// fetched is None if chips.json couldn't be downloaded, etag is the one the server sent with it
+    /// (or the saved copy's if the server said it hadn't changed)
+    ///
+    /// sources are laid over chips.json in order, so each one's chips replace the ones before it
+    pub(crate) fn init(fetched: Option<&str>, etag: Option<&str>, sources: &[ChipSource], config: RuntimeConfig, storage: Option<Box<dyn DataStore>>, notifier: Box<dyn Notifier>, clock: Box<dyn Clock>) -> Result<(), String> {
         //initialize library, and allocate
-        let library = Box::new(ChipLibrary::import_local(data)?);
+        let library = Box::new(ChipLibrary::load(fetched, etag, sources, config, storage, notifier, clock)?);
 
         unsafe {
             INSTANCE = Box::into_raw(library); //convert to raw ptr, don't have to worry about deallocating later since it's basically static
@@ -73,116 +206,402 @@ impl ChipLibrary {
         unsafe { &*INSTANCE }
     }
 
-    fn import_local(data: &str) -> Result<ChipLibrary, String> {
-        let mut chip_list: Vec<BattleChip> = match serde_json::from_str::<Vec<BattleChip>>(data) {
-            Ok(chip_list) => chip_list,
-            Err(e) => {
-                let msg = e.to_string();
-                unsafe{util::alert(&msg)};
-                return Err(msg)
+    /// build a library from the chips.json text, loading any saved pack and folder from storage
+    #[cfg(test)]
+    pub(crate) fn new(data: &str, storage: Option<Box<dyn DataStore>>, notifier: Box<dyn Notifier>, clock: Box<dyn Clock>) -> Result<ChipLibrary, String> {
+        ChipLibrary::load(Some(data), None, &[], RuntimeConfig::default(), storage, notifier, clock)
+    }
+
+    /// build a library from the downloaded chips.json, or the saved copy of it if that's all there is,
+    /// with the other sources on top of it
+    fn load(fetched: Option<&str>, etag: Option<&str>, sources: &[ChipSource], runtime_config: RuntimeConfig, storage: Option<Box<dyn DataStore>>, notifier: Box<dyn Notifier>, clock: Box<dyn Clock>) -> Result<ChipLibrary, String> {
+        let cached = storage.as_deref().and_then(CachedLibrary::load);
+        let PickedLibrary{data, library, cached_library, problems} = match ChipLibrary::pick_library(fetched, etag, cached, &*notifier) {
+            Ok(picked) => picked,
+            Err(msg) => {
+                notifier.alert(&msg);
+                return Err(msg);
             }
         };
-        let mut library: HashMap<String, Rc<BattleChip>> = HashMap::with_capacity(chip_list.len());
-        for chip in chip_list.drain(..) {
-            library.insert(chip.name.clone(), Rc::new(chip));
-        }
-        /*
-        while !chip_list.is_empty() {
-            let chip = chip_list.pop().unwrap();
-            library.insert(chip.name.clone(), Rc::new(chip));
-        }
-        */
 
-        let window = unsafe{web_sys::window().unchecked_unwrap()};
-        let storage = match window.local_storage().ok().flatten() {
+        let storage = match storage {
             Some(storage) => storage,
             None => {
-                unsafe{util::alert("Local storage is not available, it is used to backup your folder and pack periodically")};
-                return Ok(ChipLibrary {
-                    library,
-                    pack: RefCell::new(HashMap::new()),
-                    folder: RefCell::new(Vec::new()),
-                    chip_limit: AtomicUsize::new(12),
-                    group_folders: RefCell::new(HashMap::new()),
-                    change_since_last_save: AtomicBool::new(false),
-                    change_since_last_group_post: AtomicBool::new(false),
-                });
+                notifier.alert("Local storage is not available, it is used to backup your folder and pack periodically");
+                Box::new(MemoryStore::default())
             }
         };
 
-        let pack = RefCell::new(ChipLibrary::load_pack(&storage, &library).unwrap_or_default());
-        let folder = RefCell::new(ChipLibrary::load_folder(&storage, &library).unwrap_or_default());
-        let chip_limit = AtomicUsize::new(ChipLibrary::load_chip_limit(&storage).unwrap_or(12));
+        let now = clock.now();
+        if cached_library.is_none() {
+            CachedLibrary::store(&*storage, &data, etag, now);
+        }
+        let disabled = sources::disabled_sources(&*storage);
+        let MergedSources{library, mut sources, conflicts, data, unavailable} = sources::merge(library, &data, sources, &disabled, &*storage, now);
+        sources[0].problems = problems;
+        for source in sources.iter() {
+            if let Some(why) = &source.error {
+                notifier.alert(&(String::from("The ") + &source.namespace + " chips couldn't be loaded\n" + why));
+            }
+            if !source.problems.is_empty() {
+                notifier.alert(&validation::report(&source.namespace, &source.problems));
+            }
+        }
+        let library_diff = ChipLibrary::check_for_changes(&*storage, &data, &library);
+        let profiles = ChipLibrary::load_profiles(&*storage, &*notifier);
+        let save = ChipLibrary::load_save(&*storage, &*notifier, profiles.active_id()).unwrap_or_default();
+        let aliases = ChipLibrary::build_aliases(&library);
+        let loaded = ChipLibrary::unpack_save(save, &library, &aliases, &unavailable, &*notifier);
+
+        let renamed = loaded.renamed;
+        let pack = RefCell::new(loaded.pack);
+        let folder = RefCell::new(loaded.folder);
+        let chip_limit = AtomicUsize::new(loaded.limit);
+        let presets = RefCell::new(loaded.presets);
+        let renames = RefCell::new(loaded.renames);
+        let custom_chips = RefCell::new(loaded.custom);
+        let held = RefCell::new(loaded.held);
 
         Ok(ChipLibrary {
             library,
+            aliases,
+            library_diff,
+            cached_library,
+            sources,
+            source_conflicts: conflicts,
+            unavailable,
+            runtime_config,
             pack,
             folder,
             chip_limit,
+            custom_chips,
+            held,
+            shared_chips: RefCell::new(HashMap::new()),
             group_folders: RefCell::new(HashMap::new()),
-            change_since_last_save: AtomicBool::new(false),
+            change_since_last_save: AtomicBool::new(renamed),
             change_since_last_group_post: AtomicBool::new(false),
+            combat_log: RefCell::new(CombatLog::default()),
+            presets,
+            renames,
+            profiles: RefCell::new(profiles),
+            history: RefCell::new(History::default()),
+            storage,
+            notifier,
+            clock,
         })
         
     }
 
-    /// load the pack from local storage
-    fn load_pack(storage: &web_sys::Storage, library: &HashMap<String, Rc<BattleChip>>) -> Option<HashMap<String, PackChip>> {
-        let pack_str: String = storage.get_item("pack").ok().flatten()?; //flatten an Option<Option<String>> into Option<String>
-        //let mut map = serde_json::from_str::<HashMap<String, (u8,u8)>>(&pack_str).ok()?;
-        let json = serde_json::from_str::<serde_json::Value>(&pack_str).ok()?;
-        let map = json.as_object()?;
-
-        let mut to_ret: HashMap<String, PackChip> = HashMap::new();
-
-        for pack_chip in map.iter() {
-            let owned = unsafe{pack_chip.1["owned"].as_u64().unchecked_unwrap()} as u32;
-            let used = unsafe{pack_chip.1["used"].as_u64().unchecked_unwrap()} as u32;
-            if let Some(chip) = library.get(pack_chip.0.as_str()) {
-                to_ret.insert(pack_chip.0.clone(), PackChip{
-                    owned,
-                    used,
-                    chip: Rc::clone(chip),
-                });
-            } else {
-                ChipLibrary::warn_missing_pack(pack_chip.0.as_str(), owned, used);
+    /// read chips.json, chips with problems are left out and listed instead
+    fn parse_library(data: String, cached_library: Option<f64>) -> Result<PickedLibrary, String> {
+        let ValidatedLibrary{chips, problems} = validation::validate(&data)?;
+        let mut library: HashMap<String, Rc<BattleChip>> = HashMap::with_capacity(chips.len());
+        for mut chip in chips {
+            chip.source = BASE_SOURCE.to_owned();
+            library.insert(chip.name.clone(), Rc::new(chip));
+        }
+        Ok(PickedLibrary{data, library, cached_library, problems})
+    }
+
+    /// use the downloaded library if it reads fine, otherwise fall back on the saved copy
+    ///
+    /// also returns when the saved copy was saved if that's what's being used and it might be out of date
+    fn pick_library(fetched: Option<&str>, etag: Option<&str>, cached: Option<CachedLibrary>, notifier: &dyn Notifier) -> Result<PickedLibrary, String> {
+        if let Some(data) = fetched {
+            match ChipLibrary::parse_library(data.to_owned(), None) {
+                Ok(picked) => return Ok(picked),
+                Err(msg) if cached.is_some() => {
+                    notifier.alert(&(String::from("The latest chip library couldn't be read, using the saved copy instead\n") + &msg));
+                }
+                Err(msg) => return Err(msg),
             }
         }
+        let cached = cached.ok_or("Couldn't load the chip library and there's no saved copy of it to use")?;
+        // the server saying nothing changed since the saved copy means it's still the latest
+        let up_to_date = fetched.is_none() && etag.is_some() && cached.etag.as_deref() == etag;
+        let cached_library = if up_to_date {None} else {Some(cached.saved)};
+        ChipLibrary::parse_library(cached.data, cached_library)
+    }
 
-        Some(to_ret)
+    /// compare the library with the one seen last time, then remember this one for next time
+    fn check_for_changes(storage: &dyn DataStore, data: &str, library: &HashMap<String, Rc<BattleChip>>) -> Option<LibraryDiff> {
+        let snapshot = LibrarySnapshot::new(data, library.values().map(|chip| &**chip));
+        let previous = storage.get_item(LIBRARY_SNAPSHOT_KEY).and_then(|text| serde_json::from_str::<LibrarySnapshot>(&text).ok());
+        if previous.as_ref().map_or(false, |previous| previous.fingerprint == snapshot.fingerprint) {
+            return None;
+        }
+        if let Ok(text) = serde_json::to_string(&snapshot) {
+            let _ = storage.set_item(LIBRARY_SNAPSHOT_KEY, &text);
+        }
+        previous.map(|previous| library_diff::diff(&previous, &snapshot)).filter(|diff| !diff.is_empty())
     }
 
-    /// load the folder from local storage
-    fn load_folder(storage: &web_sys::Storage, library: &HashMap<String, Rc<BattleChip>>) -> Option<Vec<FolderChip>> {
-        let folder_str: String = storage.get_item("folder").ok().flatten()?; //flatten an Option<Option<String>> into Option<String>
-        let json = serde_json::from_str::<serde_json::Value>(&folder_str).ok()?;
-        let fldr = json.as_array()?;
-        let mut to_ret: Vec<FolderChip> = Vec::new();
-        for folder_chip in fldr.iter() {
-            let name = unsafe{folder_chip["name"].as_str().unchecked_unwrap()};
-            let used = unsafe{folder_chip["used"].as_bool().unchecked_unwrap()};
-            if let Some(chip) = library.get(name) {
-                to_ret.push(
-                    FolderChip{
-                    name: name.to_owned(),
-                    used,
-                    chip: Rc::clone(chip),
-                });
-            } else {
-                ChipLibrary::warn_missing_fldr(name, used);
+    /// the settings the active profile would use, for before the library is loaded
+    pub(crate) fn startup_config(storage: &dyn DataStore, runtime: &RuntimeConfig) -> AppConfig {
+        let profile = ChipLibrary::read_profiles(storage).unwrap_or_default().active_id();
+        runtime.resolve(&ChipLibrary::load_settings(storage, profile))
+    }
+
+    /// a profile's own settings, nothing is overridden if they can't be read
+    fn load_settings(storage: &dyn DataStore, profile: u32) -> ConfigOverrides {
+        storage.get_item(&profile_key(profile, CONFIG_KEY))
+            .and_then(|text| ConfigOverrides::from_json(&text))
+            .unwrap_or_default()
+    }
+
+    /// the saved list of profiles, the default profile if there isn't one saved yet
+    ///
+    /// Err holds the saved text if it couldn't be read
+    fn read_profiles(storage: &dyn DataStore) -> Result<Profiles, String> {
+        match storage.get_item(PROFILES_KEY) {
+            Some(text) => serde_json::from_str::<Profiles>(&text).map_err(|_| text),
+            None => Ok(Profiles::default()),
+        }
+    }
+
+    /// load the list of profiles, if it can't be read the player is told and only the default profile is left
+    fn load_profiles(storage: &dyn DataStore, notifier: &dyn Notifier) -> Profiles {
+        match ChipLibrary::read_profiles(storage) {
+            Ok(profiles) => profiles,
+            Err(text) => {
+                let _ = storage.set_item(PROFILES_BACKUP_KEY, &text);
+                notifier.alert("Your list of profiles could not be read, only the Default profile is available. A copy of the list was kept as a backup.");
+                Profiles::recovered(&storage.keys())
+            }
+        }
+    }
+
+    /// load a profile's save data from storage, migrating it from the format it was saved in
+    fn load_save(storage: &dyn DataStore, notifier: &dyn Notifier, profile: u32) -> Option<SaveData> {
+        let (text, res) = match storage.get_item(&profile_key(profile, SAVE_KEY)) {
+            Some(text) => {
+                let res = SaveData::from_json(&text);
+                (text, res)
+            }
+            None => {
+                let legacy = ChipLibrary::load_legacy_save(storage, profile)?;
+                let text = legacy.to_string();
+                (text, SaveData::from_value(legacy))
+            }
+        };
+        match res {
+            Ok(save) => Some(save),
+            Err(why) => {
+                let _ = storage.set_item(&profile_key(profile, SAVE_BACKUP_KEY), &text);
+                let msg = String::from("Your saved data could not be loaded, a copy of it was kept as a backup.\n") + &why.to_string();
+                notifier.alert(&msg);
+                None
+            }
+        }
+    }
+
+    /// before saves were versioned the pack, folder, chip limit and presets were each saved on their own
+    ///
+    /// gathers them into an unversioned save, None if none of them were saved
+    fn load_legacy_save(storage: &dyn DataStore, profile: u32) -> Option<Value> {
+        let mut obj = serde_json::Map::new();
+        for (field, key) in LEGACY_KEYS.iter() {
+            if let Some(text) = storage.get_item(&profile_key(profile, key)) {
+                let value = serde_json::from_str::<Value>(&text).unwrap_or(Value::String(text));
+                obj.insert((*field).to_owned(), value);
+            }
+        }
+        if obj.is_empty() {
+            return None;
+        }
+        obj.entry("Limit").or_insert_with(|| Value::from(DEFAULT_CHIP_LIMIT));
+        Some(Value::Object(obj))
+    }
+
+    /// old names to current ones, an old name that's also a current chip or is claimed by two chips is ignored
+    fn build_aliases(library: &HashMap<String, Rc<BattleChip>>) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        let mut claimed_twice = Vec::new();
+        for chip in library.values() {
+            for alias in chip.aliases.iter().filter(|alias| !library.contains_key(*alias)) {
+                if aliases.insert(alias.clone(), chip.name.clone()).is_some() {
+                    claimed_twice.push(alias.clone());
+                }
+            }
+        }
+        for alias in claimed_twice {
+            aliases.remove(&alias);
+        }
+        aliases
+    }
+
+    /// move chips the library has renamed over to their new names, recording each rename in the save
+    fn apply_aliases(save: &mut SaveData, aliases: &HashMap<String, String>) -> Vec<ChipRename> {
+        let mut applied = Vec::new();
+        for (from, to) in aliases.iter() {
+            if save.has_chip(from) {
+                save.rename_chip(from, to);
+                applied.push(ChipRename{from: from.clone(), to: to.clone()});
+            }
+        }
+        applied.sort_unstable_by(|a, b| a.from.cmp(&b.from));
+        save.renames.extend(applied.iter().cloned());
+        applied
+    }
+
+    /// look up every saved chip in the library, warning about any that don't exist anymore
+    ///
+    /// unavailable chips are held on to as they were saved instead
+    fn unpack_save(mut save: SaveData, library: &HashMap<String, Rc<BattleChip>>, aliases: &HashMap<String, String>, unavailable: &Unavailable, notifier: &dyn Notifier) -> LoadedData {
+        let applied = ChipLibrary::apply_aliases(&mut save, aliases);
+        if !applied.is_empty() {
+            let mut msg = String::from("The library renamed some of your chips, your copies now go by the new names:");
+            for rename in applied.iter() {
+                msg.push('\n');
+                msg.push_str(&rename.from);
+                msg.push_str(" -> ");
+                msg.push_str(&rename.to);
+            }
+            notifier.alert(&msg);
+        }
+        let custom = ChipLibrary::unpack_custom(&save.custom_chips, library, notifier);
+        let library = &ChipLibrary::with_custom(library, &custom);
+        let renamed = ChipLibrary::rename_unknown(&mut save, library, unavailable, notifier) || !applied.is_empty();
+        let mut held = HeldChips::default();
+        let mut pack = HashMap::with_capacity(save.pack.len());
+        for (name, SavedPackChip{owned, used}) in save.pack {
+            match library.get(&name) {
+                Some(chip) => {
+                    pack.insert(name, PackChip{owned, used, chip: Rc::clone(chip)});
+                }
+                None if unavailable.contains(&name) => {
+                    held.pack.insert(name, SavedPackChip{owned, used});
+                }
+                None => ChipLibrary::warn_missing_pack(notifier, &name, owned, used),
+            }
+        }
+
+        let mut folder = Vec::with_capacity(save.folder.len());
+        for SavedFolderChip{name, used} in save.folder {
+            match library.get(&name) {
+                Some(chip) => {
+                    let chip = Rc::clone(chip);
+                    folder.push(FolderChip{name, used, chip});
+                }
+                None if unavailable.contains(&name) => held.folder.push(SavedFolderChip{name, used}),
+                None => ChipLibrary::warn_missing_fldr(notifier, &name, used),
+            }
+        }
+
+        LoadedData {
+            pack,
+            folder,
+            limit: save.limit,
+            log: save.log,
+            presets: save.presets,
+            renames: save.renames,
+            custom,
+            held,
+            renamed,
+        }
+    }
+
+    /// custom chips keyed by name, any the library now has a chip of its own for are dropped
+    fn unpack_custom(chips: &[BattleChip], library: &HashMap<String, Rc<BattleChip>>, notifier: &dyn Notifier) -> HashMap<String, Rc<BattleChip>> {
+        let mut custom = HashMap::with_capacity(chips.len());
+        for chip in chips {
+            if library.contains_key(&chip.name) {
+                let msg = String::from("The library now has a chip named \"") + &chip.name + "\", it's used instead of your custom chip";
+                notifier.alert(&msg);
+                continue;
+            }
+            custom.insert(chip.name.clone(), Rc::new(chip.as_custom()));
+        }
+        custom
+    }
+
+    /// the library with custom chips added, for looking up saved chips
+    fn with_custom(library: &HashMap<String, Rc<BattleChip>>, custom: &HashMap<String, Rc<BattleChip>>) -> HashMap<String, Rc<BattleChip>> {
+        let mut chips = library.clone();
+        chips.extend(custom.iter().map(|(name, chip)| (name.clone(), Rc::clone(chip))));
+        chips
+    }
+
+    /// names in the save that aren't in the library
+    fn unknown_chips(save: &SaveData, library: &HashMap<String, Rc<BattleChip>>) -> Vec<String> {
+        save.chip_names().into_iter().filter(|name| !library.contains_key(*name)).map(String::from).collect()
+    }
+
+    /// offer to swap each chip the library doesn't have for the closest name it does, unavailable chips are left be
+    ///
+    /// returns true if anything was renamed
+    fn rename_unknown(save: &mut SaveData, library: &HashMap<String, Rc<BattleChip>>, unavailable: &Unavailable, notifier: &dyn Notifier) -> bool {
+        let mut renamed = false;
+        for name in ChipLibrary::unknown_chips(save, library).into_iter().filter(|name| !unavailable.contains(name)) {
+            let suggestion = match fuzzy::suggest(&name, library.keys()) {
+                Some(suggestion) => suggestion,
+                None => continue,
+            };
+            let mut msg = String::from("There's a chip named \"");
+            msg.push_str(&name);
+            msg.push_str("\" that isn't in the library, did you mean \"");
+            msg.push_str(suggestion);
+            msg.push_str("\"?");
+            if notifier.confirm(&msg) {
+                save.rename_chip(&name, suggestion);
+                renamed = true;
             }
-            
         }
-        Some(to_ret)
+        renamed
     }
 
-    /// load the folder size from local storage
-    fn load_chip_limit(storage: &web_sys::Storage) -> Option<usize> {
-        let limit_str: String = storage.get_item("chip_limit").ok().flatten()?;
-        limit_str.parse::<usize>().ok()
+    /// replace the pack, folder, chip limit, presets, and log
+    fn apply_loaded(&self, loaded: LoadedData) {
+        let folder = self.folder.try_borrow_mut();
+        let pack = self.pack.try_borrow_mut();
+        let presets = self.presets.try_borrow_mut();
+        let log = self.combat_log.try_borrow_mut();
+        let (mut folder, mut pack, mut presets, mut log) = match (folder, pack, presets, log) {
+            (Ok(folder), Ok(pack), Ok(presets), Ok(log)) => (folder, pack, presets, log),
+            _ => {
+                self.notifier.alert("failed to borrow folder or pack, inform Major");
+                return;
+            }
+        };
+        *folder = loaded.folder;
+        *pack = loaded.pack;
+        *presets = loaded.presets;
+        if let Ok(mut renames) = self.renames.try_borrow_mut() {
+            *renames = loaded.renames;
+        }
+        if let Ok(mut custom) = self.custom_chips.try_borrow_mut() {
+            *custom = loaded.custom;
+        }
+        if let Ok(mut held) = self.held.try_borrow_mut() {
+            *held = loaded.held;
+        }
+        log.clear();
+        for entry in loaded.log {
+            log.push(entry.time, entry.event);
+        }
+        self.chip_limit.store(loaded.limit, Ordering::Relaxed);
     }
 
-    fn warn_missing_pack(name: &str, owned: u32, used: u32) {
+    /// the current pack, folder, chip limit, and presets in their saved form
+    fn to_save_data(&self, include_log: bool) -> Result<SaveData, &'static str> {
+        let (folder, pack, presets, log) = match (self.folder.try_borrow(), self.pack.try_borrow(), self.presets.try_borrow(), self.combat_log.try_borrow()) {
+            (Ok(folder), Ok(pack), Ok(presets), Ok(log)) => (folder, pack, presets, log),
+            _ => return Err("Failed to borrow the folder, pack, presets, or combat log, inform Major"),
+        };
+        let held = self.held.try_borrow().map(|held| held.clone()).unwrap_or_default();
+        Ok(SaveData {
+            version: save_format::SAVE_VERSION,
+            folder: folder.iter().map(|chip| SavedFolderChip{name: chip.name.clone(), used: chip.used}).chain(held.folder).collect(),
+            pack: pack.iter().map(|(name, chip)| (name.clone(), SavedPackChip{owned: chip.owned, used: chip.used})).chain(held.pack).collect(),
+            limit: self.chip_limit.load(Ordering::Relaxed),
+            log: if include_log {log.entries().to_vec()} else {Vec::new()},
+            presets: presets.clone(),
+            renames: self.renames.try_borrow().map(|renames| renames.clone()).unwrap_or_default(),
+            custom_chips: self.custom_chips(),
+        })
+    }
+
+    fn warn_missing_pack(notifier: &dyn Notifier, name: &str, owned: u32, used: u32) {
 
         let mut msg = String::from("Your pack had a chip named \"");
         msg.push_str(name);
@@ -191,51 +610,66 @@ impl ChipLibrary {
         msg.push_str(" (of which ");
         msg.push_str(&used.to_string());
         msg.push_str(" were used)");
-        unsafe{util::alert(&msg)};
+        notifier.alert(&msg);
     }
 
-    fn warn_missing_fldr(name: &str, used: bool) {
+    fn warn_missing_fldr(notifier: &dyn Notifier, name: &str, used: bool) {
         let used_unused = if used {"used"} else {"unused"};
         let mut msg = String::from("Your folder had a chip named \"");
         msg.push_str(name);
         msg.push_str("\", this no longer exists in the library, you had it marked as: ");
         msg.push_str(used_unused);
-        unsafe{util::alert(&msg)};
+        notifier.alert(&msg);
     }
 
     /// add a copy of a chip to the pack
     pub(crate) fn add_copy_to_pack(&self, name: &str) -> Option<u32> {
         
+        let before = self.snapshot();
         let mut pack = self.pack.try_borrow_mut().ok()?;
 
         if let Some(chip) = pack.get_mut(name) {
             chip.owned += 1;
-            return Some(chip.owned);
+            let owned = chip.owned;
+            drop(pack);
+            self.record(Command::AddCopyToPack(name.to_owned()), before);
+            self.change_since_last_save.store(true, Ordering::Relaxed);
+            return Some(owned);
         }
         //else not already in pack
-        let lib_chip = self.library.get(name)?;
+        let lib_chip = self.find_chip(name)?;
         pack.insert(name.to_owned(), PackChip{
             used: 0,
             owned: 1,
-            chip: Rc::clone(lib_chip),
+            chip: lib_chip,
         });
+        drop(pack);
+        self.record(Command::AddCopyToPack(name.to_owned()), before);
         self.change_since_last_save.store(true, Ordering::Relaxed);
         Some(1)
     }
 
     /// returned bool indicates if it was the last chip of that kind in the pack
     pub(crate) fn move_to_folder(&self, name: &str) -> Result<bool, &'static str> {
+        let before = self.snapshot();
+        let last_copy = self.move_chip_to_folder(name)?;
+        self.record(Command::MoveToFolder(name.to_owned()), before);
+        Ok(last_copy)
+    }
+
+    /// move_to_folder without recording it in the undo history
+    fn move_chip_to_folder(&self, name: &str) -> Result<bool, &'static str> {
         let folder = self.folder.try_borrow_mut();
         let pack = self.pack.try_borrow_mut();
         let (mut folder, mut pack) = match (folder,pack) {
             (Ok(folder), Ok(pack)) => (folder,pack),
             _ => {return Err("failed to borrow folder or pack, inform Major")}
         };
-        if self.chip_limit.load(Ordering::Relaxed) <= folder.len() {
+        if self.chip_limit.load(Ordering::Relaxed) <= folder.len() + self.held_in_folder() {
             return Err("Your folder is full");
         }
 
-        let chip = self.library.get(name).ok_or("No chip with that name exists")?;
+        let chip = self.find_chip(name).ok_or("No chip with that name exists")?;
         let pack_chip = pack.get_mut(name).ok_or("There are no copies of that chip in your pack")?;
 
         if pack_chip.used >= pack_chip.owned {
@@ -261,23 +695,22 @@ impl ChipLibrary {
         let folder_chip = FolderChip {
             name: name.to_owned(),
             used: false,
-            chip: Rc::clone(chip),
+            chip,
         };
 
         folder.push(folder_chip);
         drop(folder);
         self.change_since_last_save.store(true, Ordering::Relaxed);
         self.change_since_last_group_post.store(true, Ordering::Relaxed);
-        if pack_chip.owned != 0 {
-            return Ok(false);
+        let last_copy = pack_chip.owned == 0;
+        if last_copy {
+            pack.remove(name);
         }
-        //else it is zero
-        drop(pack_chip);
-        pack.remove(name);
-        Ok(true)
+        Ok(last_copy)
     }
 
     pub(crate) fn flip_used_folder(&self, idx: usize) {
+        let before = self.snapshot();
         let mut folder = match self.folder.try_borrow_mut() {
             Ok(folder) => folder,
             Err(_) => return,
@@ -287,30 +720,56 @@ impl ChipLibrary {
             None => return,
         };
         chip.used = !chip.used;
+        let name = chip.name.clone();
+        let command = Command::MarkFolderChip(name.clone(), chip.used);
+        let event = if chip.used {
+            LogEvent::FolderChipUsed{name}
+        } else {
+            LogEvent::FolderChipUnused{name}
+        };
+        drop(folder);
+        self.log(event);
+        self.record(command, before);
         self.change_since_last_group_post.store(true, Ordering::Relaxed);
         self.change_since_last_save.store(true, Ordering::Relaxed);
     }
 
+    /// record an event in the combat log
+    pub(crate) fn log(&self, event: LogEvent) {
+        if let Ok(mut log) = self.combat_log.try_borrow_mut() {
+            log.push(self.clock.now(), event);
+        }
+    }
+
+    pub(crate) fn log_roll(&self, roll: &ChipRoll) {
+        self.log(LogEvent::Rolled{
+            name: roll.chip.clone(),
+            hits: roll.hits.iter().map(|hit| hit.total).collect(),
+            total: roll.total,
+        });
+    }
+
     /// returned bool indicates if it was the last chip of that kind in the pack
     pub(crate) fn remove_from_pack(&self, name:&str) -> Result<bool, &'static str> {
+        let before = self.snapshot();
         let mut pack = match self.pack.try_borrow_mut() {
             Ok(pack) => pack,
             Err(_) => return Err("Failed to borrow pack mutably, inform Major"),
         };
         let pack_chip = pack.get_mut(name).ok_or("No chip with that name in the pack")?;
         pack_chip.owned -= 1;
-        if pack_chip.owned != 0 {
-            return Ok(false);
+        let last_copy = pack_chip.owned == 0;
+        if last_copy {
+            pack.remove(name);
         }
-
-        //else last chip
-        drop(pack_chip);
-        pack.remove(name);
+        drop(pack);
+        self.record(Command::RemoveFromPack(name.to_owned()), before);
         self.change_since_last_save.store(true, Ordering::Relaxed);
-        Ok(true)
+        Ok(last_copy)
     }
 
     pub(crate) fn mark_pack_copy_unused(&self, name: &str) -> Result<u32, &'static str> {
+        let before = self.snapshot();
         let mut pack = match self.pack.try_borrow_mut() {
             Ok(pack) => pack,
             Err(_) => return Err("Failed to borrow pack mutably, inform Major"),
@@ -320,11 +779,25 @@ impl ChipLibrary {
             return Err("No used copies of that chip in you pack");
         }
         chip.used -= 1;
+        let used = chip.used;
+        drop(pack);
+        self.log(LogEvent::PackCopyUnused{name: name.to_owned(), used});
+        self.record(Command::MarkPackCopyUnused(name.to_owned()), before);
         self.change_since_last_save.store(true, Ordering::Relaxed);
-        Ok(chip.used)
+        Ok(used)
     }
     /// returned bool indicates if it was used or not
     pub(crate) fn return_fldr_chip_to_pack(&self, index: usize) -> Result<bool, &'static str> {
+        let before = self.snapshot();
+        let (name, used) = self.return_chip_to_pack(index)?;
+        self.record(Command::ReturnToPack(name), before);
+        Ok(used)
+    }
+
+    /// return_fldr_chip_to_pack without recording it in the undo history
+    ///
+    /// returns the chip's name and if it was used
+    fn return_chip_to_pack(&self, index: usize) -> Result<(String, bool), &'static str> {
         let pack = self.pack.try_borrow_mut();
         let folder = self.folder.try_borrow_mut();
 
@@ -339,9 +812,10 @@ impl ChipLibrary {
 
         let fldr_chip = folder.remove(index);
         let used_incr = if fldr_chip.used {1} else {0};
-        if let Some(pack_chip) = pack.get_mut(&fldr_chip.name) {
+        let pack_used = if let Some(pack_chip) = pack.get_mut(&fldr_chip.name) {
             pack_chip.owned += 1;
             pack_chip.used += used_incr;
+            pack_chip.used
         } else {
             //else no coppies already in pack
             let pack_chip = PackChip {
@@ -349,14 +823,21 @@ impl ChipLibrary {
                 used: used_incr,
                 chip: fldr_chip.chip,
             };
-            pack.insert(fldr_chip.name, pack_chip);
+            pack.insert(fldr_chip.name.clone(), pack_chip);
+            used_incr
+        };
+        drop(folder);
+        drop(pack);
+        if fldr_chip.used {
+            self.log(LogEvent::PackCopyUsed{name: fldr_chip.name.clone(), used: pack_used});
         }
         self.change_since_last_save.store(true, Ordering::Relaxed);
         self.change_since_last_group_post.store(true, Ordering::Relaxed);
-        Ok(fldr_chip.used)
+        Ok((fldr_chip.name, fldr_chip.used))
     }
 
     pub(crate) fn clear_folder(&self) -> usize {
+        let before = self.snapshot();
         let pack = self.pack.try_borrow_mut();
         let folder = self.folder.try_borrow_mut();
 
@@ -365,15 +846,17 @@ impl ChipLibrary {
             _ => return 0,
         };
         let returned_count = folder.len();
+        let mut used_returns = Vec::new();
         for fldr_chip in folder.drain(..) {
             
             //number to add to the used_count
             let used_incr = if fldr_chip.used {1} else {0};
 
-            if let Some(pack_chip) = pack.get_mut(&fldr_chip.name) {
+            let pack_used = if let Some(pack_chip) = pack.get_mut(&fldr_chip.name) {
                 
                 pack_chip.owned += 1;
                 pack_chip.used += used_incr;
+                pack_chip.used
 
             } else {
                 //else no coppies already in the pack
@@ -382,10 +865,21 @@ impl ChipLibrary {
                     used: used_incr,
                     chip: fldr_chip.chip,
                 };
-                pack.insert(fldr_chip.name, pack_chip);
+                pack.insert(fldr_chip.name.clone(), pack_chip);
+                used_incr
+            };
+
+            if fldr_chip.used {
+                used_returns.push(LogEvent::PackCopyUsed{name: fldr_chip.name, used: pack_used});
             }
         }
+        drop(folder);
+        drop(pack);
+        for event in used_returns {
+            self.log(event);
+        }
         if returned_count > 0 {
+            self.record(Command::ClearFolder, before);
             self.change_since_last_save.store(true, Ordering::Relaxed);
             self.change_since_last_group_post.store(true, Ordering::Relaxed);
         }
@@ -393,11 +887,12 @@ impl ChipLibrary {
     }
 
     pub(crate) fn jack_out(&self) -> u32 {
+        let before = self.snapshot();
         let mut accumulator: u32 = 0;
         let mut folder = match self.folder.try_borrow_mut() {
             Ok(folder) => folder,
             Err(_) => {
-                unsafe{util::alert("failed to borrow folder mutably, inform Major")};
+                self.notifier.alert("failed to borrow folder mutably, inform Major");
                 return 0;
             }
         };
@@ -412,7 +907,7 @@ impl ChipLibrary {
         let mut pack = match self.pack.try_borrow_mut() {
             Ok(pack) => pack,
             Err(_) => {
-                unsafe{util::alert("failed to borrow pack mutably, inform Major")};
+                self.notifier.alert("failed to borrow pack mutably, inform Major");
                 return 0;
             }
         };
@@ -420,8 +915,11 @@ impl ChipLibrary {
             accumulator += chip.used as u32;
             chip.used = 0;
         }
+        drop(pack);
+        self.log(LogEvent::JackOut{count: accumulator});
 
         if accumulator > 0 {
+            self.record(Command::JackOut, before);
             self.change_since_last_save.store(true, Ordering::Relaxed);
             self.change_since_last_group_post.store(true, Ordering::Relaxed);
         }
@@ -438,162 +936,295 @@ impl ChipLibrary {
         };
 
         
-        if new_limit < folder.len() {
+        if new_limit < folder.len() + self.held_in_folder() {
             return Err("You must remove chips from your folder first");
         }
 
-        if new_limit == self.chip_limit.load(Ordering::Relaxed) {
+        let old_limit = self.chip_limit.load(Ordering::Relaxed);
+        if new_limit == old_limit {
             return Ok(false);
         }
 
         self.chip_limit.store(new_limit, Ordering::Relaxed);
         self.change_since_last_save.store(true, Ordering::Relaxed);
+        let before = Snapshot {
+            limit: old_limit,
+            ..self.snapshot()
+        };
+        self.record(Command::UpdateChipLimit(new_limit), before);
         Ok(true)
     }
 
-    pub(crate) fn export_json(&self) {
-        let (folder, pack) = unsafe {
-            let folder = self.folder.try_borrow_unguarded().unchecked_unwrap();
-            let pack = self.pack.try_borrow_unguarded().unchecked_unwrap();
-            (folder, pack)
+    /// save the current folder as a preset, overwriting any preset with the same name
+    pub(crate) fn save_preset(&self, name: &str) -> Result<(), &'static str> {
+        let chips = match self.folder.try_borrow() {
+            Ok(folder) => folder.iter().map(|chip| chip.name.clone()).collect::<Vec<String>>(),
+            Err(_) => return Err("failed to borrow folder, inform Major"),
         };
-        let limit = self.chip_limit.load(Ordering::Relaxed);
-        let to_save = json!({
-            "Folder": folder,
-            "Pack": pack,
-            "Limit": limit,
-        });
-        let data = serde_json::to_string_pretty(&to_save).unwrap();
-        crate::util::save_json(data);
+        let mut presets = self.presets.try_borrow_mut().map_err(|_| "failed to borrow presets, inform Major")?;
+        presets.save(name, chips)?;
+        self.change_since_last_save.store(true, Ordering::Relaxed);
+        Ok(())
     }
 
-    pub(crate) fn import_json(&self, data: String) -> Result<(), &'static str> {
-        self.erase_data();
-        let save_data = serde_json::from_str::<Value>(&data).map_err(|_| "Ill formed save data")?;
-        let limit = save_data["Limit"].as_u64().ok_or("Ill formed save data")?;
-        if limit > 45 {
-            return Err("Ill formed save data, folder limit too high");
-        }
-        self.chip_limit.store(limit as usize, Ordering::Relaxed);
-        if let Some(folder_chips) = save_data["Folder"].as_array() {
-            self.parse_folder(folder_chips)?;
-        }
-        if let Some(pack_chips) = save_data["Pack"].as_object() {
-            self.parse_pack(pack_chips)?;
-        }
+    pub(crate) fn rename_preset(&self, name: &str, new_name: &str) -> Result<(), &'static str> {
+        let mut presets = self.presets.try_borrow_mut().map_err(|_| "failed to borrow presets, inform Major")?;
+        presets.rename(name, new_name)?;
         self.change_since_last_save.store(true, Ordering::Relaxed);
         Ok(())
     }
 
-    fn parse_pack(&self, data: &serde_json::Map<String, Value>) -> Result<(), &'static str> {
-        let mut pack = match self.pack.try_borrow_mut() {
-            Ok(pack) => pack,
-            Err(_) => return Err("failed to borrow pack mutably, inform Major"),
-        };
+    pub(crate) fn duplicate_preset(&self, name: &str, new_name: &str) -> Result<(), &'static str> {
+        let mut presets = self.presets.try_borrow_mut().map_err(|_| "failed to borrow presets, inform Major")?;
+        presets.duplicate(name, new_name)?;
+        self.change_since_last_save.store(true, Ordering::Relaxed);
+        Ok(())
+    }
 
-        for (name, chip) in data.iter() {
-            let owned = chip["owned"].as_u64().ok_or("Ill formed save data")?;
-            let used = chip["used"].as_u64().ok_or("Ill formed save data")?;
-            if used > owned {
-                return Err("Ill formed save data");
+    pub(crate) fn delete_preset(&self, name: &str) -> Result<(), &'static str> {
+        let mut presets = self.presets.try_borrow_mut().map_err(|_| "failed to borrow presets, inform Major")?;
+        presets.delete(name)?;
+        self.change_since_last_save.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// return the whole folder to the pack, then move the preset's chips back out of it
+    ///
+    /// follows the same rules as moving chips by hand, returns the chips the pack couldn't supply and why
+    pub(crate) fn switch_preset(&self, name: &str) -> Result<Vec<String>, &'static str> {
+        let chips = match self.presets.try_borrow() {
+            Ok(presets) => presets.get(name).ok_or("No preset with that name exists")?.chips.clone(),
+            Err(_) => return Err("failed to borrow presets, inform Major"),
+        };
+        let before = self.snapshot();
+
+        let folder_len = self.folder.try_borrow().map_err(|_| "failed to borrow folder, inform Major")?.len();
+        for _ in 0..folder_len {
+            // a half emptied folder is worse than no switch at all
+            if let Err(why) = self.return_chip_to_pack(0) {
+                self.restore(before)?;
+                return Err(why);
             }
-            if let Some(lib_chip) = self.library.get(name) {
-                let pack_chip = PackChip{
-                    owned: owned as u32,
-                    used: used as u32,
-                    chip: Rc::clone(lib_chip)
-                };
-                pack.insert(name.clone(), pack_chip);
-            } else {
-                let msg = String::from("Ignoring a chip your pack has that doesn't exist anymore: ") + name;
-                unsafe{crate::util::alert(&msg)};
+        }
+
+        let mut unsupplied = Vec::new();
+        for chip in chips.iter() {
+            if let Err(why) = self.move_chip_to_folder(chip) {
+                unsupplied.push(String::from(chip) + ": " + why);
             }
         }
-        Ok(())
+
+        if let Ok(mut presets) = self.presets.try_borrow_mut() {
+            presets.set_active(name);
+        }
+        self.record(Command::SwitchPreset(name.to_owned()), before);
+        self.change_since_last_save.store(true, Ordering::Relaxed);
+        self.change_since_last_group_post.store(true, Ordering::Relaxed);
+        Ok(unsupplied)
     }
 
-    fn parse_folder(&self, data: &Vec<serde_json::Value>) -> Result<(), &'static str> {
-        let mut folder = match self.folder.try_borrow_mut() {
-            Ok(folder) => folder,
-            Err(_) => return Err("Failed to borrow folder mutably, inform Major"),
+    /// the current pack, folder, and chip limit
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        let mut pack = match self.pack.try_borrow() {
+            Ok(pack) => pack.iter().map(|(name, chip)| (name.clone(), chip.owned, chip.used)).collect(),
+            Err(_) => Vec::new(),
+        };
+        // sorted so snapshots of the same pack compare equal however its map is laid out
+        pack.sort_unstable();
+        let folder = match self.folder.try_borrow() {
+            Ok(folder) => folder.iter().map(|chip| (chip.name.clone(), chip.used)).collect(),
+            Err(_) => Vec::new(),
         };
+        Snapshot {
+            pack,
+            folder,
+            limit: self.chip_limit.load(Ordering::Relaxed),
+        }
+    }
 
-        if data.len() > self.chip_limit.load(Ordering::Relaxed) as usize {
-            return Err("Chip limit was set lower than the actual folder size");
+    /// called once the command is done, so the current state is what it left behind
+    fn record(&self, command: Command, before: Snapshot) {
+        let after = self.snapshot();
+        if let Ok(mut history) = self.history.try_borrow_mut() {
+            history.record(command, before, after);
         }
+    }
 
-        for chip in data.iter() {
-            let name = chip["name"].as_str().ok_or("Ill formed save data")?;
-            let used = chip["used"].as_bool().ok_or("Ill formed save data")?;
-            if let Some(lib_chip) = self.library.get(name) {
-                folder.push(FolderChip{
-                    name: name.to_owned(),
-                    used,
-                    chip: Rc::clone(lib_chip)
-                });
-            } else {
-                let msg = String::from("Ignoring a chip your folder has that doesn't exist anymore: ") + name;
-                unsafe{crate::util::alert(&msg)};
+    /// put the pack, folder, and chip limit back the way they were
+    fn restore(&self, state: Snapshot) -> Result<(), &'static str> {
+        let folder = self.folder.try_borrow_mut();
+        let pack = self.pack.try_borrow_mut();
+        let (mut folder, mut pack) = match (folder, pack) {
+            (Ok(folder), Ok(pack)) => (folder, pack),
+            _ => return Err("failed to borrow folder or pack, inform Major"),
+        };
+
+        pack.clear();
+        for (name, owned, used) in state.pack {
+            // snapshots only ever contain chips from the library or custom chips
+            if let Some(chip) = self.find_chip(&name) {
+                pack.insert(name, PackChip{owned, used, chip});
+            }
+        }
+        folder.clear();
+        for (name, used) in state.folder {
+            if let Some(chip) = self.find_chip(&name) {
+                folder.push(FolderChip{name, used, chip});
             }
         }
+        self.chip_limit.store(state.limit, Ordering::Relaxed);
+        self.change_since_last_save.store(true, Ordering::Relaxed);
+        self.change_since_last_group_post.store(true, Ordering::Relaxed);
         Ok(())
     }
 
-    pub(crate) fn save_data(&self) -> Result<(), &'static str> {
-        if self.change_since_last_save.load(Ordering::Relaxed) == false {
-            return Ok(());
-        }
-        
-        let window = web_sys::window().ok_or("failed to get window")?;
-        let storage = match window.local_storage().ok().flatten() {
-            Some(storage) => storage,
-            None => return Err("could not get storage"),
+    /// undo the last change to the pack, folder, or chip limit
+    ///
+    /// returns the command that was undone, None if there was nothing to undo
+    pub(crate) fn undo(&self) -> Result<Option<Command>, &'static str> {
+        let current = self.snapshot();
+        let mut history = self.history.try_borrow_mut().map_err(|_| "failed to borrow history, inform Major")?;
+        let (command, state) = match history.undo(&current) {
+            Some(undone) => undone,
+            None => return Ok(None),
         };
+        drop(history);
+        self.restore(state)?;
+        Ok(Some(command))
+    }
 
-        let pack = match self.pack.try_borrow() {
-            Ok(pack) => pack,
-            Err(_) => return Err("Failed to borrow pack, inform Major"),
+    /// redo the last undone change
+    ///
+    /// returns the command that was redone, None if there was nothing to redo
+    pub(crate) fn redo(&self) -> Result<Option<Command>, &'static str> {
+        let current = self.snapshot();
+        let mut history = self.history.try_borrow_mut().map_err(|_| "failed to borrow history, inform Major")?;
+        let (command, state) = match history.redo(&current) {
+            Some(redone) => redone,
+            None => return Ok(None),
         };
+        drop(history);
+        self.restore(state)?;
+        Ok(Some(command))
+    }
 
-        let pack_text = serde_json::to_string(&*pack).map_err(|_|"Failed to serialize pack")?;
-        storage.set_item("pack", &pack_text).unwrap();
-        // no longer needed, free memory
-        drop(pack_text);
-        drop(pack);
+    /// the pack, folder, chip limit, presets, and combat log as pretty printed json
+    pub(crate) fn export_json(&self) -> Result<String, &'static str> {
+        Ok(self.to_save_data(true)?.to_json_pretty())
+    }
 
-        let folder = match self.folder.try_borrow() {
-            Ok(folder) => folder,
-            Err(_) => return Err("Failed to borrow folder, inform Major"),
-        };
+    /// what importing a file would change, nothing is touched
+    pub(crate) fn preview_import(&self, incoming: &SaveData, mode: ImportMode) -> Result<ImportPreview, &'static str> {
+        let current = self.to_save_data(true)?;
+        let merged = merge::merge(&current, incoming, mode);
+        Ok(merge::preview(&current, &merged))
+    }
 
-        //have to deref then borrow to coerce to a reference from a std::cell::Ref
-        let folder_text = match serde_json::to_string(&*folder) {
-            Ok(folder_text) => folder_text,
-            Err(_) => return Err("Failed to serialize folder"),
-        };//.map_err(|_| "Failed to serialize folder")?;
+    /// fix up chip names in data about to be imported, before it's previewed
+    ///
+    /// chips the library renamed are moved to their new names, misspelled chips are renamed if the user agrees, anything still not in the library
+    /// is left out and returned so the preview can say so
+    pub(crate) fn resolve_import(&self, save: &mut SaveData) -> Vec<String> {
+        ChipLibrary::apply_aliases(save, &self.aliases);
+        // custom chips coming in with the import count as known too
+        let mut chips = self.lookup();
+        chips.extend(ChipLibrary::unpack_custom(&save.custom_chips, &self.library, &*self.notifier));
+        ChipLibrary::rename_unknown(save, &chips, &self.unavailable, &*self.notifier);
+        let mut unknown = ChipLibrary::unknown_chips(save, &chips);
+        unknown.retain(|name| !self.unavailable.contains(name));
+        for name in unknown.iter() {
+            save.remove_chip(name);
+        }
+        unknown
+    }
 
-        storage.set_item("folder", &folder_text).unwrap();
+    /// import a file that's already been checked with SaveData::from_json and resolve_import
+    pub(crate) fn import_save(&self, incoming: &SaveData, mode: ImportMode) -> Result<(), &'static str> {
+        let merged = merge::merge(&self.to_save_data(true)?, incoming, mode);
+        // the backup of a save that couldn't be read is kept, importing doesn't mean giving up on it
+        self.reset_data()?;
+        let loaded = ChipLibrary::unpack_save(merged, &self.library, &self.aliases, &self.unavailable, &*self.notifier);
+        self.apply_loaded(loaded);
+        self.change_since_last_save.store(true, Ordering::Relaxed);
+        self.change_since_last_group_post.store(true, Ordering::Relaxed);
+        Ok(())
+    }
 
-        drop(folder);
-        drop(folder_text);
+    pub(crate) fn save_data(&self) -> Result<(), &'static str> {
+        if self.change_since_last_save.load(Ordering::Relaxed) == false {
+            return Ok(());
+        }
 
-        let chip_limit = self.chip_limit.load(Ordering::Relaxed).to_string();
-        storage.set_item("chip_limit", &chip_limit).unwrap();
+        // the log is only kept for the session
+        let save_text = self.to_save_data(false)?.to_json();
+        self.storage.set_item(&self.key(SAVE_KEY), &save_text)?;
+
+        // everything from the old keys is in the versioned save now
+        for (_, key) in LEGACY_KEYS.iter() {
+            self.storage.remove_item(&self.key(key));
+        }
 
         self.change_since_last_save.store(false, Ordering::Relaxed);
         Ok(())
     }
 
-    pub(crate) fn export_txt(&self) {
+    /// read pasted "Export Txt" text into save data that can be previewed and imported
+    ///
+    /// names are matched to the library ignoring case, ones it doesn't have are left for resolve_import
+    pub(crate) fn parse_txt(&self, text: &str) -> Result<SaveData, String> {
+        let parsed = text_format::parse_txt(text)?;
+        let mut save = SaveData::default();
+
+        for entry in parsed.folder {
+            // checked before copies are made, so "Cannon x4000000000" is refused without filling memory
+            let total = save.folder.len().saturating_add(entry.count as usize);
+            if total > MAX_CHIP_LIMIT {
+                return Err(String::from("The folder has at least ") + &total.to_string() + " chips, no folder can hold more than 45");
+            }
+            let name = self.find_chip_name(&entry.name).unwrap_or(entry.name);
+            for idx in 0..entry.count {
+                save.folder.push(SavedFolderChip{name: name.clone(), used: idx < entry.used});
+            }
+        }
+        for entry in parsed.pack {
+            let name = self.find_chip_name(&entry.name).unwrap_or(entry.name);
+            let chip = save.pack.entry(name.clone()).or_insert(SavedPackChip{owned: 0, used: 0});
+            match (chip.owned.checked_add(entry.count), chip.used.checked_add(entry.used)) {
+                (Some(owned), Some(used)) => {
+                    chip.owned = owned;
+                    chip.used = used;
+                }
+                _ => return Err(String::from("The pack has too many copies of ") + &name),
+            }
+        }
+
+        save.limit = self.chip_limit.load(Ordering::Relaxed).max(save.folder.len());
+        // the text has no presets, keep the current ones so replacing doesn't lose them
+        if let Ok(presets) = self.presets.try_borrow() {
+            save.presets = presets.clone();
+        }
+        Ok(save)
+    }
+
+    /// the library's spelling of a chip name, ignoring case
+    fn find_chip_name(&self, name: &str) -> Option<String> {
+        if self.find_chip(name).is_some() {
+            return Some(name.to_owned());
+        }
+        if let Some(key) = self.library.keys().find(|key| key.eq_ignore_ascii_case(name)) {
+            return Some(key.clone());
+        }
+        self.custom_chips.try_borrow().ok()?.keys().find(|key| key.eq_ignore_ascii_case(name)).cloned()
+    }
+
+    /// the human readable version of the folder and pack
+    pub(crate) fn export_txt(&self) -> Result<String, &'static str> {
         let folder = self.folder.try_borrow();
         let pack = self.pack.try_borrow();
 
         let (folder, pack) = match (folder, pack) {
             (Ok(folder), Ok(pack)) => (folder, pack),
-            _ => {
-                unsafe{util::alert("failed to borrow folder or pack, inform Major")};
-                return;
-            }
+            _ => return Err("failed to borrow folder or pack, inform Major"),
         };
 
         //let mut to_save_text = String::with_capacity(100);
@@ -628,7 +1259,7 @@ impl ChipLibrary {
 
             if chip.used > 0 {
                 to_ret.push_str(" (");
-                to_ret.push_str(&chip.owned.to_string());
+                to_ret.push_str(&chip.used.to_string());
                 to_ret.push_str(" Used)");
             }
             to_ret
@@ -639,50 +1270,342 @@ impl ChipLibrary {
             String::new()
         };
         text_to_save.push_str(&pack_text);
-        crate::util::save_txt(text_to_save);
+        Ok(text_to_save)
     }
 
     pub(crate) fn erase_data(&self) {
-        self.chip_limit.store(12, Ordering::Relaxed);
+        if let Err(why) = self.reset_data() {
+            self.notifier.alert(why);
+            return;
+        }
+
+        // only the active profile is erased
+        self.storage.remove_item(&self.key(SAVE_KEY));
+        self.storage.remove_item(&self.key(SAVE_BACKUP_KEY));
+        for (_, key) in LEGACY_KEYS.iter() {
+            self.storage.remove_item(&self.key(key));
+        }
+        self.change_since_last_save.store(false, Ordering::Relaxed);
+    }
+
+    /// empty the pack, folder, and everything else held in memory, storage is left alone
+    fn reset_data(&self) -> Result<(), &'static str> {
+        self.chip_limit.store(DEFAULT_CHIP_LIMIT, Ordering::Relaxed);
         let folder = self.folder.try_borrow_mut();
         let pack = self.pack.try_borrow_mut();
 
         let (mut folder, mut pack) = match (folder, pack) {
             (Ok(folder), Ok(pack)) => (folder, pack),
-            _ => {
-                unsafe{util::alert("failed to borrow folder or pack, inform Major")};
-                return;
-            }
+            _ => return Err("failed to borrow folder or pack, inform Major"),
         };
 
         folder.clear();
         pack.clear();
         drop(folder);
         drop(pack);
+        if let Ok(mut log) = self.combat_log.try_borrow_mut() {
+            log.clear();
+        }
+        if let Ok(mut history) = self.history.try_borrow_mut() {
+            history.clear();
+        }
+        if let Ok(mut presets) = self.presets.try_borrow_mut() {
+            presets.clear();
+        }
+        if let Ok(mut renames) = self.renames.try_borrow_mut() {
+            renames.clear();
+        }
+        if let Ok(mut custom) = self.custom_chips.try_borrow_mut() {
+            custom.clear();
+        }
+        if let Ok(mut held) = self.held.try_borrow_mut() {
+            *held = HeldChips::default();
+        }
+        Ok(())
+    }
+
+    /// the storage key for the active profile's data
+    fn key(&self, key: &str) -> String {
+        let profile = unsafe{self.profiles.try_borrow_unguarded().unchecked_unwrap()}.active_id();
+        profile_key(profile, key)
+    }
+
+    fn save_profiles(&self) -> Result<(), &'static str> {
+        let profiles = self.profiles.try_borrow().map_err(|_| "Failed to borrow profiles, inform Major")?;
+        let text = serde_json::to_string(&*profiles).map_err(|_| "Failed to serialize profiles")?;
+        self.storage.set_item(PROFILES_KEY, &text)
+    }
+
+    pub(crate) fn active_profile(&self) -> String {
+        unsafe{self.profiles.try_borrow_unguarded().unchecked_unwrap()}.active_name().to_owned()
+    }
+
+    /// make a new empty profile, it isn't switched to
+    pub(crate) fn create_profile(&self, name: &str) -> Result<(), &'static str> {
+        self.profiles.try_borrow_mut().map_err(|_| "Failed to borrow profiles, inform Major")?.create(name)?;
+        self.save_profiles()
+    }
 
-        let window = match web_sys::window() {
-            Some(window) => window,
-            None => return
+    pub(crate) fn rename_profile(&self, name: &str, new_name: &str) -> Result<(), &'static str> {
+        self.profiles.try_borrow_mut().map_err(|_| "Failed to borrow profiles, inform Major")?.rename(name, new_name)?;
+        self.save_profiles()
+    }
+
+    /// delete a profile and everything saved for it, the active profile cannot be deleted
+    pub(crate) fn delete_profile(&self, name: &str) -> Result<(), &'static str> {
+        let id = self.profiles.try_borrow_mut().map_err(|_| "Failed to borrow profiles, inform Major")?.remove(name)?;
+        self.storage.remove_item(&profile_key(id, SAVE_KEY));
+        self.storage.remove_item(&profile_key(id, SAVE_BACKUP_KEY));
+        self.storage.remove_item(&profile_key(id, CONFIG_KEY));
+        for (_, key) in LEGACY_KEYS.iter() {
+            self.storage.remove_item(&profile_key(id, key));
+        }
+        self.save_profiles()
+    }
+
+    /// save the active profile then load another one in its place
+    pub(crate) fn switch_profile(&self, name: &str) -> Result<(), &'static str> {
+        let id = match self.profiles.try_borrow() {
+            Ok(profiles) => {
+                let id = profiles.find(name).ok_or("No profile with that name exists")?.id;
+                if id == profiles.active_id() {
+                    return Ok(());
+                }
+                id
+            }
+            Err(_) => return Err("Failed to borrow profiles, inform Major"),
         };
-        let storage = match window.local_storage().ok().flatten() {
-            Some(storage) => storage,
-            None => return
+
+        // make sure nothing from the profile being left is lost
+        self.change_since_last_save.store(true, Ordering::Relaxed);
+        self.save_data()?;
+
+        let save = ChipLibrary::load_save(&*self.storage, &*self.notifier, id).unwrap_or_default();
+        let loaded = ChipLibrary::unpack_save(save, &self.library, &self.aliases, &self.unavailable, &*self.notifier);
+        let renamed = loaded.renamed;
+        self.apply_loaded(loaded);
+
+        // the undo history belongs to the profile being left
+        if let Ok(mut history) = self.history.try_borrow_mut() {
+            history.clear();
+        }
+
+        self.profiles.try_borrow_mut().map_err(|_| "Failed to borrow profiles, inform Major")?.set_active(id)?;
+        self.save_profiles()?;
+        self.change_since_last_save.store(renamed, Ordering::Relaxed);
+        self.change_since_last_group_post.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// export any profile's data in the same format as export_json
+    ///
+    /// profiles that aren't active are read from storage, so they have no combat log
+    pub(crate) fn export_profile_json(&self, name: &str) -> Result<String, &'static str> {
+        let profiles = self.profiles.try_borrow().map_err(|_| "Failed to borrow profiles, inform Major")?;
+        let id = profiles.find(name).ok_or("No profile with that name exists")?.id;
+        if id == profiles.active_id() {
+            drop(profiles);
+            return self.export_json();
+        }
+        drop(profiles);
+
+        let save = ChipLibrary::load_save(&*self.storage, &*self.notifier, id).unwrap_or_default();
+        Ok(save.to_json_pretty())
+    }
+
+    /// the active profile's own settings
+    pub(crate) fn settings(&self) -> ConfigOverrides {
+        let profile = unsafe{self.profiles.try_borrow_unguarded().unchecked_unwrap()}.active_id();
+        ChipLibrary::load_settings(&*self.storage, profile)
+    }
+
+    /// the settings in use for the active profile
+    pub(crate) fn config(&self) -> AppConfig {
+        self.runtime_config.resolve(&self.settings())
+    }
+
+    /// true if the group server only speaks the protocol from before versioning, as far as is known
+    pub(crate) fn is_legacy_server(&self, server: &str) -> bool {
+        is_legacy_server(&*self.storage, server)
+    }
+
+    /// the group server turned out to only speak the protocol from before versioning
+    pub(crate) fn mark_legacy_server(&self, server: &str) -> Result<(), &'static str> {
+        mark_legacy_server(&*self.storage, server)
+    }
+
+    /// replace the active profile's own settings, anything left unset uses the defaults
+    pub(crate) fn save_settings(&self, settings: &ConfigOverrides) -> Result<(), &'static str> {
+        settings.check()?;
+        if settings.is_empty() {
+            self.storage.remove_item(&self.key(CONFIG_KEY));
+            return Ok(());
+        }
+        let text = serde_json::to_string(settings).map_err(|_| "Failed to serialize settings, inform Major")?;
+        self.storage.set_item(&self.key(CONFIG_KEY), &text)
+    }
+
+    /// switch a source on or off, it takes effect once the library is loaded again
+    pub(crate) fn set_source_enabled(&self, namespace: &str, enabled: bool) -> Result<(), &'static str> {
+        if !self.sources.iter().any(|source| source.namespace == namespace) {
+            return Err("No chip source with that name exists");
+        }
+        sources::set_source_enabled(&*self.storage, namespace, enabled)?;
+        self.save_data()
+    }
+
+    /// how many unavailable chips are saved in the folder, they still take up room in it
+    fn held_in_folder(&self) -> usize {
+        self.held.try_borrow().map_or(0, |held| held.folder.len())
+    }
+
+    /// true if there's a copy of the chip in the pack or folder
+    pub(crate) fn owns(&self, name: &str) -> bool {
+        let in_pack = self.pack.try_borrow().map_or(false, |pack| pack.contains_key(name));
+        in_pack || self.folder.try_borrow().map_or(false, |folder| folder.iter().any(|chip| chip.name == name))
+    }
+
+    /// how many copies of each chip are in the folder, for sorting by it
+    pub(crate) fn folder_counts(&self) -> HashMap<String, u32> {
+        let mut counts = HashMap::new();
+        if let Ok(folder) = self.folder.try_borrow() {
+            for chip in folder.iter() {
+                *counts.entry(chip.name.clone()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// the library and the active profile's custom chips, keyed by name
+    ///
+    /// this copies the whole library, so it's only for one off jobs like imports, find_chip is for everything else
+    fn lookup(&self) -> HashMap<String, Rc<BattleChip>> {
+        match self.custom_chips.try_borrow() {
+            Ok(custom) => ChipLibrary::with_custom(&self.library, &custom),
+            Err(_) => self.library.clone(),
+        }
+    }
+
+    /// a chip from the library or one of the active profile's custom chips
+    fn find_chip(&self, name: &str) -> Option<Rc<BattleChip>> {
+        if let Some(chip) = self.library.get(name) {
+            return Some(Rc::clone(chip));
+        }
+        self.custom_chips.try_borrow().ok()?.get(name).cloned()
+    }
+
+    /// like find_chip, but custom chips shared by the rest of the group are found too
+    pub(crate) fn get_chip(&self, name: &str) -> Option<Rc<BattleChip>> {
+        self.find_chip(name).or_else(|| self.shared_chips.try_borrow().ok()?.get(name).cloned())
+    }
+
+    /// every chip in the library followed by the active profile's custom chips
+    pub(crate) fn chips(&self) -> Vec<Rc<BattleChip>> {
+        let mut chips = self.library.values().cloned().collect::<Vec<Rc<BattleChip>>>();
+        if let Ok(custom) = self.custom_chips.try_borrow() {
+            chips.extend(custom.values().cloned());
+        }
+        chips
+    }
+
+    /// the active profile's custom chips, sorted by name
+    pub(crate) fn custom_chips(&self) -> Vec<BattleChip> {
+        let mut chips = match self.custom_chips.try_borrow() {
+            Ok(custom) => custom.values().map(|chip| (**chip).clone()).collect::<Vec<BattleChip>>(),
+            Err(_) => Vec::new(),
         };
-        
-        let _ = storage.remove_item("folder");
-        let _ = storage.remove_item("pack");
-        let _ = storage.remove_item("chip_limit");
-        self.change_since_last_save.store(false, Ordering::Relaxed);
+        chips.sort_unstable();
+        chips
     }
 
-    pub(crate) fn serialize_folder(&self) -> Vec<u8> {
+    /// add a custom chip, or replace the one named replacing
+    ///
+    /// copies of a replaced chip are moved over to its new name
+    pub(crate) fn save_custom_chip(&self, mut chip: BattleChip, replacing: Option<&str>) -> Result<(), &'static str> {
+        chip.name = chip.name.trim().to_owned();
+        chip.check_custom()?;
+        if self.library.contains_key(&chip.name) {
+            return Err("The library already has a chip with that name");
+        }
+        let custom = self.custom_chips.try_borrow().map_err(|_| "Failed to borrow custom chips, inform Major")?;
+        if let Some(replacing) = replacing {
+            if !custom.contains_key(replacing) {
+                return Err("No custom chip with that name exists");
+            }
+        }
+        if custom.contains_key(&chip.name) && replacing != Some(chip.name.as_str()) {
+            return Err("You already have a custom chip with that name");
+        }
+        drop(custom);
+
+        let mut save = self.to_save_data(true)?;
+        let renamed = match replacing {
+            Some(replacing) => {
+                save.custom_chips.retain(|custom| custom.name != replacing);
+                if replacing != chip.name {
+                    save.rename_chip(replacing, &chip.name);
+                }
+                replacing != chip.name
+            }
+            None => false,
+        };
+        save.custom_chips.push(chip.as_custom());
+        self.reload(save, renamed);
+        Ok(())
+    }
+
+    /// delete a custom chip along with every copy of it
+    pub(crate) fn delete_custom_chip(&self, name: &str) -> Result<(), &'static str> {
+        let exists = self.custom_chips.try_borrow().map_err(|_| "Failed to borrow custom chips, inform Major")?.contains_key(name);
+        if !exists {
+            return Err("No custom chip with that name exists");
+        }
+        let mut save = self.to_save_data(true)?;
+        save.custom_chips.retain(|custom| custom.name != name);
+        save.remove_chip(name);
+        self.reload(save, true);
+        Ok(())
+    }
+
+    /// swap in changed save data for the active profile, the undo history is dropped if chips it names are gone
+    fn reload(&self, save: SaveData, clear_history: bool) {
+        let loaded = ChipLibrary::unpack_save(save, &self.library, &self.aliases, &self.unavailable, &*self.notifier);
+        self.apply_loaded(loaded);
+        if clear_history {
+            if let Ok(mut history) = self.history.try_borrow_mut() {
+                history.clear();
+            }
+        }
+        self.change_since_last_save.store(true, Ordering::Relaxed);
+        self.change_since_last_group_post.store(true, Ordering::Relaxed);
+    }
+
+    /// pick up the custom chips in the group's folders, the library and the player's own chips take priority
+    pub(crate) fn update_shared_chips(&self) {
+        let (folders, mut shared) = match (self.group_folders.try_borrow(), self.shared_chips.try_borrow_mut()) {
+            (Ok(folders), Ok(shared)) => (folders, shared),
+            _ => return,
+        };
+        shared.clear();
+        // chips from other players haven't been checked yet
+        let custom = folders.values().flatten().filter_map(|chip| chip.custom.as_ref()).filter(|chip| chip.check_custom().is_ok());
+        for chip in custom {
+            if self.find_chip(&chip.name).is_none() && !shared.contains_key(&chip.name) {
+                shared.insert(chip.name.clone(), Rc::new(chip.as_custom()));
+            }
+        }
+    }
+
+    /// the folder as it's sent to the group, custom chips are only written out if the server passes them on
+    pub(crate) fn shared_folder(&self, custom_chips: bool) -> Vec<SharedChip> {
         let folder = unsafe{self.folder.try_borrow().unchecked_unwrap()};
-        let folder_str = bincode::serialize(&*folder).unwrap_or_else(|_| {
-            let chips: Vec<FolderChip> = Vec::new();
-            unsafe{bincode::serialize(&chips).unchecked_unwrap()}
-        });
+        let shared = folder.iter().map(|chip| SharedChip {
+            name: chip.name.clone(),
+            used: chip.used,
+            custom: if custom_chips && chip.chip.custom {serde_json::to_string(&*chip.chip).ok()} else {None},
+        }).collect::<Vec<SharedChip>>();
         self.change_since_last_group_post.store(false, Ordering::Relaxed);
-        folder_str
+        shared
     }
     
     /// Returns true if the folder has changed since the last time
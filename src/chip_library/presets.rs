@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+/// A named folder loadout, only the chip names are kept
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct FolderPreset {
+    pub name: String,
+    pub chips: Vec<String>,
+}
+
+/// Every saved folder preset, in the order they were made
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub(crate) struct FolderPresets {
+    presets: Vec<FolderPreset>,
+    /// the preset the current folder was last switched to or saved as
+    active: Option<String>,
+}
+
+impl FolderPresets {
+    pub(crate) fn presets(&self) -> &[FolderPreset] {
+        &self.presets
+    }
+
+    pub(crate) fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&FolderPreset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut FolderPreset> {
+        self.presets.iter_mut().find(|preset| preset.name == name)
+    }
+
+    /// save chips under name, overwriting any preset already using it
+    pub(crate) fn save(&mut self, name: &str, chips: Vec<String>) -> Result<(), &'static str> {
+        let name = check_name(name)?;
+        match self.get_mut(name) {
+            Some(preset) => preset.chips = chips,
+            None => self.presets.push(FolderPreset{name: name.to_owned(), chips}),
+        }
+        self.active = Some(name.to_owned());
+        Ok(())
+    }
+
+    pub(crate) fn rename(&mut self, old_name: &str, new_name: &str) -> Result<(), &'static str> {
+        let new_name = check_name(new_name)?;
+        if old_name == new_name {
+            return Ok(());
+        }
+        if self.get(new_name).is_some() {
+            return Err("A preset with that name already exists");
+        }
+        let preset = self.get_mut(old_name).ok_or("No preset with that name exists")?;
+        preset.name = new_name.to_owned();
+        if self.active.as_deref() == Some(old_name) {
+            self.active = Some(new_name.to_owned());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn duplicate(&mut self, name: &str, new_name: &str) -> Result<(), &'static str> {
+        let new_name = check_name(new_name)?;
+        if self.get(new_name).is_some() {
+            return Err("A preset with that name already exists");
+        }
+        let chips = self.get(name).ok_or("No preset with that name exists")?.chips.clone();
+        self.presets.push(FolderPreset{name: new_name.to_owned(), chips});
+        Ok(())
+    }
+
+    pub(crate) fn delete(&mut self, name: &str) -> Result<(), &'static str> {
+        let idx = self.presets.iter().position(|preset| preset.name == name).ok_or("No preset with that name exists")?;
+        self.presets.remove(idx);
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_active(&mut self, name: &str) {
+        self.active = Some(name.to_owned());
+    }
+
+    /// copy in presets from other that don't share a name with one already saved
+    pub(crate) fn add_missing(&mut self, other: &FolderPresets) {
+        for preset in other.presets.iter() {
+            if self.get(&preset.name).is_none() {
+                self.presets.push(preset.clone());
+            }
+        }
+    }
+
+    pub(crate) fn has_chip(&self, name: &str) -> bool {
+        self.presets.iter().any(|preset| preset.chips.iter().any(|chip| chip == name))
+    }
+
+    /// replace a chip name in every preset
+    pub(crate) fn rename_chip(&mut self, from: &str, to: &str) {
+        for chip in self.presets.iter_mut().flat_map(|preset| preset.chips.iter_mut()).filter(|chip| *chip == from) {
+            *chip = to.to_owned();
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.presets.clear();
+        self.active = None;
+    }
+}
+
+fn check_name(name: &str) -> Result<&str, &'static str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Preset names cannot be empty");
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chips(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| (*name).to_owned()).collect()
+    }
+
+    #[test]
+    fn save_overwrites() {
+        let mut presets = FolderPresets::default();
+        presets.save("Boss", chips(&["Cannon"])).unwrap();
+        presets.save(" Boss ", chips(&["Sword"])).unwrap();
+        assert_eq!(presets.presets().len(), 1);
+        assert_eq!(presets.get("Boss").unwrap().chips, chips(&["Sword"]));
+        assert_eq!(presets.active(), Some("Boss"));
+        assert!(presets.save("  ", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn rename_duplicate_and_delete() {
+        let mut presets = FolderPresets::default();
+        presets.save("Boss", chips(&["Cannon"])).unwrap();
+        presets.save("Stealth", chips(&["Sword"])).unwrap();
+
+        assert!(presets.rename("Boss", "Stealth").is_err());
+        presets.rename("Stealth", "Infiltration").unwrap();
+        assert_eq!(presets.active(), Some("Infiltration"));
+
+        presets.duplicate("Boss", "Boss 2").unwrap();
+        assert_eq!(presets.get("Boss 2").unwrap().chips, chips(&["Cannon"]));
+        assert!(presets.duplicate("Gone", "Other").is_err());
+
+        presets.delete("Infiltration").unwrap();
+        assert_eq!(presets.active(), None);
+        assert_eq!(presets.presets().len(), 2);
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+/// the storage key the profile list is kept under, it's shared by every profile
+pub(crate) const PROFILES_KEY: &str = "profiles";
+
+/// where an unreadable profile list is kept so it isn't lost when the list is next saved
+pub(crate) const PROFILES_BACKUP_KEY: &str = "profiles_backup";
+
+/// the profile anyone upgrading from before profiles existed ends up in
+const DEFAULT_PROFILE_ID: u32 = 0;
+
+/// One character's pack, folder, and settings
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Profile {
+    /// never changes, the storage keys are derived from it so renaming is free
+    pub id: u32,
+    pub name: String,
+}
+
+/// The profiles saved in this browser and which one is in use
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Profiles {
+    profiles: Vec<Profile>,
+    active: u32,
+    next_id: u32,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles {
+            profiles: vec![Profile{id: DEFAULT_PROFILE_ID, name: String::from("Default")}],
+            active: DEFAULT_PROFILE_ID,
+            next_id: DEFAULT_PROFILE_ID + 1,
+        }
+    }
+}
+
+/// the storage key for a profile's data
+///
+/// the default profile uses the bare key so data saved before profiles existed is still found
+pub(crate) fn profile_key(id: u32, key: &str) -> String {
+    if id == DEFAULT_PROFILE_ID {
+        return String::from(key);
+    }
+    String::from("profile_") + &id.to_string() + "_" + key
+}
+
+impl Profiles {
+    /// the default profile alone, for when the saved list can't be read
+    ///
+    /// new ids start after any found in keys, so a new profile never picks up a lost one's data
+    pub(crate) fn recovered(keys: &[String]) -> Profiles {
+        let highest = keys.iter().filter_map(|key| stored_id(key)).max().unwrap_or(DEFAULT_PROFILE_ID);
+        Profiles{next_id: highest + 1, ..Profiles::default()}
+    }
+
+    pub(crate) fn list(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    pub(crate) fn active_id(&self) -> u32 {
+        self.active
+    }
+
+    pub(crate) fn active_name(&self) -> &str {
+        self.profiles.iter().find(|profile| profile.id == self.active).map_or("", |profile| profile.name.as_str())
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// returns the new profile's id
+    pub(crate) fn create(&mut self, name: &str) -> Result<u32, &'static str> {
+        let name = check_name(name)?;
+        if self.find(name).is_some() {
+            return Err("A profile with that name already exists");
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.profiles.push(Profile{id, name: name.to_owned()});
+        Ok(id)
+    }
+
+    pub(crate) fn rename(&mut self, old_name: &str, new_name: &str) -> Result<(), &'static str> {
+        let new_name = check_name(new_name)?;
+        if old_name == new_name {
+            return Ok(());
+        }
+        if self.find(new_name).is_some() {
+            return Err("A profile with that name already exists");
+        }
+        let profile = self.profiles.iter_mut().find(|profile| profile.name == old_name).ok_or("No profile with that name exists")?;
+        profile.name = new_name.to_owned();
+        Ok(())
+    }
+
+    /// returns the removed profile's id so its data can be erased
+    pub(crate) fn remove(&mut self, name: &str) -> Result<u32, &'static str> {
+        let idx = self.profiles.iter().position(|profile| profile.name == name).ok_or("No profile with that name exists")?;
+        if self.profiles[idx].id == self.active {
+            return Err("You cannot delete the profile you are using, switch to another one first");
+        }
+        Ok(self.profiles.remove(idx).id)
+    }
+
+    pub(crate) fn set_active(&mut self, id: u32) -> Result<(), &'static str> {
+        if !self.profiles.iter().any(|profile| profile.id == id) {
+            return Err("No profile with that id exists");
+        }
+        self.active = id;
+        Ok(())
+    }
+}
+
+/// the id of the profile a storage key belongs to, None for the default profile's bare keys
+fn stored_id(key: &str) -> Option<u32> {
+    let rest = key.strip_prefix("profile_")?;
+    rest[..rest.find('_')?].parse().ok()
+}
+
+fn check_name(name: &str) -> Result<&str, &'static str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile names cannot be empty");
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_profile_uses_bare_keys() {
+        let profiles = Profiles::default();
+        assert_eq!(profile_key(profiles.active_id(), "pack"), "pack");
+        assert_eq!(profile_key(3, "pack"), "profile_3_pack");
+        assert_eq!(profiles.active_name(), "Default");
+    }
+
+    #[test]
+    fn create_rename_and_remove() {
+        let mut profiles = Profiles::default();
+        let id = profiles.create("Lan").unwrap();
+        assert!(profiles.create(" Lan ").is_err());
+        assert!(profiles.create("").is_err());
+
+        profiles.rename("Lan", "Mayl").unwrap();
+        assert_eq!(profiles.find("Mayl").unwrap().id, id);
+        assert!(profiles.rename("Mayl", "Default").is_err());
+
+        profiles.set_active(id).unwrap();
+        assert_eq!(profiles.active_name(), "Mayl");
+        assert!(profiles.remove("Mayl").is_err());
+        assert_eq!(profiles.remove("Default"), Ok(DEFAULT_PROFILE_ID));
+        assert_eq!(profiles.list().len(), 1);
+        // ids are never reused
+        assert_eq!(profiles.create("Dex"), Ok(id + 1));
+    }
+
+    #[test]
+    fn recovered_ids_skip_saved_data() {
+        let keys = ["pack", "profile_4_save", "profile_12_config", "profile_x_save", "profiles"].iter().map(|key| (*key).to_owned()).collect::<Vec<String>>();
+        let mut profiles = Profiles::recovered(&keys);
+        assert_eq!(profiles.list().len(), 1);
+        assert_eq!(profiles.create("Lan"), Ok(13));
+        assert_eq!(Profiles::recovered(&[]).create("Lan"), Ok(DEFAULT_PROFILE_ID + 1));
+    }
+}
@@ -0,0 +1,402 @@
+use crate::chip_library::{
+    battle_chip::BattleChip,
+    chip_type::{ChipClass, ChipType},
+    elements::Elements,
+    fuzzy,
+    ranges::Ranges,
+    skills::Skills,
+};
+use std::fmt;
+
+/// A parsed chip search, e.g. `elem:fire class:mega dmg>=10 "knock back"`
+///
+/// bare words match the name forgivingly (typos and missing letters are fine), quoted text matches part of the description
+#[derive(Clone, Debug)]
+pub(crate) enum Query {
+    All,
+    Name(String),
+    Description(String),
+    Element(Elements),
+    Class(ChipClass),
+    Skill(Skills),
+    Range(Ranges),
+    Kind(ChipType),
+    Damage{stat: DamageStat, cmp: Comparison, value: f32},
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DamageStat {
+    Max,
+    Average,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn test(&self, lhs: f32, rhs: f32) -> bool {
+        match self {
+            Comparison::Eq => (lhs - rhs).abs() < f32::EPSILON,
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum QueryError {
+    UnclosedQuote(usize),
+    UnexpectedToken{pos: usize, found: String},
+    UnexpectedEnd,
+    UnknownField{pos: usize, field: String},
+    UnknownValue{pos: usize, field: &'static str, value: String},
+    NotAComparison{pos: usize, field: String},
+    BadNumber{pos: usize, value: String},
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::UnclosedQuote(pos) => write!(f, "the quote at position {} is never closed", pos + 1),
+            QueryError::UnexpectedToken{pos, found} => write!(f, "unexpected \"{}\" at position {}", found, pos + 1),
+            QueryError::UnexpectedEnd => write!(f, "the search ended unexpectedly"),
+            QueryError::UnknownField{pos, field} => write!(f, "\"{}\" at position {} is not something chips can be searched by", field, pos + 1),
+            QueryError::UnknownValue{pos, field, value} => write!(f, "\"{}\" at position {} is not {}", value, pos + 1, field),
+            QueryError::NotAComparison{pos, field} => write!(f, "\"{}\" at position {} can only be matched with ':'", field, pos + 1),
+            QueryError::BadNumber{pos, value} => write!(f, "\"{}\" at position {} is not a number", value, pos + 1),
+        }
+    }
+}
+
+impl Query {
+    pub(crate) fn parse(text: &str) -> Result<Query, QueryError> {
+        let tokens = tokenize(text)?;
+        if tokens.is_empty() {
+            return Ok(Query::All);
+        }
+        let mut parser = Parser{tokens, idx: 0};
+        let query = parser.or()?;
+        match parser.next() {
+            None => Ok(query),
+            Some((pos, token)) => Err(QueryError::UnexpectedToken{pos, found: token.to_string()}),
+        }
+    }
+
+    /// how closely a search for just a name matches the chip, lower is closer
+    ///
+    /// None for any other kind of search, there's nothing to rank by
+    pub(crate) fn name_score(&self, chip: &BattleChip) -> Option<u32> {
+        match self {
+            Query::Name(name) => fuzzy::score(name, &chip.name),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn matches(&self, chip: &BattleChip) -> bool {
+        match self {
+            Query::All => true,
+            Query::Name(name) => fuzzy::score(name, &chip.name).is_some(),
+            Query::Description(text) => chip.description.to_ascii_lowercase().contains(text.as_str()),
+            Query::Element(element) => chip.element.contains(element),
+            Query::Class(class) => chip.class.is(*class),
+            Query::Skill(skill) => chip.skills.contains(skill),
+            Query::Range(range) => chip.range == *range,
+            Query::Kind(kind) => chip.kind == *kind,
+            Query::Damage{stat, cmp, value} => {
+                let dmg = match stat {
+                    DamageStat::Max => chip.max_dmg() as f32,
+                    DamageStat::Average => chip.avg_dmg(),
+                };
+                cmp.test(dmg, *value)
+            }
+            Query::Not(query) => !query.matches(chip),
+            Query::And(lhs, rhs) => lhs.matches(chip) && rhs.matches(chip),
+            Query::Or(lhs, rhs) => lhs.matches(chip) || rhs.matches(chip),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    /// quoted words are never keywords or fields
+    Word{text: String, quoted: bool},
+    Minus,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LParen => f.write_str("("),
+            Token::RParen => f.write_str(")"),
+            Token::Word{text, quoted: true} => write!(f, "\"{}\"", text),
+            Token::Word{text, quoted: false} => f.write_str(text),
+            Token::Minus => f.write_str("-"),
+        }
+    }
+}
+
+impl Token {
+    /// AND, OR and NOT are keywords in any case when they aren't quoted
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match self {
+            Token::Word{text, quoted: false} => text.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars = text.char_indices().collect::<Vec<(usize, char)>>();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (pos, ch) = chars[idx];
+        match ch {
+            _ if ch.is_whitespace() => {
+                idx += 1;
+                continue;
+            }
+            '(' => {
+                tokens.push((pos, Token::LParen));
+                idx += 1;
+                continue;
+            }
+            ')' => {
+                tokens.push((pos, Token::RParen));
+                idx += 1;
+                continue;
+            }
+            // only a negation at the start of a word, "-" on its own is searched for
+            '-' if chars.get(idx + 1).map_or(false, |(_, next)| !next.is_whitespace()) => {
+                tokens.push((pos, Token::Minus));
+                idx += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        // a word runs until whitespace or a bracket, quoted parts can hold either
+        let mut word = String::new();
+        let mut quoted = true;
+        while idx < chars.len() {
+            let (quote_pos, ch) = chars[idx];
+            if ch.is_whitespace() || ch == '(' || ch == ')' {
+                break;
+            }
+            if ch != '"' {
+                quoted = false;
+                word.push(ch);
+                idx += 1;
+                continue;
+            }
+            idx += 1;
+            loop {
+                match chars.get(idx) {
+                    None => return Err(QueryError::UnclosedQuote(quote_pos)),
+                    Some((_, '"')) => break,
+                    Some((_, ch)) => word.push(*ch),
+                }
+                idx += 1;
+            }
+            idx += 1;
+        }
+        tokens.push((pos, Token::Word{text: word, quoted}));
+    }
+    Ok(tokens)
+}
+
+/// recursive descent parser for searches
+///
+/// or    := and ("OR" and)*
+/// and   := unary ("AND"? unary)*
+/// unary := ("NOT" | '-') unary | '(' or ')' | term
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    idx: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.idx).cloned();
+        self.idx += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut query = self.and()?;
+        while self.peek().map_or(false, |token| token.is_keyword("or")) {
+            self.idx += 1;
+            let rhs = self.and()?;
+            query = Query::Or(Box::new(query), Box::new(rhs));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut query = self.unary()?;
+        loop {
+            match self.peek() {
+                None | Some(Token::RParen) => break,
+                Some(token) if token.is_keyword("or") => break,
+                Some(token) if token.is_keyword("and") => self.idx += 1,
+                Some(_) => {}
+            }
+            let rhs = self.unary()?;
+            query = Query::And(Box::new(query), Box::new(rhs));
+        }
+        Ok(query)
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        let (pos, token) = self.next().ok_or(QueryError::UnexpectedEnd)?;
+        match token {
+            Token::Minus => Ok(Query::Not(Box::new(self.unary()?))),
+            _ if token.is_keyword("not") => Ok(Query::Not(Box::new(self.unary()?))),
+            Token::LParen => {
+                let query = self.or()?;
+                match self.next() {
+                    Some((_, Token::RParen)) => Ok(query),
+                    Some((pos, token)) => Err(QueryError::UnexpectedToken{pos, found: token.to_string()}),
+                    None => Err(QueryError::UnexpectedEnd),
+                }
+            }
+            Token::Word{text, quoted: true} => Ok(Query::Description(text.to_ascii_lowercase())),
+            Token::Word{text, quoted: false} => {
+                if token_is_operator(&text) {
+                    return Err(QueryError::UnexpectedToken{pos, found: text});
+                }
+                term(pos, &text)
+            }
+            Token::RParen => Err(QueryError::UnexpectedToken{pos, found: token.to_string()}),
+        }
+    }
+}
+
+fn token_is_operator(text: &str) -> bool {
+    text.eq_ignore_ascii_case("and") || text.eq_ignore_ascii_case("or")
+}
+
+/// a bare word, or a field and value like `elem:fire` or `dmg>=10`
+fn term(pos: usize, text: &str) -> Result<Query, QueryError> {
+    let split = match text.find(&[':', '=', '<', '>'][..]) {
+        Some(split) => split,
+        None => return Ok(Query::Name(text.to_ascii_lowercase())),
+    };
+    let field = &text[..split];
+    let rest = &text[split..];
+    let (cmp, value) = if let Some(value) = rest.strip_prefix(">=") {
+        (Comparison::Ge, value)
+    } else if let Some(value) = rest.strip_prefix("<=") {
+        (Comparison::Le, value)
+    } else if let Some(value) = rest.strip_prefix('>') {
+        (Comparison::Gt, value)
+    } else if let Some(value) = rest.strip_prefix('<') {
+        (Comparison::Lt, value)
+    } else {
+        (Comparison::Eq, &rest[1..])
+    };
+    let value_pos = pos + text.len() - value.len();
+    if value.is_empty() {
+        return Err(QueryError::UnexpectedEnd);
+    }
+
+    let field_lower = field.to_ascii_lowercase();
+    let stat = match field_lower.as_str() {
+        "dmg" | "damage" | "max" => Some(DamageStat::Max),
+        "avg" | "average" => Some(DamageStat::Average),
+        _ => None,
+    };
+    if let Some(stat) = stat {
+        let value = value.parse::<f32>().map_err(|_| QueryError::BadNumber{pos: value_pos, value: value.to_owned()})?;
+        return Ok(Query::Damage{stat, cmp, value});
+    }
+    if cmp != Comparison::Eq {
+        return Err(QueryError::NotAComparison{pos, field: field.to_owned()});
+    }
+
+    let unknown = |field: &'static str| QueryError::UnknownValue{pos: value_pos, field, value: value.to_owned()};
+    let query = match field_lower.as_str() {
+        "name" => Query::Name(value.to_ascii_lowercase()),
+        "desc" | "description" => Query::Description(value.to_ascii_lowercase()),
+        "elem" | "element" => Query::Element(Elements::from_name(value).ok_or_else(|| unknown("an element"))?),
+        "class" => Query::Class(ChipClass::from_name(value).ok_or_else(|| unknown("a class"))?),
+        "skill" => Query::Skill(Skills::from_name(value).ok_or_else(|| unknown("a skill"))?),
+        "range" => Query::Range(Ranges::from_name(value).ok_or_else(|| unknown("a range"))?),
+        "type" | "kind" => Query::Kind(ChipType::from_name(value).ok_or_else(|| unknown("a chip type"))?),
+        _ => return Err(QueryError::UnknownField{pos, field: field.to_owned()}),
+    };
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chips() -> Vec<BattleChip> {
+        serde_json::from_str(r#"[
+            {"Name": "FireSword", "Element": ["Fire", "Sword"], "Skills": ["Strength"], "Damage": "3d6", "Class": "Standard", "Type": "Melee", "Range": "Close", "Hits": "1", "Description": "A burning blade with knock back"},
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A basic cannon"},
+            {"Name": "BigBomb", "Element": ["Fire"], "Skills": ["Strength", "Tech"], "Damage": "4d8", "Class": "Mega", "Type": "Burst", "Range": "Near", "Hits": "1", "Description": "A big bomb"}
+        ]"#).unwrap()
+    }
+
+    fn names(query: &str) -> Vec<String> {
+        let query = Query::parse(query).unwrap();
+        chips().iter().filter(|chip| query.matches(chip)).map(|chip| chip.name.clone()).collect()
+    }
+
+    #[test]
+    fn fields_and_words() {
+        assert_eq!(names(""), vec!["FireSword", "Cannon", "BigBomb"]);
+        assert_eq!(names("elem:fire"), vec!["FireSword", "BigBomb"]);
+        assert_eq!(names("ELEM:fire class:mega"), vec!["BigBomb"]);
+        assert_eq!(names("skill:tch range:far"), vec!["Cannon"]);
+        assert_eq!(names("type:melee"), vec!["FireSword"]);
+        assert_eq!(names("dmg>=18"), vec!["FireSword", "BigBomb"]);
+        assert_eq!(names("avg<10"), vec!["Cannon"]);
+        assert_eq!(names("bomb"), vec!["BigBomb"]);
+        assert_eq!(names("canon"), vec!["Cannon"]);
+        assert_eq!(names("\"knock back\""), vec!["FireSword"]);
+        assert_eq!(names("desc:\"basic cannon\""), vec!["Cannon"]);
+    }
+
+    #[test]
+    fn boolean_operators() {
+        assert_eq!(names("class:mega OR range:far"), vec!["Cannon", "BigBomb"]);
+        assert_eq!(names("elem:fire and not class:mega"), vec!["FireSword"]);
+        assert_eq!(names("-elem:fire"), vec!["Cannon"]);
+        assert_eq!(names("(elem:null or elem:sword) skill:str"), vec!["FireSword"]);
+        // "and" binds tighter than "or"
+        assert_eq!(names("range:far or elem:fire class:mega"), vec!["Cannon", "BigBomb"]);
+    }
+
+    #[test]
+    fn errors_say_where() {
+        assert_eq!(Query::parse("elem:fir").unwrap_err(), QueryError::UnknownValue{pos: 5, field: "an element", value: String::from("fir")});
+        assert_eq!(Query::parse("colour:red").unwrap_err(), QueryError::UnknownField{pos: 0, field: String::from("colour")});
+        assert_eq!(Query::parse("dmg>=lots").unwrap_err(), QueryError::BadNumber{pos: 5, value: String::from("lots")});
+        assert_eq!(Query::parse("elem>fire").unwrap_err(), QueryError::NotAComparison{pos: 0, field: String::from("elem")});
+        assert_eq!(Query::parse("\"knock").unwrap_err(), QueryError::UnclosedQuote(0));
+        assert_eq!(Query::parse("(elem:fire").unwrap_err(), QueryError::UnexpectedEnd);
+        assert_eq!(Query::parse("fire)").unwrap_err(), QueryError::UnexpectedToken{pos: 4, found: String::from(")")});
+        assert_eq!(Query::parse("or fire").unwrap_err(), QueryError::UnexpectedToken{pos: 0, found: String::from("or")});
+        assert_eq!(Query::parse("not").unwrap_err(), QueryError::UnexpectedEnd);
+    }
+}
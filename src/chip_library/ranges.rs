@@ -1,12 +1,12 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Eq, PartialEq, Clone, Copy, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone, Copy, PartialOrd, Ord)]
 pub(crate) enum Ranges {
     Varies,
     Far,
     Near,
     Close,
-    #[serde(rename(deserialize = "Self"))]
+    #[serde(rename = "Self")]
     Itself,
 }
 
@@ -20,4 +20,17 @@ impl Ranges {
             Ranges::Varies => "Var",
         }
     }
+
+    /// look up a range by name, ignoring case
+    pub(crate) fn from_name(name: &str) -> Option<Ranges> {
+        let range = match name.to_ascii_lowercase().as_str() {
+            "self" => Ranges::Itself,
+            "close" => Ranges::Close,
+            "near" => Ranges::Near,
+            "far" => Ranges::Far,
+            "varies" | "var" => Ranges::Varies,
+            _ => return None,
+        };
+        Some(range)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,196 @@
+use crate::chip_library::BattleChip;
+use crate::chip_library::dice::{DiceError, RollResult};
+use std::collections::VecDeque;
+
+/// how many chip rolls are kept for the session
+const MAX_HISTORY: usize = 200;
+
+/// Small seedable random number generator (splitmix64)
+///
+/// not suitable for anything but dice
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        Rng{state: seed}
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut val = self.state;
+        val = (val ^ (val >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        val = (val ^ (val >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        val ^ (val >> 31)
+    }
+
+    /// a random number in low..=high
+    pub(crate) fn range(&mut self, low: u32, high: u32) -> u32 {
+        if high <= low {
+            return low;
+        }
+        let span = (high - low) as u128 + 1;
+        low + ((self.next_u64() as u128 * span) >> 64) as u32
+    }
+}
+
+/// the most hits a chip can roll at once
+pub(crate) const MAX_HITS: u32 = 100;
+
+/// The number of hits a chip makes, "3" or a range like "1-3"
+///
+/// None if the chip doesn't list a hit count
+pub(crate) fn parse_hits(hits: &str) -> Option<(u32, u32)> {
+    let hits = hits.trim();
+    if let Some((low, high)) = hits.split_once('-') {
+        let low = low.trim().parse::<u32>().ok()?;
+        let high = high.trim().parse::<u32>().ok()?;
+        if low > high {
+            return None;
+        }
+        return Some((low, high));
+    }
+    let count = hits.parse::<u32>().ok()?;
+    Some((count, count))
+}
+
+/// One use of a chip, the damage is rolled once per hit
+pub(crate) struct ChipRoll {
+    pub chip: String,
+    pub hits: Vec<RollResult>,
+    pub total: i64,
+}
+
+/// Rolls chip damage and keeps a history of the results for the session
+pub(crate) struct DiceRoller {
+    rng: Rng,
+    history: VecDeque<ChipRoll>,
+}
+
+impl DiceRoller {
+    pub(crate) fn new(seed: u64) -> DiceRoller {
+        DiceRoller {
+            rng: Rng::new(seed),
+            history: VecDeque::new(),
+        }
+    }
+
+    /// roll a chip's damage for each of its hits, chips without a hit count are rolled once
+    pub(crate) fn roll_chip(&mut self, chip: &BattleChip, skill_rank: i64) -> Result<&ChipRoll, DiceError> {
+        if !chip.has_damage() {
+            return Err(DiceError::Empty);
+        }
+        let expr = chip.damage_expr()?;
+
+        let hit_count = match parse_hits(&chip.hits) {
+            Some((_, high)) if high > MAX_HITS => return Err(DiceError::TooManyHits),
+            Some((low, high)) => self.rng.range(low, high).max(1),
+            None => 1,
+        };
+
+        let mut hits = Vec::with_capacity(hit_count as usize);
+        for _ in 0..hit_count {
+            hits.push(expr.roll(&mut self.rng, skill_rank)?);
+        }
+        let total = hits.iter().try_fold(0i64, |sum, hit| sum.checked_add(hit.total)).ok_or(DiceError::Overflow)?;
+
+        if self.history.len() >= MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(ChipRoll{
+            chip: chip.name.clone(),
+            hits,
+            total,
+        });
+        // just pushed, can't be empty
+        Ok(&self.history[self.history.len() - 1])
+    }
+
+    /// oldest roll first
+    pub(crate) fn history(&self) -> impl DoubleEndedIterator<Item = &ChipRoll> {
+        self.history.iter()
+    }
+
+    pub(crate) fn clear_history(&mut self) {
+        self.history.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chip(damage: &str, hits: &str) -> BattleChip {
+        let json = String::from(r#"{"Name": "Test", "Element": ["Null"], "Skills": ["Tech"], "Damage": ""#)
+            + damage + r#"", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": ""#
+            + hits + r#"", "Description": "test"}"#;
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn rng_is_seedable() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+        for _ in 0..100 {
+            let val = first.range(1, 6);
+            assert_eq!(val, second.range(1, 6));
+            assert!((1..=6).contains(&val));
+        }
+    }
+
+    #[test]
+    fn hits_are_parsed() {
+        assert_eq!(parse_hits("1"), Some((1, 1)));
+        assert_eq!(parse_hits(" 1-3 "), Some((1, 3)));
+        assert_eq!(parse_hits("--"), None);
+        assert_eq!(parse_hits("3-1"), None);
+    }
+
+    #[test]
+    fn rolls_once_per_hit() {
+        let mut roller = DiceRoller::new(7);
+        let roll = roller.roll_chip(&chip("2d6+1", "3"), 1).unwrap();
+        assert_eq!(roll.hits.len(), 3);
+        for hit in roll.hits.iter() {
+            assert_eq!(hit.dice.len(), 2);
+            let dice_total: i64 = hit.dice.iter().map(|die| die.value as i64).sum();
+            assert_eq!(hit.total, dice_total + 1);
+        }
+        assert_eq!(roll.total, roll.hits.iter().map(|hit| hit.total).sum::<i64>());
+    }
+
+    #[test]
+    fn hit_ranges_and_history() {
+        let mut roller = DiceRoller::new(99);
+        let chip = chip("1d4", "1-3");
+        for _ in 0..20 {
+            let hits = roller.roll_chip(&chip, 1).unwrap().hits.len();
+            assert!((1..=3).contains(&hits));
+        }
+        assert_eq!(roller.history().count(), 20);
+        assert!(roller.roll_chip(&self::chip("--", "1"), 1).is_err());
+        assert_eq!(roller.history().count(), 20);
+    }
+
+    #[test]
+    fn too_many_hits_are_refused() {
+        let mut roller = DiceRoller::new(3);
+        assert!(roller.roll_chip(&chip("1d6", "100"), 1).is_ok());
+        assert_eq!(roller.roll_chip(&chip("1d6", "1-101"), 1).err(), Some(DiceError::TooManyHits));
+        assert_eq!(roller.roll_chip(&chip("1d6", "4294967295"), 1).err(), Some(DiceError::TooManyHits));
+        assert_eq!(roller.history().count(), 1);
+    }
+
+    #[test]
+    fn same_seed_same_rolls() {
+        let chip = chip("3d8 per Tech", "1-2");
+        let mut first = DiceRoller::new(1234);
+        let mut second = DiceRoller::new(1234);
+        for _ in 0..10 {
+            let a = first.roll_chip(&chip, 2).unwrap().total;
+            let b = second.roll_chip(&chip, 2).unwrap().total;
+            assert_eq!(a, b);
+        }
+    }
+}
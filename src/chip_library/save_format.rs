@@ -0,0 +1,319 @@
+use crate::chip_library::{BattleChip, FolderPresets, LogEntry};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// the version written by this build, bump it and add a migration when the format changes
+pub(crate) const SAVE_VERSION: u64 = 2;
+
+/// the most chips a folder can ever hold
+pub(crate) const MAX_CHIP_LIMIT: usize = 45;
+
+/// the chip limit for a new profile
+pub(crate) const DEFAULT_CHIP_LIMIT: usize = 12;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), SaveError>;
+
+/// brings save data from version N to N + 1, indexed by N - 1
+const MIGRATIONS: [Migration; (SAVE_VERSION - 1) as usize] = [
+    migrate_v1,
+];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SavedFolderChip {
+    pub name: String,
+    pub used: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SavedPackChip {
+    pub owned: u32,
+    pub used: u32,
+}
+
+/// A chip the library renamed, the saved copies were moved from one name to the other
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ChipRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// Everything saved for a profile, used both for localStorage and for exported files
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct SaveData {
+    pub version: u64,
+    pub folder: Vec<SavedFolderChip>,
+    pub pack: BTreeMap<String, SavedPackChip>,
+    pub limit: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub log: Vec<LogEntry>,
+    pub presets: FolderPresets,
+    /// every rename that's been applied to this save, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renames: Vec<ChipRename>,
+    /// chips made by the player, saved with the profile they were made in
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub custom_chips: Vec<BattleChip>,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData {
+            version: SAVE_VERSION,
+            folder: Vec::new(),
+            pack: BTreeMap::new(),
+            limit: DEFAULT_CHIP_LIMIT,
+            log: Vec::new(),
+            presets: FolderPresets::default(),
+            renames: Vec::new(),
+            custom_chips: Vec::new(),
+        }
+    }
+}
+
+/// Why save data couldn't be read, points at the part that's wrong
+#[derive(Debug, PartialEq)]
+pub(crate) enum SaveError {
+    Json(String),
+    NotAnObject,
+    UnsupportedVersion(u64),
+    MissingField(&'static str),
+    BadField{field: &'static str, problem: String},
+    BadFolderEntry{index: usize, problem: String},
+    BadPackEntry{name: String, problem: String},
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Json(why) => write!(f, "The save data is not valid JSON: {}", why),
+            SaveError::NotAnObject => write!(f, "The save data should be a JSON object"),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "The save data is version {}, this app only understands up to version {}", version, SAVE_VERSION)
+            }
+            SaveError::MissingField(field) => write!(f, "The save data is missing \"{}\"", field),
+            SaveError::BadField{field, problem} => write!(f, "\"{}\" is invalid: {}", field, problem),
+            SaveError::BadFolderEntry{index, problem} => write!(f, "Folder chip #{} is invalid: {}", index + 1, problem),
+            SaveError::BadPackEntry{name, problem} => write!(f, "Pack entry \"{}\" is invalid: {}", name, problem),
+        }
+    }
+}
+
+impl SaveData {
+    pub(crate) fn from_json(text: &str) -> Result<SaveData, SaveError> {
+        let value = serde_json::from_str::<Value>(text).map_err(|e| SaveError::Json(e.to_string()))?;
+        SaveData::from_value(value)
+    }
+
+    /// migrates older versions, then checks every entry
+    pub(crate) fn from_value(mut value: Value) -> Result<SaveData, SaveError> {
+        let obj = value.as_object_mut().ok_or(SaveError::NotAnObject)?;
+
+        // the first format had no version at all
+        let mut version = match obj.get("Version") {
+            Some(version) => version.as_u64().ok_or_else(|| bad_field("Version", "must be a whole number"))?,
+            None => 1,
+        };
+        if version == 0 || version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+        while version < SAVE_VERSION {
+            MIGRATIONS[(version - 1) as usize](obj)?;
+            version += 1;
+        }
+
+        let limit = obj.get("Limit").ok_or(SaveError::MissingField("Limit"))?;
+        let limit = limit.as_u64().ok_or_else(|| bad_field("Limit", "must be a whole number"))? as usize;
+        if limit > MAX_CHIP_LIMIT {
+            return Err(bad_field("Limit", "cannot be more than 45"));
+        }
+
+        let folder = match obj.get("Folder") {
+            Some(folder) => parse_folder(folder, limit)?,
+            None => Vec::new(),
+        };
+        let pack = match obj.get("Pack") {
+            Some(pack) => parse_pack(pack)?,
+            None => BTreeMap::new(),
+        };
+        let log = match obj.remove("Log") {
+            Some(log) => serde_json::from_value::<Vec<LogEntry>>(log).map_err(|e| bad_field("Log", &e.to_string()))?,
+            None => Vec::new(),
+        };
+        let presets = match obj.remove("Presets") {
+            Some(presets) => serde_json::from_value::<FolderPresets>(presets).map_err(|e| bad_field("Presets", &e.to_string()))?,
+            None => FolderPresets::default(),
+        };
+        let renames = match obj.remove("Renames") {
+            Some(renames) => serde_json::from_value::<Vec<ChipRename>>(renames).map_err(|e| bad_field("Renames", &e.to_string()))?,
+            None => Vec::new(),
+        };
+
+        let custom_chips = match obj.remove("CustomChips") {
+            Some(chips) => parse_custom_chips(chips)?,
+            None => Vec::new(),
+        };
+
+        Ok(SaveData {
+            version: SAVE_VERSION,
+            folder,
+            pack,
+            limit,
+            log,
+            presets,
+            renames,
+            custom_chips,
+        })
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        // only strings, numbers and maps with string keys, can't fail
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub(crate) fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// every chip name in the pack or folder, each only once
+    pub(crate) fn chip_names(&self) -> Vec<&str> {
+        let mut names = self.pack.keys().map(String::as_str).chain(self.folder.iter().map(|chip| chip.name.as_str())).collect::<Vec<&str>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// call a chip by another name, copies in the pack are added to any already under the new name
+    pub(crate) fn rename_chip(&mut self, from: &str, to: &str) {
+        if let Some(chip) = self.pack.remove(from) {
+            let entry = self.pack.entry(to.to_owned()).or_insert(SavedPackChip{owned: 0, used: 0});
+            entry.owned += chip.owned;
+            entry.used += chip.used;
+        }
+        for chip in self.folder.iter_mut().filter(|chip| chip.name == from) {
+            chip.name = to.to_owned();
+        }
+        self.presets.rename_chip(from, to);
+    }
+
+    /// true if the pack, folder or any preset has the chip
+    pub(crate) fn has_chip(&self, name: &str) -> bool {
+        self.pack.contains_key(name) || self.folder.iter().any(|chip| chip.name == name) || self.presets.has_chip(name)
+    }
+
+    /// drop a chip from the pack and folder
+    pub(crate) fn remove_chip(&mut self, name: &str) {
+        self.pack.remove(name);
+        self.folder.retain(|chip| chip.name != name);
+    }
+}
+
+fn bad_field(field: &'static str, problem: &str) -> SaveError {
+    SaveError::BadField{field, problem: problem.to_owned()}
+}
+
+fn parse_folder(folder: &Value, limit: usize) -> Result<Vec<SavedFolderChip>, SaveError> {
+    let entries = folder.as_array().ok_or_else(|| bad_field("Folder", "should be a list of chips"))?;
+    if entries.len() > limit {
+        let problem = String::from("it has ") + &entries.len().to_string() + " chips but the chip limit is " + &limit.to_string();
+        return Err(SaveError::BadField{field: "Folder", problem});
+    }
+    let mut to_ret = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let chip = serde_json::from_value::<SavedFolderChip>(entry.clone())
+            .map_err(|e| SaveError::BadFolderEntry{index, problem: e.to_string()})?;
+        to_ret.push(chip);
+    }
+    Ok(to_ret)
+}
+
+fn parse_pack(pack: &Value) -> Result<BTreeMap<String, SavedPackChip>, SaveError> {
+    let entries = pack.as_object().ok_or_else(|| bad_field("Pack", "should map chip names to their counts"))?;
+    let mut to_ret = BTreeMap::new();
+    for (name, entry) in entries.iter() {
+        let chip = serde_json::from_value::<SavedPackChip>(entry.clone())
+            .map_err(|e| SaveError::BadPackEntry{name: name.clone(), problem: e.to_string()})?;
+        if chip.used > chip.owned {
+            let problem = chip.used.to_string() + " copies are used but only " + &chip.owned.to_string() + " are owned";
+            return Err(SaveError::BadPackEntry{name: name.clone(), problem});
+        }
+        if chip.owned == 0 {
+            return Err(SaveError::BadPackEntry{name: name.clone(), problem: String::from("no copies are owned")});
+        }
+        to_ret.insert(name.clone(), chip);
+    }
+    Ok(to_ret)
+}
+
+fn parse_custom_chips(chips: Value) -> Result<Vec<BattleChip>, SaveError> {
+    let chips = serde_json::from_value::<Vec<BattleChip>>(chips).map_err(|e| bad_field("CustomChips", &e.to_string()))?;
+    for chip in chips.iter() {
+        chip.check_custom().map_err(|why| bad_field("CustomChips", &(chip.name.clone() + ": " + why)))?;
+    }
+    Ok(chips.into_iter().map(|mut chip| {
+        chip.custom = true;
+        chip
+    }).collect())
+}
+
+/// the unversioned format, localStorage kept the chip limit as a string
+fn migrate_v1(obj: &mut Map<String, Value>) -> Result<(), SaveError> {
+    if let Some(Value::String(limit)) = obj.get("Limit") {
+        let limit = limit.trim().parse::<u64>().map_err(|_| bad_field("Limit", "must be a whole number"))?;
+        obj.insert(String::from("Limit"), Value::from(limit));
+    }
+    obj.insert(String::from("Version"), Value::from(2u64));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY: &str = r#"{
+        "Folder": [{"name": "Sword", "used": true}],
+        "Pack": {"Cannon": {"owned": 2, "used": 1}},
+        "Limit": 15
+    }"#;
+
+    #[test]
+    fn legacy_saves_are_migrated() {
+        let save = SaveData::from_json(LEGACY).unwrap();
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.folder, vec![SavedFolderChip{name: String::from("Sword"), used: true}]);
+        assert_eq!(save.pack["Cannon"], SavedPackChip{owned: 2, used: 1});
+        assert_eq!(save.limit, 15);
+
+        let string_limit = SaveData::from_json(r#"{"Limit": "20"}"#).unwrap();
+        assert_eq!(string_limit.limit, 20);
+    }
+
+    #[test]
+    fn round_trips() {
+        let save = SaveData::from_json(LEGACY).unwrap();
+        let reloaded = SaveData::from_json(&save.to_json_pretty()).unwrap();
+        assert_eq!(reloaded.folder, save.folder);
+        assert_eq!(reloaded.pack, save.pack);
+        assert_eq!(reloaded.limit, save.limit);
+    }
+
+    #[test]
+    fn errors_point_at_the_entry() {
+        let err = SaveData::from_json(r#"{"Limit": 12, "Pack": {"Cannon": {"owned": 1, "used": 2}}}"#).unwrap_err();
+        assert!(matches!(err, SaveError::BadPackEntry{ref name, ..} if name == "Cannon"));
+
+        let err = SaveData::from_json(r#"{"Limit": 12, "Folder": [{"name": "Sword", "used": false}, {"name": "Cannon"}]}"#).unwrap_err();
+        assert!(matches!(err, SaveError::BadFolderEntry{index: 1, ..}));
+        assert!(err.to_string().starts_with("Folder chip #2 is invalid"));
+
+        assert_eq!(SaveData::from_json(r#"{"Folder": []}"#).unwrap_err(), SaveError::MissingField("Limit"));
+        assert_eq!(SaveData::from_json(r#"{"Version": 99, "Limit": 12}"#).unwrap_err(), SaveError::UnsupportedVersion(99));
+        assert!(matches!(SaveData::from_json(r#"{"Limit": 50}"#).unwrap_err(), SaveError::BadField{field: "Limit", ..}));
+        assert!(matches!(SaveData::from_json("[1, 2").unwrap_err(), SaveError::Json(_)));
+    }
+}
@@ -1,6 +1,6 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Eq, PartialEq, Clone, Copy, Ord, PartialOrd)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone, Copy, Ord, PartialOrd)]
 pub(crate) enum Skills {
     Perception,
     Info,
@@ -31,4 +31,21 @@ impl Skills {
             Skills::Varies => "VAR",
         }
     }
+
+    /// look up a skill by its full name or its abbreviation, ignoring case
+    pub(crate) fn from_name(name: &str) -> Option<Skills> {
+        let skill = match name.to_ascii_lowercase().as_str() {
+            "perception" | "per" => Skills::Perception,
+            "info" | "inf" => Skills::Info,
+            "tech" | "tch" => Skills::Tech,
+            "strength" | "str" => Skills::Strength,
+            "agility" | "agi" => Skills::Agility,
+            "endurance" | "end" => Skills::Endurance,
+            "charm" | "chm" => Skills::Charm,
+            "valor" | "vlr" => Skills::Valor,
+            "affinity" | "aff" => Skills::Affinity,
+            _ => return None,
+        };
+        Some(skill)
+    }
 }
\ No newline at end of file
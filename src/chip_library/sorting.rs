@@ -0,0 +1,281 @@
+use crate::chip_library::{battle_chip::BattleChip, roller::parse_hits};
+use std::cmp::Ordering;
+
+/// the most keys a sort can have, older ones drop off the end
+const MAX_SORT_KEYS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SortKey {
+    Name,
+    Class,
+    Element,
+    MaxDamage,
+    AverageDamage,
+    Skill,
+    Range,
+    Kind,
+    Hits,
+    Owned,
+    Used,
+    InFolder,
+    /// how closely the chip's name matches a name search
+    Relevance,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    fn flip(self) -> SortDirection {
+        match self {
+            SortDirection::Ascending => SortDirection::Descending,
+            SortDirection::Descending => SortDirection::Ascending,
+        }
+    }
+
+    pub(crate) fn arrow(&self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "▲",
+            SortDirection::Descending => "▼",
+        }
+    }
+}
+
+impl SortKey {
+    /// what the library can be sorted by, it has no owned or used counts
+    pub(crate) const LIBRARY: &'static [SortKey] = &[
+        SortKey::Name, SortKey::Class, SortKey::Element, SortKey::MaxDamage, SortKey::AverageDamage,
+        SortKey::Skill, SortKey::Range, SortKey::Kind, SortKey::Hits, SortKey::InFolder, SortKey::Relevance,
+    ];
+
+    pub(crate) const PACK: &'static [SortKey] = &[
+        SortKey::Name, SortKey::Class, SortKey::Element, SortKey::MaxDamage, SortKey::AverageDamage,
+        SortKey::Skill, SortKey::Range, SortKey::Kind, SortKey::Hits, SortKey::Owned, SortKey::Used, SortKey::InFolder,
+        SortKey::Relevance,
+    ];
+
+    pub(crate) const FOLDER: &'static [SortKey] = &[
+        SortKey::Name, SortKey::Class, SortKey::Element, SortKey::MaxDamage, SortKey::AverageDamage,
+        SortKey::Skill, SortKey::Range, SortKey::Kind, SortKey::Hits, SortKey::Used, SortKey::Relevance,
+    ];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::Class => "Class",
+            SortKey::Element => "Element",
+            SortKey::MaxDamage => "MaxDamage",
+            SortKey::AverageDamage => "AverageDamage",
+            SortKey::Skill => "Skill",
+            SortKey::Range => "Range",
+            SortKey::Kind => "Type",
+            SortKey::Hits => "Hits",
+            SortKey::Owned => "Owned",
+            SortKey::Used => "Used",
+            SortKey::InFolder => "InFolder",
+            SortKey::Relevance => "Relevance",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            SortKey::MaxDamage => "Max Damage",
+            SortKey::AverageDamage => "Average Damage",
+            SortKey::InFolder => "In Folder",
+            SortKey::Relevance => "Best Match",
+            _ => self.as_str(),
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<SortKey> {
+        SortKey::PACK.iter().copied().find(|key| key.as_str() == name)
+    }
+
+    /// bigger is usually what you're looking for with numbers
+    fn default_direction(&self) -> SortDirection {
+        match self {
+            SortKey::MaxDamage | SortKey::AverageDamage | SortKey::Hits | SortKey::Owned | SortKey::InFolder => SortDirection::Descending,
+            _ => SortDirection::Ascending,
+        }
+    }
+
+    fn compare(&self, a: &SortRow, b: &SortRow) -> Ordering {
+        match self {
+            SortKey::Name => a.chip.cmp(b.chip),
+            SortKey::Class => a.chip.class.cmp(&b.chip.class),
+            SortKey::Element => a.chip.element.cmp(&b.chip.element),
+            SortKey::MaxDamage => a.chip.max_dmg().cmp(&b.chip.max_dmg()),
+            SortKey::AverageDamage => a.chip.avg_dmg().partial_cmp(&b.chip.avg_dmg()).unwrap_or(Ordering::Equal),
+            SortKey::Skill => a.chip.skill().cmp(&b.chip.skill()),
+            SortKey::Range => a.chip.range.cmp(&b.chip.range),
+            SortKey::Kind => a.chip.kind.cmp(&b.chip.kind),
+            // chips without a hit count go first
+            SortKey::Hits => parse_hits(&a.chip.hits).cmp(&parse_hits(&b.chip.hits)),
+            SortKey::Owned => a.owned.cmp(&b.owned),
+            SortKey::Used => a.used.cmp(&b.used),
+            SortKey::InFolder => a.in_folder.cmp(&b.in_folder),
+            // lower is closer, so ascending puts the best matches first
+            SortKey::Relevance => a.relevance.cmp(&b.relevance),
+        }
+    }
+}
+
+/// What a chip list knows about one of its rows
+pub(crate) struct SortRow<'a> {
+    pub chip: &'a BattleChip,
+    pub owned: u32,
+    pub used: u32,
+    pub in_folder: u32,
+    /// the chip's name search score, every chip ties when there's no name search
+    pub relevance: u32,
+}
+
+impl<'a> SortRow<'a> {
+    pub(crate) fn new(chip: &'a BattleChip) -> SortRow<'a> {
+        SortRow{chip, owned: 0, used: 0, in_folder: 0, relevance: 0}
+    }
+}
+
+/// An ordered list of keys to sort by, the first one wins and the rest break ties
+///
+/// names always break whatever ties are left so the order never jumps around
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SortSpec {
+    keys: Vec<(SortKey, SortDirection)>,
+}
+
+impl Default for SortSpec {
+    /// the order chips have always been listed in
+    fn default() -> Self {
+        SortSpec {
+            keys: vec![(SortKey::Class, SortDirection::Ascending), (SortKey::Name, SortDirection::Ascending)],
+        }
+    }
+}
+
+impl SortSpec {
+    pub(crate) fn primary(&self) -> SortKey {
+        self.keys.first().map_or(SortKey::Name, |(key, _)| *key)
+    }
+
+    /// where a key is in the sort and which way it goes
+    pub(crate) fn position(&self, key: SortKey) -> Option<(usize, SortDirection)> {
+        self.keys.iter().position(|(sort_key, _)| *sort_key == key).map(|idx| (idx, self.keys[idx].1))
+    }
+
+    /// sort by key first, or flip it if it's already first
+    pub(crate) fn sort_by(&mut self, key: SortKey) {
+        if let Some((0, direction)) = self.position(key) {
+            self.keys[0].1 = direction.flip();
+            return;
+        }
+        self.keys.retain(|(sort_key, _)| *sort_key != key);
+        self.keys.insert(0, (key, key.default_direction()));
+        self.keys.truncate(MAX_SORT_KEYS);
+    }
+
+    /// break ties with key, or flip it if it's already being sorted by
+    pub(crate) fn then_by(&mut self, key: SortKey) {
+        match self.position(key) {
+            Some((idx, direction)) => self.keys[idx].1 = direction.flip(),
+            None if self.keys.len() < MAX_SORT_KEYS => self.keys.push((key, key.default_direction())),
+            None => {}
+        }
+    }
+
+    pub(crate) fn compare(&self, a: &SortRow, b: &SortRow) -> Ordering {
+        for (key, direction) in self.keys.iter() {
+            let ordering = key.compare(a, b);
+            let ordering = match direction {
+                SortDirection::Ascending => ordering,
+                SortDirection::Descending => ordering.reverse(),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        a.chip.cmp(b.chip)
+    }
+
+    /// sort anything that can be turned into a row
+    pub(crate) fn sort<T, F>(&self, items: &mut [T], row: F) where F: Fn(&T) -> SortRow {
+        items.sort_by(|a, b| self.compare(&row(a), &row(b)));
+    }
+
+    /// e.g. "Element ▲, Max Damage ▼"
+    pub(crate) fn describe(&self) -> String {
+        self.keys.iter().map(|(key, direction)| String::from(key.label()) + " " + direction.arrow()).collect::<Vec<String>>().join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chips() -> Vec<BattleChip> {
+        serde_json::from_str(r#"[
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": ""},
+            {"Name": "BigBomb", "Element": ["Fire"], "Skills": ["Strength"], "Damage": "4d8", "Class": "Mega", "Type": "Burst", "Range": "Near", "Hits": "1", "Description": ""},
+            {"Name": "Bubbler", "Element": ["Aqua"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1-3", "Description": ""}
+        ]"#).unwrap()
+    }
+
+    fn sorted(spec: &SortSpec, owned: &[u32]) -> Vec<String> {
+        let chips = chips();
+        let mut rows = chips.iter().zip(owned.iter()).collect::<Vec<_>>();
+        spec.sort(&mut rows, |(chip, owned)| SortRow{owned: **owned, ..SortRow::new(chip)});
+        rows.iter().map(|(chip, _)| chip.name.clone()).collect()
+    }
+
+    #[test]
+    fn default_is_class_then_name() {
+        assert_eq!(sorted(&SortSpec::default(), &[0, 0, 0]), vec!["Bubbler", "Cannon", "BigBomb"]);
+    }
+
+    #[test]
+    fn keys_stack_and_flip() {
+        let mut spec = SortSpec::default();
+        spec.sort_by(SortKey::MaxDamage);
+        assert_eq!(spec.position(SortKey::MaxDamage), Some((0, SortDirection::Descending)));
+        // 2d6 ties are broken by class then name
+        assert_eq!(sorted(&spec, &[0, 0, 0]), vec!["BigBomb", "Bubbler", "Cannon"]);
+
+        spec.sort_by(SortKey::MaxDamage);
+        assert_eq!(sorted(&spec, &[0, 0, 0]), vec!["Bubbler", "Cannon", "BigBomb"]);
+        assert_eq!(spec.keys.len(), 3);
+
+        let mut spec = SortSpec::default();
+        spec.sort_by(SortKey::Owned);
+        spec.then_by(SortKey::Hits);
+        assert_eq!(spec.keys.len(), 3);
+        spec.then_by(SortKey::Class);
+        assert_eq!(spec.position(SortKey::Class), Some((1, SortDirection::Descending)));
+        assert_eq!(sorted(&spec, &[2, 1, 2]), vec!["Bubbler", "Cannon", "BigBomb"]);
+    }
+
+    #[test]
+    fn hits_and_type() {
+        let mut spec = SortSpec::default();
+        spec.sort_by(SortKey::Hits);
+        assert_eq!(sorted(&spec, &[0, 0, 0])[0], "Bubbler");
+        spec.sort_by(SortKey::Kind);
+        assert_eq!(sorted(&spec, &[0, 0, 0])[0], "BigBomb");
+        assert_eq!(spec.describe(), "Type ▲, Hits ▼, Class ▲");
+    }
+
+    #[test]
+    fn relevance_only_applies_when_asked_for() {
+        let chips = chips();
+        let mut rows = chips.iter().zip([5, 0, 1].iter()).collect::<Vec<_>>();
+        let mut spec = SortSpec::default();
+        spec.sort(&mut rows, |(chip, relevance)| SortRow{relevance: **relevance, ..SortRow::new(chip)});
+        assert_eq!(rows[0].0.name, "Bubbler");
+
+        spec.sort_by(SortKey::Relevance);
+        spec.sort(&mut rows, |(chip, relevance)| SortRow{relevance: **relevance, ..SortRow::new(chip)});
+        assert_eq!(rows.iter().map(|(chip, _)| chip.name.as_str()).collect::<Vec<&str>>(), vec!["BigBomb", "Bubbler", "Cannon"]);
+    }
+}
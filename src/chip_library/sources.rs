@@ -0,0 +1,280 @@
+use crate::chip_library::{battle_chip::BattleChip, library_cache::CachedLibrary, storage::DataStore, validation::{self, LibraryProblem, ValidatedLibrary}};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// where the namespaces of switched off sources are kept, shared by every profile
+pub(crate) const DISABLED_SOURCES_KEY: &str = "disabled_sources";
+
+/// the last copy of each source that loaded is kept under this followed by its namespace, shared by every profile
+pub(crate) const SOURCE_CACHE_KEY: &str = "source_cache_";
+
+/// what chips.json itself goes by
+pub(crate) const BASE_SOURCE: &str = "Base";
+
+/// what custom chips show as their source, no other source can use it
+pub(crate) const CUSTOM_SOURCE: &str = "Custom";
+
+/// A list of chips loaded on top of chips.json, like an expansion or a GM's campaign overrides
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ChipSource {
+    pub namespace: String,
+    /// the source's text, None if it couldn't be downloaded
+    #[serde(default)]
+    pub data: Option<String>,
+}
+
+/// How a source fared when the library was loaded
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SourceStatus {
+    pub namespace: String,
+    pub enabled: bool,
+    /// how many chips it added or replaced
+    pub chips: usize,
+    /// why it couldn't be used, if it couldn't
+    pub error: Option<String>,
+    /// what was wrong with the chips that were left out of it
+    pub problems: Vec<LibraryProblem>,
+    /// when the saved copy was saved, if that's what's being used
+    pub cached: Option<f64>,
+}
+
+/// A chip name found in more than one source, the last one listed is what's used
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SourceConflict {
+    pub name: String,
+    pub sources: Vec<String>,
+}
+
+/// Every enabled source's chips laid over each other in order
+pub(crate) struct MergedSources {
+    pub library: HashMap<String, Rc<BattleChip>>,
+    pub sources: Vec<SourceStatus>,
+    pub conflicts: Vec<SourceConflict>,
+    /// the text of every source that was used, just chips.json's if it's the only one
+    pub data: String,
+    pub unavailable: Unavailable,
+}
+
+/// Chips a save can have that aren't in the library because their source is switched off or didn't load
+///
+/// saved copies of them are kept as they are rather than dropped
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Unavailable {
+    /// the chips of switched off sources
+    pub chips: HashSet<String>,
+    /// a source couldn't be read at all, so any chip the library doesn't have might be one of its
+    pub any: bool,
+}
+
+impl Unavailable {
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.any || self.chips.contains(name)
+    }
+}
+
+/// A source's chips and the text they were read from
+struct ReadSource {
+    data: String,
+    validated: ValidatedLibrary,
+    cached: Option<f64>,
+}
+
+/// the namespaces that have been switched off
+pub(crate) fn disabled_sources(storage: &dyn DataStore) -> Vec<String> {
+    storage.get_item(DISABLED_SOURCES_KEY)
+        .and_then(|text| serde_json::from_str::<Vec<String>>(&text).ok())
+        .unwrap_or_default()
+}
+
+/// switch a source on or off for the next time the library is loaded
+pub(crate) fn set_source_enabled(storage: &dyn DataStore, namespace: &str, enabled: bool) -> Result<(), &'static str> {
+    if namespace == BASE_SOURCE {
+        return Err("The base chip library can't be switched off");
+    }
+    let mut disabled = disabled_sources(storage);
+    disabled.retain(|name| name != namespace);
+    if !enabled {
+        disabled.push(namespace.to_owned());
+    }
+    let text = serde_json::to_string(&disabled).map_err(|_| "Failed to serialize disabled sources, inform Major")?;
+    storage.set_item(DISABLED_SOURCES_KEY, &text)
+}
+
+/// read a source, falling back on the copy saved last time if it didn't download or can't be read
+///
+/// a downloaded copy that reads fine replaces the saved one
+fn read_source(source: &ChipSource, storage: &dyn DataStore, now: f64) -> Result<ReadSource, String> {
+    let key = String::from(SOURCE_CACHE_KEY) + &source.namespace;
+    let why = match &source.data {
+        Some(data) => match validation::validate(data) {
+            Ok(validated) => {
+                CachedLibrary::store_in(storage, &key, data, None, now);
+                return Ok(ReadSource{data: data.clone(), validated, cached: None});
+            }
+            Err(why) => why,
+        },
+        None => String::from("Couldn't be downloaded"),
+    };
+    let cached = CachedLibrary::load_from(storage, &key).ok_or(why)?;
+    let validated = validation::validate(&cached.data)?;
+    Ok(ReadSource{data: cached.data, validated, cached: Some(cached.saved)})
+}
+
+/// lay each extra source over chips.json in the order they're listed
+///
+/// a later source replaces chips an earlier one had, which is recorded as a conflict
+pub(crate) fn merge(base: HashMap<String, Rc<BattleChip>>, base_data: &str, extra: &[ChipSource], disabled: &[String], storage: &dyn DataStore, now: f64) -> MergedSources {
+    let mut merged = MergedSources {
+        sources: vec![SourceStatus{namespace: BASE_SOURCE.to_owned(), enabled: true, chips: base.len(), error: None, problems: Vec::new(), cached: None}],
+        library: base,
+        conflicts: Vec::new(),
+        data: base_data.to_owned(),
+        unavailable: Unavailable::default(),
+    };
+
+    for source in extra.iter() {
+        let mut status = SourceStatus{namespace: source.namespace.clone(), enabled: !disabled.contains(&source.namespace), chips: 0, error: None, problems: Vec::new(), cached: None};
+        let taken = merged.sources.iter().any(|other| other.namespace == source.namespace) || source.namespace == CUSTOM_SOURCE;
+        let read = if taken {
+            Err(String::from("Another source already uses this name"))
+        } else {
+            read_source(source, storage, now)
+        };
+        let ReadSource{data, validated, cached} = match read {
+            Ok(read) => read,
+            Err(why) => {
+                merged.unavailable.any = true;
+                // a switched off source isn't expected to load
+                if status.enabled {
+                    status.error = Some(why);
+                }
+                merged.sources.push(status);
+                continue;
+            }
+        };
+
+        if !status.enabled {
+            merged.unavailable.chips.extend(validated.chips.into_iter().map(|chip| chip.name));
+            merged.sources.push(status);
+            continue;
+        }
+        status.chips = validated.chips.len();
+        status.problems = validated.problems;
+        status.cached = cached;
+        merged.data.push('\n');
+        merged.data.push_str(&source.namespace);
+        merged.data.push('\n');
+        merged.data.push_str(&data);
+        for mut chip in validated.chips {
+            chip.source = source.namespace.clone();
+            if let Some(earlier) = merged.library.get(&chip.name) {
+                match merged.conflicts.iter_mut().find(|conflict| conflict.name == chip.name) {
+                    Some(conflict) => conflict.sources.push(source.namespace.clone()),
+                    None => merged.conflicts.push(SourceConflict{name: chip.name.clone(), sources: vec![earlier.source.clone(), source.namespace.clone()]}),
+                }
+            }
+            merged.library.insert(chip.name.clone(), Rc::new(chip));
+        }
+        merged.sources.push(status);
+    }
+    // a chip a switched off source shares with one that's on is still there
+    let library = &merged.library;
+    merged.unavailable.chips.retain(|name| !library.contains_key(name));
+    merged.conflicts.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip_library::storage::MemoryStore;
+
+    fn base() -> HashMap<String, Rc<BattleChip>> {
+        let chips = serde_json::from_str::<Vec<BattleChip>>(r#"[
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A cannon"}
+        ]"#).unwrap();
+        chips.into_iter().map(|mut chip| {
+            chip.source = BASE_SOURCE.to_owned();
+            (chip.name.clone(), Rc::new(chip))
+        }).collect()
+    }
+
+    fn source(namespace: &str, data: Option<&str>) -> ChipSource {
+        ChipSource{namespace: namespace.to_owned(), data: data.map(str::to_owned)}
+    }
+
+    #[test]
+    fn later_sources_win_conflicts() {
+        let expansion = r#"[
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "3d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A better cannon"},
+            {"Name": "Tornado", "Element": ["Wind"], "Skills": ["Tech"], "Damage": "1d6", "Class": "Standard", "Type": "Projectile", "Range": "Near", "Hits": "1-8", "Description": "Wind"}
+        ]"#;
+        let campaign = r#"[
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "1d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A worse cannon"}
+        ]"#;
+        let merged = merge(base(), "[]", &[source("Expansion", Some(expansion)), source("Campaign", Some(campaign))], &[], &MemoryStore::default(), 0.0);
+        assert_eq!(merged.library.len(), 2);
+        assert_eq!(merged.library["Cannon"].source, "Campaign");
+        assert_eq!(merged.library["Tornado"].source, "Expansion");
+        assert_eq!(merged.conflicts, vec![SourceConflict{
+            name: String::from("Cannon"),
+            sources: vec![String::from("Base"), String::from("Expansion"), String::from("Campaign")],
+        }]);
+        assert_eq!(merged.sources.iter().map(|source| source.chips).collect::<Vec<usize>>(), vec![1, 2, 1]);
+    }
+
+    #[test]
+    fn bad_and_disabled_sources_are_skipped() {
+        let extra = [
+            source("Expansion", Some("not json")),
+            source("Missing", None),
+            source("Campaign", Some("[]")),
+            source("Custom", Some("[]")),
+        ];
+        let merged = merge(base(), "[]", &extra, &[String::from("Campaign")], &MemoryStore::default(), 0.0);
+        assert_eq!(merged.library.len(), 1);
+        assert_eq!(merged.data, "[]");
+        assert!(merged.unavailable.any);
+        assert!(merged.sources[1].error.is_some());
+        assert_eq!(merged.sources[2].error.as_deref(), Some("Couldn't be downloaded"));
+        assert!(!merged.sources[3].enabled);
+        assert_eq!(merged.sources[3].error, None);
+        assert!(merged.sources[4].error.is_some());
+    }
+
+    #[test]
+    fn the_last_copy_that_loaded_stands_in() {
+        let storage = MemoryStore::default();
+        let expansion = r#"[
+            {"Name": "Tornado", "Element": ["Wind"], "Skills": ["Tech"], "Damage": "1d6", "Class": "Standard", "Type": "Projectile", "Range": "Near", "Hits": "1-8", "Description": "Wind"}
+        ]"#;
+        let merged = merge(base(), "[]", &[source("Expansion", Some(expansion))], &[], &storage, 1.0);
+        assert_eq!(merged.sources[1].cached, None);
+
+        for data in [None, Some("not json")].iter() {
+            let merged = merge(base(), "[]", &[source("Expansion", *data)], &[], &storage, 2.0);
+            assert!(merged.library.contains_key("Tornado"));
+            assert_eq!(merged.sources[1].error, None);
+            assert_eq!(merged.sources[1].cached, Some(1.0));
+            assert_eq!(merged.unavailable, Unavailable::default());
+        }
+
+        // a switched off source's chips can't be looked up, but it's known which they are
+        let merged = merge(base(), "[]", &[source("Expansion", None)], &[String::from("Expansion")], &storage, 2.0);
+        assert!(!merged.library.contains_key("Tornado"));
+        assert!(merged.unavailable.contains("Tornado"));
+        assert!(!merged.unavailable.contains("Sword"));
+    }
+
+    #[test]
+    fn toggles_are_saved() {
+        let storage = MemoryStore::default();
+        assert!(set_source_enabled(&storage, BASE_SOURCE, false).is_err());
+        set_source_enabled(&storage, "Expansion", false).unwrap();
+        set_source_enabled(&storage, "Campaign", false).unwrap();
+        set_source_enabled(&storage, "Expansion", true).unwrap();
+        assert_eq!(disabled_sources(&storage), vec![String::from("Campaign")]);
+    }
+}
@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Key/value persistence used by the chip library
+///
+/// the webapp backs this with localStorage, tests and browsers
+/// without localStorage use a `MemoryStore`
+pub(crate) trait DataStore {
+    fn get_item(&self, key: &str) -> Option<String>;
+
+    fn set_item(&self, key: &str, value: &str) -> Result<(), &'static str>;
+
+    fn remove_item(&self, key: &str);
+
+    /// every key with something saved under it, in no particular order
+    fn keys(&self) -> Vec<String>;
+}
+
+/// How the chip library tells the user about problems
+///
+/// the webapp backs this with `window.alert` and `window.confirm`
+pub(crate) trait Notifier {
+    fn alert(&self, msg: &str);
+
+    /// ask a yes or no question, true for yes
+    fn confirm(&self, msg: &str) -> bool;
+}
+
+impl<T: DataStore + ?Sized> DataStore for Rc<T> {
+    fn get_item(&self, key: &str) -> Option<String> {
+        (**self).get_item(key)
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<(), &'static str> {
+        (**self).set_item(key, value)
+    }
+
+    fn remove_item(&self, key: &str) {
+        (**self).remove_item(key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        (**self).keys()
+    }
+}
+
+impl<T: Notifier + ?Sized> Notifier for Rc<T> {
+    fn alert(&self, msg: &str) {
+        (**self).alert(msg)
+    }
+
+    fn confirm(&self, msg: &str) -> bool {
+        (**self).confirm(msg)
+    }
+}
+
+/// A store that only lives as long as the page does
+#[derive(Default)]
+pub(crate) struct MemoryStore {
+    items: RefCell<HashMap<String, String>>,
+}
+
+impl DataStore for MemoryStore {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.items.borrow().get(key).cloned()
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<(), &'static str> {
+        let mut items = self.items.try_borrow_mut().map_err(|_| "Failed to borrow storage, inform Major")?;
+        items.insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    fn remove_item(&self, key: &str) {
+        if let Ok(mut items) = self.items.try_borrow_mut() {
+            items.remove(key);
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.items.borrow().keys().cloned().collect()
+    }
+}
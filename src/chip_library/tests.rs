@@ -0,0 +1,899 @@
+use super::*;
+use super::skills::Skills;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+const CHIPS: &str = r#"[
+    {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A basic cannon"},
+    {"Name": "Sword", "Element": ["Sword"], "Skills": ["Strength"], "Damage": "3d6", "Class": "Standard", "Type": "Melee", "Range": "Close", "Hits": "1", "Description": "A basic sword"},
+    {"Name": "BigBomb", "Element": ["Fire"], "Skills": ["Strength", "Tech"], "Damage": "4d8", "Class": "Mega", "Type": "Burst", "Range": "Near", "Hits": "1", "Description": "A big bomb"}
+]"#;
+
+/// records every alert and question so tests can check for them, questions get `answer`
+#[derive(Default)]
+struct RecordingNotifier {
+    messages: RefCell<Vec<String>>,
+    questions: RefCell<Vec<String>>,
+    answer: Cell<bool>,
+}
+
+impl Notifier for RecordingNotifier {
+    fn alert(&self, msg: &str) {
+        self.messages.borrow_mut().push(msg.to_owned());
+    }
+
+    fn confirm(&self, msg: &str) -> bool {
+        self.questions.borrow_mut().push(msg.to_owned());
+        self.answer.get()
+    }
+}
+
+/// ticks forward one second every time it's read
+#[derive(Default)]
+struct TickingClock {
+    time: Cell<f64>,
+}
+
+impl Clock for TickingClock {
+    fn now(&self) -> f64 {
+        let time = self.time.get() + 1000.0;
+        self.time.set(time);
+        time
+    }
+}
+
+fn library_with(storage: Rc<MemoryStore>, notifier: Rc<RecordingNotifier>) -> ChipLibrary {
+    ChipLibrary::new(CHIPS, Some(Box::new(storage)), Box::new(notifier), Box::new(TickingClock::default())).unwrap()
+}
+
+fn library() -> ChipLibrary {
+    library_with(Rc::new(MemoryStore::default()), Rc::new(RecordingNotifier::default()))
+}
+
+fn pack_counts(library: &ChipLibrary, name: &str) -> Option<(u32, u32)> {
+    library.pack.borrow().get(name).map(|chip| (chip.owned, chip.used))
+}
+
+#[test]
+fn bad_library_is_reported() {
+    let notifier = Rc::new(RecordingNotifier::default());
+    let res = ChipLibrary::new("not json", None, Box::new(Rc::clone(&notifier)), Box::new(TickingClock::default()));
+    assert!(res.is_err());
+    assert_eq!(notifier.messages.borrow().len(), 1);
+}
+
+#[test]
+fn move_to_folder_takes_from_pack() {
+    let library = library();
+    library.add_copy_to_pack("Cannon");
+    library.add_copy_to_pack("Cannon");
+
+    assert_eq!(library.move_to_folder("Cannon"), Ok(false));
+    assert_eq!(pack_counts(&library, "Cannon"), Some((1, 0)));
+    assert_eq!(library.move_to_folder("Cannon"), Ok(true));
+    assert_eq!(pack_counts(&library, "Cannon"), None);
+    assert_eq!(library.folder.borrow().len(), 2);
+    assert!(library.move_to_folder("Cannon").is_err());
+}
+
+#[test]
+fn move_to_folder_respects_limits() {
+    let library = library();
+    for _ in 0..5 {
+        library.add_copy_to_pack("Cannon");
+    }
+    library.add_copy_to_pack("BigBomb");
+    library.add_copy_to_pack("BigBomb");
+
+    // standard chips are limited to 3 copies, megas to 1
+    for _ in 0..3 {
+        assert!(library.move_to_folder("Cannon").is_ok());
+    }
+    assert!(library.move_to_folder("Cannon").is_err());
+    assert!(library.move_to_folder("BigBomb").is_ok());
+    assert!(library.move_to_folder("BigBomb").is_err());
+
+    assert_eq!(library.update_chip_limit(4), Ok(true));
+    library.add_copy_to_pack("Sword");
+    assert_eq!(library.move_to_folder("Sword"), Err("Your folder is full"));
+}
+
+#[test]
+fn used_pack_copies_stay_in_pack() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.pack.borrow_mut().get_mut("Sword").unwrap().used = 1;
+    assert!(library.move_to_folder("Sword").is_err());
+}
+
+#[test]
+fn return_to_pack_keeps_used_state() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+    library.flip_used_folder(0);
+
+    assert_eq!(library.return_fldr_chip_to_pack(0), Ok(true));
+    assert_eq!(pack_counts(&library, "Sword"), Some((2, 1)));
+    assert!(library.folder.borrow().is_empty());
+    assert!(library.return_fldr_chip_to_pack(0).is_err());
+}
+
+#[test]
+fn clear_folder_returns_everything() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Cannon");
+    library.move_to_folder("Sword").unwrap();
+    library.move_to_folder("Cannon").unwrap();
+    library.flip_used_folder(1);
+
+    assert_eq!(library.clear_folder(), 2);
+    assert_eq!(pack_counts(&library, "Sword"), Some((1, 0)));
+    assert_eq!(pack_counts(&library, "Cannon"), Some((1, 1)));
+}
+
+#[test]
+fn jack_out_marks_everything_unused() {
+    let library = library();
+    for _ in 0..3 {
+        library.add_copy_to_pack("Sword");
+    }
+    library.move_to_folder("Sword").unwrap();
+    library.flip_used_folder(0);
+    library.pack.borrow_mut().get_mut("Sword").unwrap().used = 2;
+
+    assert_eq!(library.jack_out(), 3);
+    assert!(!library.folder.borrow()[0].used);
+    assert_eq!(pack_counts(&library, "Sword"), Some((2, 0)));
+    assert_eq!(library.jack_out(), 0);
+}
+
+#[test]
+fn chip_limit_cannot_drop_below_folder_size() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Cannon");
+    library.move_to_folder("Sword").unwrap();
+    library.move_to_folder("Cannon").unwrap();
+
+    assert!(library.update_chip_limit(1).is_err());
+    assert_eq!(library.update_chip_limit(2), Ok(true));
+    assert_eq!(library.update_chip_limit(2), Ok(false));
+}
+
+#[test]
+fn saved_data_is_reloaded() {
+    let storage = Rc::new(MemoryStore::default());
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+    library.flip_used_folder(0);
+    library.update_chip_limit(20).unwrap();
+    library.save_data().unwrap();
+
+    let reloaded = library_with(storage, Rc::new(RecordingNotifier::default()));
+    assert_eq!(pack_counts(&reloaded, "Sword"), Some((1, 0)));
+    assert_eq!(reloaded.folder.borrow().len(), 1);
+    assert!(reloaded.folder.borrow()[0].used);
+    assert_eq!(reloaded.chip_limit.load(Ordering::Relaxed), 20);
+}
+
+#[test]
+fn missing_chips_are_reported_on_load() {
+    let storage = Rc::new(MemoryStore::default());
+    storage.set_item("pack", r#"{"Gone": {"owned": 2, "used": 1}, "Sword": {"owned": 1, "used": 0}}"#).unwrap();
+    let notifier = Rc::new(RecordingNotifier::default());
+    let library = library_with(storage, Rc::clone(&notifier));
+
+    assert_eq!(pack_counts(&library, "Sword"), Some((1, 0)));
+    assert_eq!(pack_counts(&library, "Gone"), None);
+    assert_eq!(notifier.messages.borrow().len(), 1);
+}
+
+#[test]
+fn erase_data_clears_storage() {
+    let storage = Rc::new(MemoryStore::default());
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    library.add_copy_to_pack("Sword");
+    library.save_data().unwrap();
+    assert!(storage.get_item("save").is_some());
+
+    library.erase_data();
+    assert!(library.pack.borrow().is_empty());
+    assert!(storage.get_item("save").is_none());
+}
+
+#[test]
+fn combat_log_records_a_fight() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Cannon");
+    library.move_to_folder("Sword").unwrap();
+    library.move_to_folder("Cannon").unwrap();
+    library.flip_used_folder(0);
+    library.flip_used_folder(1);
+    library.flip_used_folder(1);
+    library.flip_used_folder(1);
+    library.return_fldr_chip_to_pack(1).unwrap();
+    library.mark_pack_copy_unused("Cannon").unwrap();
+    library.jack_out();
+
+    let log = library.combat_log.borrow();
+    let events = log.entries().iter().map(|entry| entry.event.clone()).collect::<Vec<LogEvent>>();
+    assert_eq!(events, vec![
+        LogEvent::FolderChipUsed{name: "Sword".to_owned()},
+        LogEvent::FolderChipUsed{name: "Cannon".to_owned()},
+        LogEvent::FolderChipUnused{name: "Cannon".to_owned()},
+        LogEvent::FolderChipUsed{name: "Cannon".to_owned()},
+        LogEvent::PackCopyUsed{name: "Cannon".to_owned(), used: 1},
+        LogEvent::PackCopyUnused{name: "Cannon".to_owned(), used: 0},
+        LogEvent::JackOut{count: 1},
+    ]);
+    assert!(log.entries().windows(2).all(|pair| pair[0].time < pair[1].time));
+}
+
+#[test]
+fn combat_log_is_exported() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+    library.flip_used_folder(0);
+
+    let exported: serde_json::Value = serde_json::from_str(&library.export_json().unwrap()).unwrap();
+    let log = exported["Log"].as_array().unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0]["kind"], "FolderChipUsed");
+    assert_eq!(log[0]["name"], "Sword");
+    // the first tick dates the saved copy of the library
+    assert_eq!(log[0]["time"], 2000.0);
+}
+
+#[test]
+fn exporting_while_the_folder_is_in_use_fails() {
+    let library = library();
+    let folder = library.folder.borrow_mut();
+    assert!(library.export_json().is_err());
+    drop(folder);
+    assert!(library.export_json().is_ok());
+}
+
+#[test]
+fn undo_and_redo_restore_pack_and_folder() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+    library.flip_used_folder(0);
+    library.jack_out();
+
+    assert_eq!(library.undo(), Ok(Some(Command::JackOut)));
+    assert!(library.folder.borrow()[0].used);
+    assert_eq!(library.undo(), Ok(Some(Command::MarkFolderChip("Sword".to_owned(), true))));
+    assert!(!library.folder.borrow()[0].used);
+    assert_eq!(library.undo(), Ok(Some(Command::MoveToFolder("Sword".to_owned()))));
+    assert!(library.folder.borrow().is_empty());
+    assert_eq!(pack_counts(&library, "Sword"), Some((2, 0)));
+
+    assert_eq!(library.redo(), Ok(Some(Command::MoveToFolder("Sword".to_owned()))));
+    assert_eq!(library.folder.borrow().len(), 1);
+    assert_eq!(pack_counts(&library, "Sword"), Some((1, 0)));
+    assert_eq!(library.redo(), Ok(Some(Command::MarkFolderChip("Sword".to_owned(), true))));
+    assert!(library.folder.borrow()[0].used);
+    assert_eq!(library.redo(), Ok(Some(Command::JackOut)));
+    assert!(!library.folder.borrow()[0].used);
+    assert_eq!(library.redo(), Ok(None));
+}
+
+#[test]
+fn unrecorded_changes_are_never_undone() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+
+    // a change the history didn't see drops it, rather than undo quietly reverting the change too
+    let mut save = library.to_save_data(false).unwrap();
+    save.remove_chip("Sword");
+    library.reload(save, false);
+    assert_eq!(library.undo(), Ok(None));
+    assert!(library.folder.borrow().is_empty());
+}
+
+#[test]
+fn reordering_the_folder_keeps_the_history() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Cannon");
+    library.move_to_folder("Sword").unwrap();
+    library.move_to_folder("Cannon").unwrap();
+
+    library.folder.borrow_mut().reverse();
+    assert_eq!(library.undo(), Ok(Some(Command::MoveToFolder("Cannon".to_owned()))));
+    library.folder.borrow_mut().reverse();
+    assert_eq!(library.redo(), Ok(Some(Command::MoveToFolder("Cannon".to_owned()))));
+    assert_eq!(library.folder.borrow().len(), 2);
+}
+
+#[test]
+fn clear_folder_and_chip_limit_can_be_undone() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Cannon");
+    library.move_to_folder("Sword").unwrap();
+    library.move_to_folder("Cannon").unwrap();
+    library.update_chip_limit(20).unwrap();
+    library.clear_folder();
+
+    assert_eq!(library.undo(), Ok(Some(Command::ClearFolder)));
+    let folder = library.folder.borrow().iter().map(|chip| chip.name.clone()).collect::<Vec<String>>();
+    assert_eq!(folder, vec!["Sword".to_owned(), "Cannon".to_owned()]);
+    assert_eq!(pack_counts(&library, "Sword"), None);
+
+    assert_eq!(library.undo(), Ok(Some(Command::UpdateChipLimit(20))));
+    assert_eq!(library.chip_limit.load(Ordering::Relaxed), 12);
+}
+
+#[test]
+fn failed_changes_are_not_recorded() {
+    let library = library();
+    assert!(library.move_to_folder("Sword").is_err());
+    assert!(library.remove_from_pack("Sword").is_err());
+    assert_eq!(library.clear_folder(), 0);
+    assert_eq!(library.undo(), Ok(None));
+
+    library.add_copy_to_pack("Sword");
+    library.erase_data();
+    assert_eq!(library.undo(), Ok(None));
+}
+
+#[test]
+fn switching_presets_moves_chips_through_the_pack() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Cannon");
+    library.add_copy_to_pack("Cannon");
+    library.move_to_folder("Sword").unwrap();
+    library.move_to_folder("Cannon").unwrap();
+    library.save_preset("Melee").unwrap();
+    library.clear_folder();
+
+    library.move_to_folder("Cannon").unwrap();
+    library.move_to_folder("Cannon").unwrap();
+    library.save_preset("Ranged").unwrap();
+
+    assert_eq!(library.switch_preset("Melee"), Ok(Vec::new()));
+    let folder = library.folder.borrow().iter().map(|chip| chip.name.clone()).collect::<Vec<String>>();
+    assert_eq!(folder, vec!["Sword".to_owned(), "Cannon".to_owned()]);
+    assert_eq!(pack_counts(&library, "Cannon"), Some((1, 0)));
+    assert_eq!(library.presets.borrow().active(), Some("Melee"));
+
+    // the only other copy of sword is used, so the pack can't supply it
+    library.presets.borrow_mut().save("Double Sword", vec!["Sword".to_owned(), "Sword".to_owned()]).unwrap();
+    library.add_copy_to_pack("Sword");
+    library.pack.borrow_mut().get_mut("Sword").unwrap().used = 1;
+    let unsupplied = library.switch_preset("Double Sword").unwrap();
+    assert_eq!(unsupplied.len(), 1);
+    assert_eq!(library.folder.borrow().len(), 1);
+
+    assert_eq!(library.undo(), Ok(Some(Command::SwitchPreset("Double Sword".to_owned()))));
+    assert_eq!(library.folder.borrow().len(), 2);
+}
+
+#[test]
+fn presets_are_saved_and_exported() {
+    let storage = Rc::new(MemoryStore::default());
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+    library.save_preset("Melee").unwrap();
+    library.rename_preset("Melee", "Close Range").unwrap();
+    library.save_data().unwrap();
+
+    let reloaded = library_with(storage, Rc::new(RecordingNotifier::default()));
+    assert_eq!(reloaded.presets.borrow().get("Close Range").unwrap().chips, vec!["Sword".to_owned()]);
+
+    let exported = library.export_json().unwrap();
+    let imported = library_with(Rc::new(MemoryStore::default()), Rc::new(RecordingNotifier::default()));
+    imported.import_save(&SaveData::from_json(&exported).unwrap(), ImportMode::Replace).unwrap();
+    assert_eq!(*imported.presets.borrow(), *library.presets.borrow());
+}
+
+#[test]
+fn profiles_keep_separate_data() {
+    let storage = Rc::new(MemoryStore::default());
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+
+    library.create_profile("Second").unwrap();
+    library.switch_profile("Second").unwrap();
+    assert_eq!(library.active_profile(), "Second");
+    assert!(library.folder.borrow().is_empty());
+    assert!(library.pack.borrow().is_empty());
+    assert_eq!(library.undo(), Ok(None));
+    library.add_copy_to_pack("Cannon");
+    library.save_data().unwrap();
+
+    // the default profile keeps using the keys from before profiles existed
+    assert!(storage.get_item("save").unwrap().contains("Sword"));
+
+    let reloaded = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    assert_eq!(reloaded.active_profile(), "Second");
+    assert_eq!(pack_counts(&reloaded, "Cannon"), Some((1, 0)));
+
+    reloaded.switch_profile("Default").unwrap();
+    assert_eq!(reloaded.folder.borrow().len(), 1);
+    assert_eq!(pack_counts(&reloaded, "Cannon"), None);
+
+    let exported: serde_json::Value = serde_json::from_str(&reloaded.export_profile_json("Second").unwrap()).unwrap();
+    assert_eq!(exported["Pack"]["Cannon"]["owned"], 1);
+
+    reloaded.erase_data();
+    assert!(storage.get_item("save").is_none());
+    reloaded.rename_profile("Second", "Alt").unwrap();
+    reloaded.delete_profile("Alt").unwrap();
+    assert!(reloaded.switch_profile("Alt").is_err());
+    assert!(storage.get_item("profile_1_save").is_none());
+}
+
+#[test]
+fn unreadable_profiles_are_reported_and_not_overwritten() {
+    let storage = Rc::new(MemoryStore::default());
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    library.create_profile("Second").unwrap();
+    library.switch_profile("Second").unwrap();
+    library.add_copy_to_pack("Cannon");
+    library.save_data().unwrap();
+    storage.set_item("profiles", "{broken").unwrap();
+
+    let notifier = Rc::new(RecordingNotifier::default());
+    let reloaded = library_with(Rc::clone(&storage), Rc::clone(&notifier));
+    assert_eq!(reloaded.active_profile(), "Default");
+    assert_eq!(notifier.messages.borrow().len(), 1);
+    assert_eq!(storage.get_item("profiles_backup").as_deref(), Some("{broken"));
+
+    // a new profile doesn't pick up the lost one's chips
+    reloaded.create_profile("Third").unwrap();
+    reloaded.switch_profile("Third").unwrap();
+    assert!(reloaded.pack.borrow().is_empty());
+    assert!(storage.get_item("profile_1_save").unwrap().contains("Cannon"));
+}
+
+#[test]
+fn legacy_storage_is_migrated() {
+    let storage = Rc::new(MemoryStore::default());
+    storage.set_item("pack", r#"{"Sword": {"owned": 2, "used": 1}}"#).unwrap();
+    storage.set_item("folder", r#"[{"name": "Cannon", "used": true}]"#).unwrap();
+    storage.set_item("chip_limit", "20").unwrap();
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+
+    assert_eq!(pack_counts(&library, "Sword"), Some((2, 1)));
+    assert!(library.folder.borrow()[0].used);
+    assert_eq!(library.chip_limit.load(Ordering::Relaxed), 20);
+
+    library.add_copy_to_pack("Sword");
+    library.save_data().unwrap();
+    assert!(storage.get_item("pack").is_none());
+    assert!(storage.get_item("chip_limit").is_none());
+    let save = SaveData::from_json(&storage.get_item("save").unwrap()).unwrap();
+    assert_eq!(save.pack["Sword"].owned, 3);
+}
+
+#[test]
+fn malformed_storage_is_reported_and_kept() {
+    let storage = Rc::new(MemoryStore::default());
+    storage.set_item("pack", r#"{"Sword": {"owned": "lots"}}"#).unwrap();
+    let notifier = Rc::new(RecordingNotifier::default());
+    let library = library_with(Rc::clone(&storage), Rc::clone(&notifier));
+
+    assert!(library.pack.borrow().is_empty());
+    assert_eq!(notifier.messages.borrow().len(), 1);
+    assert!(notifier.messages.borrow()[0].contains("Sword"));
+    assert!(storage.get_item("save_backup").unwrap().contains("lots"));
+
+    // importing over it doesn't throw the backup away
+    library.import_save(&SaveData::from_json(r#"{"Limit": 12, "Pack": {"Sword": {"owned": 1, "used": 0}}}"#).unwrap(), ImportMode::Replace).unwrap();
+    assert_eq!(pack_counts(&library, "Sword"), Some((1, 0)));
+    assert!(storage.get_item("save_backup").unwrap().contains("lots"));
+}
+
+#[test]
+fn imports_are_previewed_before_merging() {
+    let library = library();
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+    let incoming = SaveData::from_json(r#"{"Limit": 12, "Pack": {"Sword": {"owned": 1, "used": 0}, "Cannon": {"owned": 1, "used": 0}}}"#).unwrap();
+
+    let preview = library.preview_import(&incoming, ImportMode::Replace).unwrap();
+    assert_eq!(preview.added.len(), 1);
+    assert_eq!(preview.changed[0].before, 2);
+    assert_eq!(preview.changed[0].after, 1);
+    // previewing doesn't change anything
+    assert_eq!(pack_counts(&library, "Sword"), Some((1, 0)));
+    assert_eq!(library.folder.borrow().len(), 1);
+
+    library.import_save(&incoming, ImportMode::MergeAdd).unwrap();
+    assert_eq!(pack_counts(&library, "Sword"), Some((2, 0)));
+    assert_eq!(pack_counts(&library, "Cannon"), Some((1, 0)));
+    assert_eq!(library.folder.borrow().len(), 1);
+}
+
+#[test]
+fn export_txt_round_trips() {
+    let library = library();
+    for _ in 0..3 {
+        library.add_copy_to_pack("Cannon");
+    }
+    library.add_copy_to_pack("Sword");
+    library.add_copy_to_pack("Sword");
+    library.move_to_folder("Sword").unwrap();
+    library.pack.borrow_mut().get_mut("Cannon").unwrap().used = 2;
+    library.folder.borrow_mut()[0].used = true;
+
+    let text = library.export_txt().unwrap();
+    assert!(text.contains("Cannon x3 (2 Used)"));
+
+    let save = library.parse_txt(&text).unwrap();
+    assert_eq!(save.pack["Cannon"], SavedPackChip{owned: 3, used: 2});
+    assert_eq!(save.pack["Sword"], SavedPackChip{owned: 1, used: 0});
+    assert_eq!(save.folder, vec![SavedFolderChip{name: String::from("Sword"), used: true}]);
+}
+
+#[test]
+fn pasted_text_with_too_many_chips_is_refused() {
+    let library = library();
+    let err = library.parse_txt("Folder: Cannon x40, Sword x4000000000").unwrap_err();
+    assert!(err.contains("no folder can hold more than 45"));
+    assert!(library.parse_txt("Folder: Cannon x45").is_ok());
+
+    let err = library.parse_txt("Pack: Cannon x4294967295, Cannon").unwrap_err();
+    assert!(err.contains("too many copies of Cannon"));
+    let save = library.parse_txt("Pack: Cannon x4294967294, Cannon").unwrap();
+    assert_eq!(save.pack["Cannon"].owned, u32::MAX);
+}
+
+#[test]
+fn pasted_text_is_matched_to_the_library() {
+    let notifier = Rc::new(RecordingNotifier::default());
+    notifier.answer.set(true);
+    let library = library_with(Rc::new(MemoryStore::default()), Rc::clone(&notifier));
+    let save = library.parse_txt("folder: sword, SWORD\npack: bigbomb x2").unwrap();
+    assert_eq!(save.folder.len(), 2);
+    assert_eq!(save.folder[0].name, "Sword");
+    assert_eq!(save.pack["BigBomb"].owned, 2);
+
+    let mut save = library.parse_txt("Pack: Sword, Rocket, Canon x2").unwrap();
+    let skipped = library.resolve_import(&mut save);
+    assert_eq!(skipped, vec!["Rocket"]);
+    assert_eq!(save.pack.keys().collect::<Vec<_>>(), vec!["Cannon", "Sword"]);
+    assert_eq!(notifier.questions.borrow().len(), 1);
+    assert!(notifier.questions.borrow()[0].contains("did you mean \"Cannon\""));
+}
+
+#[test]
+fn misspelled_chips_can_be_renamed_on_load() {
+    let storage = Rc::new(MemoryStore::default());
+    storage.set_item("pack", r#"{"Canon": {"owned": 1, "used": 0}, "Cannon": {"owned": 1, "used": 1}, "Rocket": {"owned": 1, "used": 0}}"#).unwrap();
+    storage.set_item("folder", r#"[{"name": "big bomb", "used": false}]"#).unwrap();
+    let notifier = Rc::new(RecordingNotifier::default());
+    notifier.answer.set(true);
+    let library = library_with(Rc::clone(&storage), Rc::clone(&notifier));
+
+    assert_eq!(pack_counts(&library, "Cannon"), Some((2, 1)));
+    assert_eq!(library.folder.borrow()[0].name, "BigBomb");
+    assert_eq!(notifier.questions.borrow().len(), 2);
+    // nothing close to Rocket, so it's reported like before
+    assert_eq!(notifier.messages.borrow().len(), 1);
+
+    // the new names are saved without anything else changing
+    library.save_data().unwrap();
+    let save = SaveData::from_json(&storage.get_item("save").unwrap()).unwrap();
+    assert_eq!(save.folder[0].name, "BigBomb");
+}
+
+#[test]
+fn renamed_chips_keep_their_copies() {
+    let chips = r#"[
+        {"Name": "Cannon", "Formerly": ["OldCannon"], "Element": ["Null"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": ""},
+        {"Name": "Sword", "Aliases": ["Blade", "Boom"], "Element": ["Sword"], "Skills": ["Strength"], "Damage": "3d6", "Class": "Standard", "Type": "Melee", "Range": "Close", "Hits": "1", "Description": ""},
+        {"Name": "BigBomb", "Aliases": ["Boom"], "Element": ["Fire"], "Skills": ["Strength"], "Damage": "4d8", "Class": "Mega", "Type": "Burst", "Range": "Near", "Hits": "1", "Description": ""}
+    ]"#;
+    let storage = Rc::new(MemoryStore::default());
+    storage.set_item("pack", r#"{"OldCannon": {"owned": 2, "used": 1}, "Cannon": {"owned": 1, "used": 0}, "Boom": {"owned": 1, "used": 0}}"#).unwrap();
+    storage.set_item("folder", r#"[{"name": "Blade", "used": true}]"#).unwrap();
+    storage.set_item("folder_presets", r#"{"presets": [{"name": "Main", "chips": ["OldCannon"]}], "active": null}"#).unwrap();
+    let notifier = Rc::new(RecordingNotifier::default());
+    let library = ChipLibrary::new(chips, Some(Box::new(Rc::clone(&storage))), Box::new(Rc::clone(&notifier)), Box::new(TickingClock::default())).unwrap();
+
+    assert_eq!(pack_counts(&library, "Cannon"), Some((3, 1)));
+    assert_eq!(library.folder.borrow()[0].name, "Sword");
+    assert_eq!(library.presets.borrow().get("Main").unwrap().chips, vec!["Cannon"]);
+    // two chips claim Boom, so it's treated as missing
+    let messages = notifier.messages.borrow();
+    assert_eq!(messages.len(), 2);
+    assert!(messages.iter().any(|msg| msg.contains("Blade -> Sword\nOldCannon -> Cannon")));
+    assert!(messages.iter().any(|msg| msg.contains("\"Boom\"")));
+    drop(messages);
+
+    library.save_data().unwrap();
+    let save = SaveData::from_json(&storage.get_item("save").unwrap()).unwrap();
+    assert_eq!(save.renames, vec![
+        ChipRename{from: String::from("Blade"), to: String::from("Sword")},
+        ChipRename{from: String::from("OldCannon"), to: String::from("Cannon")},
+    ]);
+
+    // imports of old exports are moved over too
+    let mut old_export = SaveData::default();
+    old_export.pack.insert(String::from("OldCannon"), SavedPackChip{owned: 1, used: 0});
+    assert!(library.resolve_import(&mut old_export).is_empty());
+    assert_eq!(old_export.pack.keys().collect::<Vec<_>>(), vec!["Cannon"]);
+}
+
+#[test]
+fn library_changes_are_found_on_the_next_load() {
+    let storage = Rc::new(MemoryStore::default());
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    // nothing to compare against the first time
+    assert!(library.library_diff.is_none());
+    assert!(storage.get_item(LIBRARY_SNAPSHOT_KEY).is_some());
+    drop(library);
+    assert!(library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default())).library_diff.is_none());
+
+    storage.set_item("pack", r#"{"Cannon": {"owned": 1, "used": 0}}"#).unwrap();
+    let chips = CHIPS.replace(r#""Damage": "2d6""#, r#""Damage": "3d6""#).replace("BigBomb", "HugeBomb");
+    let library = ChipLibrary::new(&chips, Some(Box::new(Rc::clone(&storage))), Box::new(RecordingNotifier::default()), Box::new(TickingClock::default())).unwrap();
+    let diff = library.library_diff.as_ref().unwrap();
+    assert_eq!(diff.added, vec!["HugeBomb"]);
+    assert_eq!(diff.removed, vec!["BigBomb"]);
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].damage, DamageChange::Buffed);
+    assert!(library.owns("Cannon"));
+    assert!(!library.owns("Sword"));
+    drop(library);
+
+    // the new version was remembered, so it's only shown once
+    let library = ChipLibrary::new(&chips, Some(Box::new(Rc::clone(&storage))), Box::new(RecordingNotifier::default()), Box::new(TickingClock::default())).unwrap();
+    assert!(library.library_diff.is_none());
+}
+
+#[test]
+fn saved_library_is_used_when_the_download_fails() {
+    let storage = Rc::new(MemoryStore::default());
+    let load = |fetched: Option<&str>, etag: Option<&str>, notifier: Rc<RecordingNotifier>| {
+        ChipLibrary::load(fetched, etag, &[], RuntimeConfig::default(), Some(Box::new(Rc::clone(&storage))), Box::new(notifier), Box::new(TickingClock::default()))
+    };
+
+    // nothing to fall back on yet
+    let notifier = Rc::new(RecordingNotifier::default());
+    assert!(load(None, None, Rc::clone(&notifier)).is_err());
+    assert_eq!(notifier.messages.borrow().len(), 1);
+
+    let library = load(Some(CHIPS), Some("v1"), Rc::default()).unwrap();
+    assert!(library.cached_library.is_none());
+    drop(library);
+    assert_eq!(CachedLibrary::etag(&*storage).as_deref(), Some("v1"));
+
+    // offline
+    let library = load(None, None, Rc::default()).unwrap();
+    assert!(library.cached_library.is_some());
+    assert!(library.library.contains_key("Cannon"));
+    drop(library);
+
+    // the server said it hasn't changed
+    assert!(load(None, Some("v1"), Rc::default()).unwrap().cached_library.is_none());
+
+    // a broken download doesn't replace the good copy
+    let notifier = Rc::new(RecordingNotifier::default());
+    let library = load(Some("[{\"Name\": "), Some("v2"), Rc::clone(&notifier)).unwrap();
+    assert!(library.cached_library.is_some());
+    assert_eq!(notifier.messages.borrow().len(), 1);
+    drop(library);
+    assert_eq!(CachedLibrary::etag(&*storage).as_deref(), Some("v1"));
+
+    // a damaged copy isn't trusted
+    let mut cached = CachedLibrary::load(&*storage).unwrap();
+    cached.data.truncate(20);
+    storage.set_item(library_cache::LIBRARY_CACHE_KEY, &serde_json::to_string(&cached).unwrap()).unwrap();
+    assert!(load(None, None, Rc::default()).is_err());
+}
+
+fn custom_chip(name: &str) -> BattleChip {
+    let mut chip = BattleChip::blank_custom();
+    chip.name = name.to_owned();
+    chip.element.push(Elements::Aqua);
+    chip.skills.push(Skills::Tech);
+    chip.damage = "3d6".to_owned();
+    chip.description = "A GM's cannon".to_owned();
+    chip
+}
+
+#[test]
+fn custom_chips_are_saved_and_exported() {
+    let storage = Rc::new(MemoryStore::default());
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    library.save_custom_chip(custom_chip("TideCannon"), None).unwrap();
+    library.add_copy_to_pack("TideCannon");
+    library.add_copy_to_pack("TideCannon");
+    library.move_to_folder("TideCannon").unwrap();
+    library.save_data().unwrap();
+
+    let reloaded = library_with(storage, Rc::new(RecordingNotifier::default()));
+    assert_eq!(pack_counts(&reloaded, "TideCannon"), Some((1, 0)));
+    assert_eq!(reloaded.folder.borrow().len(), 1);
+    assert!(reloaded.get_chip("TideCannon").unwrap().custom);
+    assert!(reloaded.export_json().unwrap().contains("\"CustomChips\""));
+
+    let imported = library_with(Rc::new(MemoryStore::default()), Rc::new(RecordingNotifier::default()));
+    imported.import_save(&SaveData::from_json(&library.export_json().unwrap()).unwrap(), ImportMode::Replace).unwrap();
+    assert_eq!(imported.custom_chips(), vec![custom_chip("TideCannon")]);
+    assert_eq!(pack_counts(&imported, "TideCannon"), Some((1, 0)));
+}
+
+#[test]
+fn custom_chips_with_too_many_hits_are_refused() {
+    let library = library();
+    let mut chip = custom_chip("Gatling");
+    chip.hits = "1-1000".to_owned();
+    assert!(library.save_custom_chip(chip.clone(), None).is_err());
+    chip.hits = "2-6".to_owned();
+    library.save_custom_chip(chip, None).unwrap();
+}
+
+#[test]
+fn custom_chips_can_be_renamed_and_deleted() {
+    let library = library();
+    assert!(library.save_custom_chip(custom_chip("Cannon"), None).is_err());
+    let mut unreadable = custom_chip("TideCannon");
+    unreadable.damage = "2d".to_owned();
+    assert!(library.save_custom_chip(unreadable, None).is_err());
+    library.save_custom_chip(custom_chip("TideCannon"), None).unwrap();
+    assert!(library.save_custom_chip(custom_chip("TideCannon"), None).is_err());
+    library.add_copy_to_pack("TideCannon");
+    library.add_copy_to_pack("TideCannon");
+    library.move_to_folder("TideCannon").unwrap();
+
+    library.save_custom_chip(custom_chip("RiptideCannon"), Some("TideCannon")).unwrap();
+    assert_eq!(pack_counts(&library, "RiptideCannon"), Some((1, 0)));
+    assert_eq!(library.folder.borrow()[0].name, "RiptideCannon");
+    assert!(library.get_chip("TideCannon").is_none());
+
+    library.delete_custom_chip("RiptideCannon").unwrap();
+    assert_eq!(pack_counts(&library, "RiptideCannon"), None);
+    assert!(library.folder.borrow().is_empty());
+    assert!(library.custom_chips().is_empty());
+}
+
+#[test]
+fn custom_chips_are_shared_with_the_group() {
+    let library = library();
+    library.save_custom_chip(custom_chip("TideCannon"), None).unwrap();
+    library.add_copy_to_pack("TideCannon");
+    library.move_to_folder("TideCannon").unwrap();
+    library.add_copy_to_pack("Cannon");
+    library.move_to_folder("Cannon").unwrap();
+    assert!(library.folder_changed());
+
+    let shared = library.shared_folder(true);
+    assert!(!library.folder_changed());
+    let cannon = shared.iter().find(|chip| chip.name == "Cannon").unwrap();
+    assert!(cannon.custom.is_none());
+    let tide_cannon = shared.iter().find(|chip| chip.name == "TideCannon").unwrap().clone();
+    let received = GroupFolderChip::from(tide_cannon).custom.unwrap();
+    assert_eq!(received.damage, "3d6");
+    assert_eq!(received.description, "A GM's cannon");
+
+    // a server that doesn't pass custom chips on only gets their names
+    assert!(library.shared_folder(false).iter().all(|chip| chip.custom.is_none()));
+}
+
+#[test]
+fn sources_are_laid_over_the_library() {
+    let storage = Rc::new(MemoryStore::default());
+    let expansion = r#"[
+        {"Name": "Sword", "Element": ["Sword"], "Skills": ["Strength"], "Damage": "4d6", "Class": "Standard", "Type": "Melee", "Range": "Close", "Hits": "1", "Description": "A sharper sword"},
+        {"Name": "Tornado", "Element": ["Wind"], "Skills": ["Tech"], "Damage": "1d6", "Class": "Standard", "Type": "Projectile", "Range": "Near", "Hits": "1-8", "Description": "Wind"}
+    ]"#;
+    let sources = [ChipSource{namespace: String::from("Expansion"), data: Some(expansion.to_owned())}];
+    let load = |storage: &Rc<MemoryStore>| {
+        ChipLibrary::load(Some(CHIPS), None, &sources, RuntimeConfig::default(), Some(Box::new(Rc::clone(storage))), Box::new(Rc::new(RecordingNotifier::default())), Box::new(TickingClock::default())).unwrap()
+    };
+
+    let library = load(&storage);
+    assert_eq!(library.library.len(), 4);
+    assert_eq!(library.get_chip("Sword").unwrap().provenance(), "Expansion");
+    assert_eq!(library.get_chip("Cannon").unwrap().provenance(), BASE_SOURCE);
+    assert_eq!(library.source_conflicts.len(), 1);
+    library.add_copy_to_pack("Tornado");
+    library.set_source_enabled("Expansion", false).unwrap();
+    assert!(library.set_source_enabled("Nowhere", false).is_err());
+
+    let reloaded = load(&storage);
+    assert_eq!(reloaded.library.len(), 3);
+    assert_eq!(reloaded.get_chip("Sword").unwrap().damage, "3d6");
+    assert!(reloaded.source_conflicts.is_empty());
+    assert!(!reloaded.sources[1].enabled);
+    assert_eq!(pack_counts(&reloaded, "Tornado"), None);
+
+    // the copy is put aside rather than lost, even once the data's saved again
+    reloaded.add_copy_to_pack("Cannon");
+    reloaded.save_data().unwrap();
+    reloaded.set_source_enabled("Expansion", true).unwrap();
+    assert_eq!(pack_counts(&load(&storage), "Tornado"), Some((1, 0)));
+}
+
+#[test]
+fn chips_from_sources_that_didnt_load_are_kept() {
+    let storage = Rc::new(MemoryStore::default());
+    let notifier = Rc::new(RecordingNotifier::default());
+    let expansion = r#"[
+        {"Name": "Tornado", "Element": ["Wind"], "Skills": ["Tech"], "Damage": "1d6", "Class": "Standard", "Type": "Projectile", "Range": "Near", "Hits": "1-8", "Description": "Wind"}
+    ]"#;
+    let load = |data: Option<&str>| {
+        let sources = [ChipSource{namespace: String::from("Expansion"), data: data.map(str::to_owned)}];
+        ChipLibrary::load(Some(CHIPS), None, &sources, RuntimeConfig::default(), Some(Box::new(Rc::clone(&storage))), Box::new(Rc::clone(&notifier)), Box::new(TickingClock::default())).unwrap()
+    };
+
+    let library = load(Some(expansion));
+    library.add_copy_to_pack("Tornado");
+    library.move_to_folder("Tornado").unwrap();
+    library.save_data().unwrap();
+
+    // the saved copy of the source stands in for the download
+    assert!(load(None).sources[1].cached.is_some());
+    assert_eq!(load(None).folder.borrow().len(), 1);
+
+    // without one, the chips can't be looked up but they're still saved and still take up room
+    for key in storage.keys().into_iter().filter(|key| key.starts_with("source_cache_")) {
+        storage.remove_item(&key);
+    }
+    let offline = load(None);
+    assert!(offline.folder.borrow().is_empty());
+    assert!(!notifier.messages.borrow().iter().any(|msg| msg.contains("Tornado")));
+    offline.update_chip_limit(1).unwrap();
+    offline.add_copy_to_pack("Cannon");
+    assert!(offline.move_to_folder("Cannon").is_err());
+    offline.save_data().unwrap();
+
+    let back = load(Some(expansion));
+    assert_eq!(back.folder.borrow().iter().map(|chip| chip.name.as_str()).collect::<Vec<&str>>(), ["Tornado"]);
+    assert_eq!(pack_counts(&back, "Cannon"), Some((1, 0)));
+}
+
+#[test]
+fn chips_with_problems_are_skipped_and_reported() {
+    let chips = CHIPS.replace(r#""Skills": ["Strength"]"#, r#""Skills": []"#).replace(r#""Class": "Mega""#, r#""Class": "Ultra""#);
+    let notifier = Rc::new(RecordingNotifier::default());
+    let library = ChipLibrary::new(&chips, None, Box::new(Rc::clone(&notifier)), Box::new(TickingClock::default())).unwrap();
+
+    assert_eq!(library.library.len(), 1);
+    assert!(library.library.contains_key("Cannon"));
+    assert_eq!(library.sources[0].problems.len(), 2);
+    let report = notifier.messages.borrow().iter().find(|msg| msg.starts_with("Some chips from Base")).cloned().unwrap();
+    assert!(report.contains("Sword: has no skills"));
+    assert!(report.contains("BigBomb: Class: unknown variant `Ultra`"));
+}
+
+#[test]
+fn settings_are_kept_per_profile() {
+    let storage = Rc::new(MemoryStore::default());
+    let library = library_with(Rc::clone(&storage), Rc::new(RecordingNotifier::default()));
+    let local = ConfigOverrides{group_server: Some(String::from("ws://localhost:8080/join")), ..ConfigOverrides::default()};
+    assert!(library.save_settings(&ConfigOverrides{group_server: Some(String::from("localhost")), ..ConfigOverrides::default()}).is_err());
+    library.save_settings(&local).unwrap();
+    assert_eq!(library.config().group_server, "ws://localhost:8080/join");
+
+    library.create_profile("Second").unwrap();
+    library.switch_profile("Second").unwrap();
+    assert_eq!(library.config(), AppConfig::default());
+    let runtime = RuntimeConfig{query: ConfigOverrides::from_query("library=chips.json"), ..RuntimeConfig::default()};
+    assert_eq!(ChipLibrary::startup_config(&*storage, &runtime).library_url, "chips.json");
+
+    library.switch_profile("Default").unwrap();
+    assert_eq!(ChipLibrary::startup_config(&*storage, &runtime).group_server, "ws://localhost:8080/join");
+    library.save_settings(&ConfigOverrides::default()).unwrap();
+    assert_eq!(library.settings(), ConfigOverrides::default());
+    assert!(storage.get_item("config").is_none());
+}
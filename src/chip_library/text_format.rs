@@ -0,0 +1,153 @@
+/// One chip from the text export, before its name is checked against the library
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TxtEntry {
+    pub name: String,
+    pub count: u32,
+    pub used: u32,
+}
+
+/// The folder and pack read back from the "Export Txt" format
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ParsedTxt {
+    pub folder: Vec<TxtEntry>,
+    pub pack: Vec<TxtEntry>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Folder,
+    Pack,
+}
+
+/// read text like "Folder: A, B (Used)\nPack: C x3 (2 Used)"
+///
+/// headers and suffixes can be any case, a line without a header carries on the line before it
+pub(crate) fn parse_txt(text: &str) -> Result<ParsedTxt, String> {
+    let mut to_ret = ParsedTxt::default();
+    let mut section = None;
+
+    for line in text.lines() {
+        let mut line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some((header, rest)) = split_header(line) {
+            section = Some(header);
+            line = rest;
+        }
+        let section = section.ok_or("The text should start with \"Folder:\" or \"Pack:\"")?;
+
+        for entry in line.split(',') {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let entry = parse_entry(entry)?;
+            match section {
+                Section::Folder => to_ret.folder.push(entry),
+                Section::Pack => to_ret.pack.push(entry),
+            }
+        }
+    }
+
+    if to_ret.folder.is_empty() && to_ret.pack.is_empty() {
+        return Err(String::from("No chips were found in the text"));
+    }
+    Ok(to_ret)
+}
+
+/// "Folder:" or "Pack:" and the rest of the line after it
+fn split_header(line: &str) -> Option<(Section, &str)> {
+    let colon = line.find(':')?;
+    let header = line[..colon].trim();
+    let section = if header.eq_ignore_ascii_case("folder") {
+        Section::Folder
+    } else if header.eq_ignore_ascii_case("pack") {
+        Section::Pack
+    } else {
+        return None;
+    };
+    Some((section, &line[colon + 1..]))
+}
+
+/// "Name", "Name xN", "Name (Used)", "Name xN (M Used)"
+fn parse_entry(entry: &str) -> Result<TxtEntry, String> {
+    let mut rest = entry;
+    let mut used = None;
+
+    if rest.ends_with(')') {
+        let open = rest.rfind('(').ok_or_else(|| bad_entry(entry))?;
+        let parsed = parse_used(&rest[open + 1..rest.len() - 1]).ok_or_else(|| bad_entry(entry))?;
+        // a bare "(Used)" marks a single copy
+        used = Some(parsed.unwrap_or(1));
+        rest = rest[..open].trim_end();
+    }
+
+    let mut count = 1;
+    if let Some(space) = rest.rfind(char::is_whitespace) {
+        let last = &rest[space + 1..];
+        if last.len() > 1 && (last.starts_with('x') || last.starts_with('X')) {
+            if let Ok(parsed) = last[1..].parse::<u32>() {
+                count = parsed;
+                rest = rest[..space].trim_end();
+            }
+        }
+    }
+
+    let name = rest.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if name.is_empty() || count == 0 {
+        return Err(bad_entry(entry));
+    }
+    let used = used.unwrap_or(0);
+    if used > count {
+        return Err(String::from("\"") + entry + "\" has more copies used than it has");
+    }
+    Ok(TxtEntry{name, count, used})
+}
+
+/// the inside of "(Used)" or "(N Used)", the count is None for a bare "Used"
+fn parse_used(text: &str) -> Option<Option<u32>> {
+    let words = text.split_whitespace().collect::<Vec<&str>>();
+    match words.as_slice() {
+        [used] if used.eq_ignore_ascii_case("used") => Some(None),
+        [count, used] if used.eq_ignore_ascii_case("used") => count.parse::<u32>().ok().map(Some),
+        _ => None,
+    }
+}
+
+fn bad_entry(entry: &str) -> String {
+    String::from("Couldn't read \"") + entry + "\", chips should look like \"Name x2 (1 Used)\""
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, count: u32, used: u32) -> TxtEntry {
+        TxtEntry{name: name.to_owned(), count, used}
+    }
+
+    #[test]
+    fn reads_the_export() {
+        let parsed = parse_txt("Folder: Cannon, Sword (Used)\nPack: BigBomb x3 (2 Used), Cannon (Used), Sword").unwrap();
+        assert_eq!(parsed.folder, vec![entry("Cannon", 1, 0), entry("Sword", 1, 1)]);
+        assert_eq!(parsed.pack, vec![entry("BigBomb", 3, 2), entry("Cannon", 1, 1), entry("Sword", 1, 0)]);
+    }
+
+    #[test]
+    fn tolerates_case_and_whitespace() {
+        let parsed = parse_txt("  PACK :   Big  Bomb X2 ( 1 used ) ,\n Cannon x4\nfolder: sword").unwrap();
+        assert_eq!(parsed.pack, vec![entry("Big Bomb", 2, 1), entry("Cannon", 4, 0)]);
+        assert_eq!(parsed.folder, vec![entry("sword", 1, 0)]);
+    }
+
+    #[test]
+    fn rejects_bad_entries() {
+        assert!(parse_txt("Pack: Sword (lots)").is_err());
+        assert!(parse_txt("Pack: Sword x2 (3 Used)").is_err());
+        assert!(parse_txt("Pack: x3").is_ok()); // a chip could be called x3
+        assert!(parse_txt("Pack: Sword x0").is_err());
+        assert!(parse_txt("Folder:").is_err());
+        assert!(parse_txt("Sword, Cannon").is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::chip_library::{
+    battle_chip::BattleChip,
+    chip_type::{ChipClass, ChipType},
+    dice::{DiceExpr, DEFAULT_SKILL_RANK},
+    elements::Elements,
+    ranges::Ranges,
+    roller::{parse_hits, MAX_HITS},
+    skills::Skills,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// how many problems an alert lists before the rest are just counted
+const MAX_REPORTED: usize = 20;
+
+/// Something wrong with one chip in a library file
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct LibraryProblem {
+    /// the chip's name, or where it is in the list if it doesn't have one
+    pub chip: String,
+    pub problem: String,
+    /// the chip is used anyway, this part of it just can't be worked with
+    pub warning: bool,
+}
+
+/// The chips from a library file that can be used, and what was wrong with the rest
+#[derive(Debug, Default)]
+pub(crate) struct ValidatedLibrary {
+    pub chips: Vec<BattleChip>,
+    pub problems: Vec<LibraryProblem>,
+}
+
+/// serde's message without the list of every variant it expected
+fn short_error(err: serde_json::Error) -> String {
+    let text = err.to_string();
+    match text.find(", expected") {
+        Some(idx) => text[..idx].to_owned(),
+        None => text,
+    }
+}
+
+/// read one field, noting why if it can't be
+fn field<T: DeserializeOwned>(entry: &Map<String, Value>, name: &str, problems: &mut Vec<String>) -> Option<T> {
+    let value = match entry.get(name) {
+        Some(value) => value,
+        None => {
+            problems.push(String::from("has no ") + name);
+            return None;
+        }
+    };
+    match serde_json::from_value::<T>(value.clone()) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            problems.push(String::from(name) + ": " + &short_error(err));
+            None
+        }
+    }
+}
+
+/// everything wrong with one entry, checked field by field so one problem doesn't hide the next
+///
+/// gives back the problems that keep it from being used and the ones that don't
+fn check_entry(entry: &Map<String, Value>) -> (Vec<String>, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut warnings = Vec::new();
+    if let Some(name) = field::<String>(entry, "Name", &mut problems) {
+        if name.trim().is_empty() {
+            problems.push(String::from("has an empty name"));
+        }
+    }
+    if let Some(elements) = field::<Vec<Elements>>(entry, "Element", &mut problems) {
+        if elements.is_empty() {
+            problems.push(String::from("has no elements"));
+        }
+    }
+    if let Some(skills) = field::<Vec<Skills>>(entry, "Skills", &mut problems) {
+        if skills.is_empty() {
+            problems.push(String::from("has no skills, it should list None if it doesn't use one"));
+        }
+    }
+    if let Some(damage) = field::<String>(entry, "Damage", &mut problems) {
+        let trimmed = damage.trim();
+        if !(trimmed.is_empty() || trimmed == "--") {
+            if let Err(err) = DiceExpr::parse(&damage).and_then(|expr| expr.stats(DEFAULT_SKILL_RANK)) {
+                warnings.push(String::from("damage \"") + &damage + "\" can't be read, " + &err.to_string());
+            }
+        }
+    }
+    field::<ChipClass>(entry, "Class", &mut problems);
+    field::<ChipType>(entry, "Type", &mut problems);
+    field::<Ranges>(entry, "Range", &mut problems);
+    if let Some(hits) = field::<String>(entry, "Hits", &mut problems) {
+        let trimmed = hits.trim();
+        if !(trimmed.is_empty() || trimmed == "--") {
+            match parse_hits(trimmed) {
+                Some((_, high)) if high > MAX_HITS => problems.push(String::from("has more than ") + &MAX_HITS.to_string() + " hits"),
+                Some(_) => {}
+                None => warnings.push(String::from("hits \"") + &hits + "\" can't be read, it should be a number or a range like 1-3"),
+            }
+        }
+    }
+    field::<String>(entry, "Description", &mut problems);
+    (problems, warnings)
+}
+
+/// read a library file, keeping every chip that's fine and noting what's wrong with the rest
+///
+/// only text that isn't a list at all is an error
+pub(crate) fn validate(data: &str) -> Result<ValidatedLibrary, String> {
+    let entries = serde_json::from_str::<Vec<Value>>(data).map_err(|e| e.to_string())?;
+    let mut validated = ValidatedLibrary::default();
+    let mut seen = HashSet::new();
+
+    for (idx, entry) in entries.into_iter().enumerate() {
+        let label = entry.get("Name").and_then(Value::as_str).map(str::to_owned).unwrap_or_else(|| String::from("Chip #") + &(idx + 1).to_string());
+        let (mut problems, mut warnings) = match entry.as_object() {
+            Some(object) => check_entry(object),
+            None => (vec![String::from("isn't a chip")], Vec::new()),
+        };
+        if problems.is_empty() {
+            match serde_json::from_value::<BattleChip>(entry) {
+                // a copy that can't be used doesn't keep a later one from being used
+                Ok(chip) if seen.insert(chip.name.clone()) => validated.chips.push(chip),
+                Ok(_) => problems.push(String::from("is listed more than once, only the first one that can be used is")),
+                Err(err) => problems.push(short_error(err)),
+            }
+        }
+        if !problems.is_empty() {
+            problems.append(&mut warnings);
+        }
+        validated.problems.extend(problems.into_iter().map(|problem| LibraryProblem{chip: label.clone(), problem, warning: false}));
+        validated.problems.extend(warnings.into_iter().map(|problem| LibraryProblem{chip: label.clone(), problem, warning: true}));
+    }
+    Ok(validated)
+}
+
+/// one alert's worth of problems from a source, long lists are cut short
+pub(crate) fn report(source: &str, problems: &[LibraryProblem]) -> String {
+    let mut text = String::from("Some chips from ") + source + " have problems, the ones that can't be used were skipped:";
+    for problem in problems.iter().take(MAX_REPORTED) {
+        text.push('\n');
+        text.push_str(&problem.chip);
+        text.push_str(": ");
+        text.push_str(&problem.problem);
+        if problem.warning {
+            text.push_str(" (it's used anyway)");
+        }
+    }
+    if problems.len() > MAX_REPORTED {
+        text.push_str("\n...and ");
+        text.push_str(&(problems.len() - MAX_REPORTED).to_string());
+        text.push_str(" more");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems_for(validated: &ValidatedLibrary, chip: &str) -> Vec<String> {
+        validated.problems.iter().filter(|problem| problem.chip == chip).map(|problem| problem.problem.clone()).collect()
+    }
+
+    #[test]
+    fn every_problem_is_found() {
+        let validated = validate(r#"[
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "2d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "A cannon"},
+            {"Name": "Blank", "Element": ["Null"], "Skills": [], "Damage": "2d", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "a few", "Description": ""},
+            {"Name": "Odd", "Element": ["Plasma"], "Skills": ["Tech"], "Damage": "--", "Class": "Legendary", "Type": "Projectile", "Range": "Far", "Hits": "1-3", "Description": ""},
+            {"Name": "Cannon", "Element": ["Null"], "Skills": ["Tech"], "Damage": "3d6", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1", "Description": "Another cannon"},
+            {"Element": ["Null"], "Skills": ["Tech"], "Damage": "--", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "--", "Description": ""},
+            "Sword"
+        ]"#).unwrap();
+
+        assert_eq!(validated.chips.len(), 1);
+        assert_eq!(validated.chips[0].damage, "2d6");
+        assert_eq!(problems_for(&validated, "Blank").len(), 3);
+        assert!(problems_for(&validated, "Blank")[0].starts_with("has no skills"));
+        assert_eq!(problems_for(&validated, "Odd"), vec!["Element: unknown variant `Plasma`", "Class: unknown variant `Legendary`"]);
+        assert_eq!(problems_for(&validated, "Cannon"), vec!["is listed more than once, only the first one that can be used is"]);
+        assert_eq!(problems_for(&validated, "Chip #5"), vec!["has no Name"]);
+        assert_eq!(problems_for(&validated, "Chip #6"), vec!["isn't a chip"]);
+        assert!(validate(r#"{"Name": "Cannon"}"#).is_err());
+    }
+
+    #[test]
+    fn unreadable_damage_is_only_a_warning() {
+        let validated = validate(r#"[
+            {"Name": "Sword", "Element": ["Sword"], "Skills": [], "Damage": "3d6", "Class": "Standard", "Type": "Melee", "Range": "Close", "Hits": "1", "Description": "Broken"},
+            {"Name": "Sword", "Element": ["Sword"], "Skills": ["Strength"], "Damage": "lots", "Class": "Standard", "Type": "Melee", "Range": "Close", "Hits": "some", "Description": "A sword"}
+        ]"#).unwrap();
+
+        // the broken copy doesn't count as the first, the second is used with its odd damage
+        assert_eq!(validated.chips.len(), 1);
+        assert_eq!(validated.chips[0].description, "A sword");
+        let warnings = validated.problems.iter().filter(|problem| problem.warning).count();
+        assert_eq!((validated.problems.len(), warnings), (3, 2));
+        assert!(report("Base", &validated.problems).contains("damage \"lots\" can't be read"));
+    }
+
+    #[test]
+    fn too_many_hits_skip_the_chip() {
+        let validated = validate(r#"[
+            {"Name": "Vulcan", "Element": ["Null"], "Skills": ["Tech"], "Damage": "1d4", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "1-1000000", "Description": ""},
+            {"Name": "Vulcan", "Element": ["Null"], "Skills": ["Tech"], "Damage": "1d4", "Class": "Standard", "Type": "Projectile", "Range": "Far", "Hits": "3-5", "Description": "Rapid fire"}
+        ]"#).unwrap();
+
+        assert_eq!(validated.chips.len(), 1);
+        assert_eq!(validated.chips[0].hits, "3-5");
+        assert_eq!(problems_for(&validated, "Vulcan"), vec!["has more than 100 hits"]);
+    }
+
+    #[test]
+    fn long_reports_are_cut_short() {
+        let problems = (0..25).map(|idx| LibraryProblem{chip: idx.to_string(), problem: String::from("has no skills"), warning: false}).collect::<Vec<_>>();
+        let text = report("Base", &problems);
+        assert_eq!(text.lines().count(), MAX_REPORTED + 2);
+        assert!(text.ends_with("...and 5 more"));
+    }
+}
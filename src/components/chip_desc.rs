@@ -1,8 +1,9 @@
 use yew::prelude::*;
 use yew::services::interval::{IntervalService, IntervalTask};
 
-use crate::chip_library::{ChipLibrary, BattleChip};
+use crate::chip_library::{ChipLibrary, BattleChip, DiceRoller, ChipRoll};
 use crate::agents::chip_desc::{ChipDescMsgBus, ChipDescMsg};
+use crate::util::alert;
 use std::rc::Rc;
 use std::time::Duration;
 
@@ -14,6 +15,9 @@ pub(crate) enum ChipDescComponentMsg {
     StopScroll,
     StartScroll,
     ClearDesc,
+    RollDice,
+    SetSkillRank(i64),
+    ClearRolls,
     DoNothing,
 }
 
@@ -23,6 +27,11 @@ pub(crate) struct ChipDescComponent {
     scroll_interval: Option<IntervalTask>,
     mouse_enter_event: Callback<MouseEvent>,
     mouse_leave_event: Callback<MouseEvent>,
+    roll_callback: Callback<MouseEvent>,
+    clear_rolls_callback: Callback<MouseEvent>,
+    skill_rank_change: Callback<ChangeData>,
+    roller: DiceRoller,
+    skill_rank: i64,
     link: ComponentLink<Self>,
     _producer: Box<dyn Bridge<ChipDescMsgBus>>,
 }
@@ -35,7 +44,7 @@ impl Component for ChipDescComponent {
         let callback = link.callback(|e| {
             match e {
                 ChipDescMsg::SetDesc(name) => {
-                    if ChipLibrary::get_instance().library.contains_key(&name) {
+                    if ChipLibrary::get_instance().get_chip(&name).is_some() {
                         ChipDescComponentMsg::SetDesc(name)
                     } else {
                         ChipDescComponentMsg::ShowUnknown(name)
@@ -49,12 +58,28 @@ impl Component for ChipDescComponent {
         let scroll_interval = IntervalService::spawn(Duration::from_millis(75), link.callback(scroll_interval));//unsafe{set_interval(75, scroll_interval).unchecked_unwrap()};
         let mouse_enter_event = link.callback(|_: MouseEvent| ChipDescComponentMsg::StopScroll);
         let mouse_leave_event = link.callback(|_: MouseEvent| ChipDescComponentMsg::StartScroll);
+        let roll_callback = link.callback(|_: MouseEvent| ChipDescComponentMsg::RollDice);
+        let clear_rolls_callback = link.callback(|_: MouseEvent| ChipDescComponentMsg::ClearRolls);
+        let skill_rank_change = link.callback(|e: ChangeData| {
+            if let ChangeData::Value(text) = e {
+                text.parse::<i64>().map_or(ChipDescComponentMsg::DoNothing, |val| ChipDescComponentMsg::SetSkillRank(val))
+            } else {
+                ChipDescComponentMsg::DoNothing
+            }
+        });
+        // seed the dice from the browser, only tests need repeatable rolls
+        let seed = (js_sys::Math::random() * u64::MAX as f64) as u64 ^ js_sys::Date::now() as u64;
         Self {
             chip_anim_ct: 0,
             curr_chip: None,
             scroll_interval: Some(scroll_interval),
             mouse_enter_event,
             mouse_leave_event,
+            roll_callback,
+            clear_rolls_callback,
+            skill_rank_change,
+            roller: DiceRoller::new(seed),
+            skill_rank: 1,
             link,
             _producer,
         }
@@ -83,6 +108,15 @@ impl Component for ChipDescComponent {
                 self.scroll_interval.take();
                 false
             }
+            ChipDescComponentMsg::RollDice => self.roll_dice(),
+            ChipDescComponentMsg::SetSkillRank(rank) => {
+                self.skill_rank = rank;
+                false
+            }
+            ChipDescComponentMsg::ClearRolls => {
+                self.roller.clear_history();
+                true
+            }
             ChipDescComponentMsg::DoNothing => false,
         }
     }
@@ -103,27 +137,87 @@ impl Component for ChipDescComponent {
 
 impl ChipDescComponent {
     fn set_chip(&mut self, name: &str) -> bool {
-        let new_chip = unsafe{ChipLibrary::get_instance().library.get(name).unchecked_unwrap()};
+        // shared and custom chips can go away between the chip being hovered and this update
+        let new_chip = match ChipLibrary::get_instance().get_chip(name) {
+            Some(chip) => chip,
+            None => Rc::new(BattleChip::unknown_chip(name)),
+        };
         match &self.curr_chip {
             Some(curr_chip) => {
-                if Rc::ptr_eq(new_chip, curr_chip) {
+                if Rc::ptr_eq(&new_chip, curr_chip) {
                     return false;
                 }
-                self.curr_chip = Some(Rc::clone(new_chip));
+                self.curr_chip = Some(new_chip);
                 self.chip_anim_ct += 1;
                 return true;
             }
             None => {
-                self.curr_chip = Some(Rc::clone(new_chip));
+                self.curr_chip = Some(new_chip);
                 self.chip_anim_ct += 1;
                 return true;
             }
         }
     }
 
+    fn roll_dice(&mut self) -> bool {
+        let chip = match &self.curr_chip {
+            Some(chip) => Rc::clone(chip),
+            None => return false,
+        };
+        match self.roller.roll_chip(&chip, self.skill_rank) {
+            Ok(roll) => {
+                ChipLibrary::get_instance().log_roll(roll);
+                true
+            }
+            Err(why) => {
+                let msg = String::from("Could not roll ") + &chip.name + ": " + &why.to_string();
+                unsafe{alert(&msg)};
+                false
+            }
+        }
+    }
+
     fn no_chip(&self) -> Html {
         html!{
-            <div class="right-panel nopadding chipDescBackgroundStd"/>
+            <div class="right-panel nopadding chipDescBackgroundStd">
+                <div class="chipDescText chipDescPadding">
+                    {self.roll_history()}
+                </div>
+            </div>
+        }
+    }
+
+    /// the roll button for chips that do damage
+    fn roll_box(&self, chip: &BattleChip) -> Html {
+        if !chip.has_damage() {
+            return html!{};
+        }
+        let skill_rank = self.skill_rank.to_string();
+        html!{
+            <div class="rollBox">
+                <button class="sideButtons ripple" onclick=self.roll_callback.clone()>
+                    <span class="Chip">{"Roll"}</span>
+                </button>
+                <span class="Chip noselect">{"Rank"}</span>
+                <input type="number" class="rollRankInput" min="0" value=skill_rank onchange=self.skill_rank_change.clone()/>
+            </div>
+        }
+    }
+
+    /// every roll this session, newest first
+    fn roll_history(&self) -> Html {
+        if self.roller.history().next().is_none() {
+            return html!{};
+        }
+        html!{
+            <>
+            <div class="rollHistory">
+                {self.roller.history().rev().map(roll_entry).collect::<Html>()}
+            </div>
+            <button class="sideButtons ripple" onclick=self.clear_rolls_callback.clone()>
+                <span class="Chip">{"Clear Rolls"}</span>
+            </button>
+            </>
         }
     }
 
@@ -151,9 +245,13 @@ impl ChipDescComponent {
             <div class=background onmouseover=enter_clone onmouseout=leave_clone>
                 <div class=outer_chip_class style="padding: 3px; font-size: 14px;">
                     {chip.gen_desc_top_row()}
+                    <div class="chipSource">{"From "}{chip.provenance()}</div>
+                    {damage_row(chip)}
                     <div class=inner_chip_class id="ScrollTextDiv">
                         {&chip.description}
                     </div>
+                    {self.roll_box(chip)}
+                    {self.roll_history()}
                 </div>
             </div>
         }
@@ -161,6 +259,49 @@ impl ChipDescComponent {
 }
 
 
+/// one line per hit, each die that was rolled and the result
+fn roll_entry(roll: &ChipRoll) -> Html {
+    let header = roll.chip.clone() + ": " + &roll.total.to_string();
+    let hits = roll.hits.iter().enumerate().map(|(idx, hit)| {
+        let dice = hit.dice.iter().map(|die| {
+            String::from("d") + &die.sides.to_string() + ":" + &die.value.to_string()
+        }).collect::<Vec<String>>().join(" ");
+        let text = String::from("Hit ") + &(idx + 1).to_string() + ": " + &dice + " = " + &hit.total.to_string();
+        html!{
+            <div>{text}</div>
+        }
+    }).collect::<Html>();
+    html!{
+        <div class="rollEntry">
+            <div style="font-weight: bold">{header}</div>
+            {hits}
+        </div>
+    }
+}
+
+/// the damage range of the chip, or why its damage couldn't be read
+fn damage_row(chip: &BattleChip) -> Html {
+    if !chip.has_damage() {
+        return html!{};
+    }
+
+    match chip.damage_stats() {
+        Ok(stats) => {
+            let avg = (stats.avg * 10.0).round() / 10.0;
+            let text = String::from(&chip.damage) + ": " + &stats.min.to_string() + "-" + &stats.max.to_string() + ", avg " + &avg.to_string();
+            html!{
+                <div class="chip-row">{text}</div>
+            }
+        }
+        Err(why) => {
+            let text = String::from("Damage \"") + &chip.damage + "\" could not be read: " + &why.to_string();
+            html!{
+                <div class="chip-row" style="color: red">{text}</div>
+            }
+        }
+    }
+}
+
 fn scroll_interval(_: ()) -> ChipDescComponentMsg {
     let window = unsafe{web_sys::window().unchecked_unwrap()};
     let document = unsafe{window.document().unchecked_unwrap()};
@@ -93,6 +93,7 @@ impl Component for FolderChipComponent {
                 </div>
                 <div class="chip-col-3 nopadding">
                     {&self.props.chip.name}
+                    {self.props.chip.custom_tag()}
                 </div>
                 <div class="chip-col-1-5 nopadding">
                     {self.props.chip.skill().as_str()}
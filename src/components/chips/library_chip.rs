@@ -84,6 +84,7 @@ impl Component for LibraryChip {
                 onmouseover=self.props.on_mouse_enter.clone()>
                 <div class="chip-col-4 nopadding" style="white-space: nowrap">
                     {&self.props.chip.name}
+                    {self.props.chip.custom_tag()}
                 </div>
                 <div class="chip-col-1-5 nopadding">
                     {self.props.chip.skill().as_str()}
@@ -68,6 +68,7 @@ impl Component for PackChipComponent {
                 >
                 <div class="chip-col-3 nopadding" style="white-space: nowrap">
                     {&self.props.chip.name}
+                    {self.props.chip.custom_tag()}
                 </div>
                 <div class="chip-col-1-5 nopadding">
                     {self.props.chip.skill().as_str()}
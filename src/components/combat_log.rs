@@ -0,0 +1,132 @@
+use unchecked_unwrap::UncheckedUnwrap;
+use yew::{prelude::*, agent::{Dispatcher, Dispatched}};
+use yewtil::function_component;
+use crate::{
+    chip_library::{ChipLibrary, LogEntry, LogEvent},
+    agents::chip_desc::{ChipDescMsg, ChipDescMsgBus},
+    util::format_time,
+};
+
+#[derive(Properties, Clone)]
+pub(crate) struct CombatLogProps {
+    pub active: bool,
+}
+
+pub(crate) enum CombatLogMsg {
+    ClearLog,
+}
+
+/// Shows everything used this session, newest first
+pub(crate) struct CombatLogComponent {
+    props: CombatLogProps,
+    set_desc_bus: Dispatcher<ChipDescMsgBus>,
+    clear_log_callback: Callback<MouseEvent>,
+}
+
+impl Component for CombatLogComponent {
+    type Message = CombatLogMsg;
+    type Properties = CombatLogProps;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let clear_log_callback = link.callback(|_: MouseEvent| CombatLogMsg::ClearLog);
+        Self {
+            props,
+            set_desc_bus: ChipDescMsgBus::dispatcher(),
+            clear_log_callback,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            CombatLogMsg::ClearLog => {
+                unsafe{ChipLibrary::get_instance().combat_log.try_borrow_mut().unchecked_unwrap()}.clear();
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        // the log only changes while on other tabs, so re-render whenever switching to it
+        if props.active == true && self.props.active == false {
+            self.props = props;
+            self.set_desc_bus.send(ChipDescMsg::ClearDesc);
+            true
+        } else if props.active != self.props.active {
+            self.props = props;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn view(&self) -> Html {
+        let (col1_display, col2_display, log_container_class) = if self.props.active {
+            ("left-panel nopadding", "middle-panel nopadding", "container-fluid Folder activeFolder")
+        } else {
+            ("inactiveTab", "inactiveTab", "container-fluid Folder")
+        };
+
+        html!{
+            <>
+            <div class=col1_display>
+                <div class="centercontent">
+                    <button class="sideButtons ripple" onclick=self.clear_log_callback.clone()>
+                        <span class="Chip">{"Clear Log"}</span>
+                    </button>
+                </div>
+            </div>
+            <div class=col2_display>
+                <div class=log_container_class>
+                    <LogTopRow />
+                    {self.build_log()}
+                </div>
+            </div>
+            </>
+        }
+    }
+}
+
+impl CombatLogComponent {
+    fn build_log(&self) -> Html {
+        let log = unsafe{ChipLibrary::get_instance().combat_log.try_borrow().unchecked_unwrap()};
+        if log.entries().is_empty() {
+            return html!{
+                <span class="noselect Chip">
+                {"Nothing has happened yet!"}
+                </span>
+            }
+        }
+        log.entries().iter().rev().map(log_entry).collect::<Html>()
+    }
+}
+
+fn log_entry(entry: &LogEntry) -> Html {
+    let class = match entry.event {
+        LogEvent::FolderChipUsed{..} | LogEvent::PackCopyUsed{..} => "chip-row UsedChip noselect",
+        _ => "chip-row Chip noselect",
+    };
+    html!{
+        <div class=class>
+            <div class="chip-col-2 nopadding">
+                {format_time(entry.time)}
+            </div>
+            <div class="chip-col-5 nopadding" style="text-align: left">
+                {entry.event.describe()}
+            </div>
+        </div>
+    }
+}
+
+#[function_component(LogTopRow)]
+pub(crate) fn log_top_row() -> Html {
+    html! {
+        <div class="chip-top-row">
+            <div class="chip-col-2 Chip nopadding">
+                {"TIME"}
+            </div>
+            <div class="chip-col-5 Chip nopadding">
+                {"EVENT"}
+            </div>
+        </div>
+    }
+}
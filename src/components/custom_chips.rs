@@ -0,0 +1,251 @@
+use yew::prelude::*;
+use yew::html::InputData;
+
+use crate::chip_library::{BattleChip, ChipLibrary, Facet, FacetGroup};
+use crate::util::{alert, confirm};
+
+#[derive(Properties, Clone, PartialEq)]
+pub(crate) struct CustomChipEditorProps {
+    /// a custom chip was saved or deleted
+    pub changed: Callback<String>,
+    pub closed: Callback<MouseEvent>,
+}
+
+pub(crate) enum CustomChipMsg {
+    SetName(String),
+    SetDamage(String),
+    SetHits(String),
+    SetDescription(String),
+    Pick(Facet),
+    Edit(String),
+    New,
+    Save,
+    Delete(String),
+}
+
+/// Lists the active profile's custom chips and edits one at a time
+pub(crate) struct CustomChipEditor {
+    props: CustomChipEditorProps,
+    link: ComponentLink<Self>,
+    chip: BattleChip,
+    /// the name of the chip being edited, None for a new one
+    editing: Option<String>,
+}
+
+impl Component for CustomChipEditor {
+    type Properties = CustomChipEditorProps;
+    type Message = CustomChipMsg;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            chip: BattleChip::blank_custom(),
+            editing: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            CustomChipMsg::SetName(name) => {
+                self.chip.name = name;
+                false
+            }
+            CustomChipMsg::SetDamage(damage) => {
+                self.chip.damage = damage;
+                false
+            }
+            CustomChipMsg::SetHits(hits) => {
+                self.chip.hits = hits;
+                false
+            }
+            CustomChipMsg::SetDescription(description) => {
+                self.chip.description = description;
+                false
+            }
+            CustomChipMsg::Pick(facet) => {
+                self.pick(facet);
+                true
+            }
+            CustomChipMsg::Edit(name) => {
+                let chip = ChipLibrary::get_instance().custom_chips().into_iter().find(|chip| chip.name == name);
+                if let Some(chip) = chip {
+                    self.chip = chip;
+                    self.editing = Some(name);
+                }
+                true
+            }
+            CustomChipMsg::New => {
+                self.chip = BattleChip::blank_custom();
+                self.editing = None;
+                true
+            }
+            CustomChipMsg::Save => self.save(),
+            CustomChipMsg::Delete(name) => self.delete(name),
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+        }
+        false
+    }
+
+    fn view(&self) -> Html {
+        let save_callback = self.link.callback(|_: MouseEvent| CustomChipMsg::Save);
+        let title = match &self.editing {
+            Some(name) => String::from("Editing ") + name,
+            None => String::from("New Custom Chip"),
+        };
+        html!{
+            <div class="yew-modal">
+                <div class="yew-modal-content">
+                    <div class="yew-modal-header">
+                        <h2>{"Custom Chips"}</h2>
+                    </div>
+                    <div class="yew-modal-body">
+                        {self.chip_list()}
+                        <h3>{title}</h3>
+                        {self.chip_form()}
+                    </div>
+                    <div class="yew-modal-footer">
+                        <span style="padding-left: 5px">
+                            <button class="ok-button" onclick=save_callback>{"Save Chip"}</button>
+                        </span>
+                        <span style="float: right">
+                            <button class="inactiveNavTab" onclick=self.props.closed.clone()>{"Close"}</button>
+                        </span>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+}
+
+impl CustomChipEditor {
+
+    /// elements and skills can have more than one picked, everything else just one
+    fn pick(&mut self, facet: Facet) {
+        match facet {
+            Facet::Element(element) => {
+                match self.chip.element.iter().position(|picked| *picked == element) {
+                    Some(idx) => {self.chip.element.remove(idx);}
+                    None => self.chip.element.push(element),
+                }
+            }
+            Facet::Skill(skill) => {
+                match self.chip.skills.iter().position(|picked| *picked == skill) {
+                    Some(idx) => {self.chip.skills.remove(idx);}
+                    None => self.chip.skills.push(skill),
+                }
+            }
+            Facet::Class(class) => self.chip.class = class,
+            Facet::Kind(kind) => self.chip.kind = kind,
+            Facet::Range(range) => self.chip.range = range,
+        }
+    }
+
+    fn is_picked(&self, facet: Facet) -> bool {
+        match facet {
+            Facet::Element(element) => self.chip.element.contains(&element),
+            Facet::Skill(skill) => self.chip.skills.contains(&skill),
+            Facet::Class(class) => self.chip.class.is(class),
+            Facet::Kind(kind) => self.chip.kind == kind,
+            Facet::Range(range) => self.chip.range == range,
+        }
+    }
+
+    fn save(&mut self) -> bool {
+        let name = self.chip.name.trim().to_owned();
+        let res = ChipLibrary::get_instance().save_custom_chip(self.chip.clone(), self.editing.as_deref());
+        if let Err(why) = res {
+            unsafe{alert(why)};
+            return false;
+        }
+        self.chip = BattleChip::blank_custom();
+        self.editing = None;
+        self.props.changed.emit(String::from("Saved ") + &name);
+        true
+    }
+
+    fn delete(&mut self, name: String) -> bool {
+        let question = String::from("Delete ") + &name + " and every copy of it in your pack and folder?";
+        if !confirm(&question) {
+            return false;
+        }
+        if let Err(why) = ChipLibrary::get_instance().delete_custom_chip(&name) {
+            unsafe{alert(why)};
+            return false;
+        }
+        if self.editing.as_deref() == Some(name.as_str()) {
+            self.chip = BattleChip::blank_custom();
+            self.editing = None;
+        }
+        self.props.changed.emit(String::from("Deleted ") + &name);
+        true
+    }
+
+    fn chip_list(&self) -> Html {
+        let new_callback = self.link.callback(|_: MouseEvent| CustomChipMsg::New);
+        let rows = ChipLibrary::get_instance().custom_chips().into_iter().map(|chip| {
+            let edit_name = chip.name.clone();
+            let delete_name = chip.name.clone();
+            let edit_callback = self.link.callback(move |_: MouseEvent| CustomChipMsg::Edit(edit_name.clone()));
+            let delete_callback = self.link.callback(move |_: MouseEvent| CustomChipMsg::Delete(delete_name.clone()));
+            html!{
+                <div class="profileRow">
+                    <span class="profileName">{&chip.name}</span>
+                    <button class="inactiveNavTab" onclick=edit_callback>{"Edit"}</button>
+                    <button class="inactiveNavTab" onclick=delete_callback>{"Delete"}</button>
+                </div>
+            }
+        }).collect::<Html>();
+        html!{
+            <>
+            {rows}
+            <button class="inactiveNavTab" onclick=new_callback>{"New Chip"}</button>
+            </>
+        }
+    }
+
+    fn chip_form(&self) -> Html {
+        let pickers = FacetGroup::ALL.iter().map(|group| {
+            let buttons = group.facets().into_iter().map(|facet| {
+                let class = if self.is_picked(facet) {"facet facetSelected"} else {"facet"};
+                let callback = self.link.callback(move |_: MouseEvent| CustomChipMsg::Pick(facet));
+                html!{
+                    <button class=class onclick=callback>{facet.label()}</button>
+                }
+            }).collect::<Html>();
+            html!{
+                <div class="customChipField">
+                    <span class="Chip noselect">{group.name()}</span>
+                    {buttons}
+                </div>
+            }
+        }).collect::<Html>();
+
+        html!{
+            <div class="customChipForm">
+                <div class="customChipField">
+                    <span class="Chip noselect">{"Name"}</span>
+                    <input type="text" value=self.chip.name.clone() oninput=self.link.callback(|e: InputData| CustomChipMsg::SetName(e.value))/>
+                </div>
+                <div class="customChipField">
+                    <span class="Chip noselect">{"Damage"}</span>
+                    <input type="text" placeholder="2d6, or -- for none" value=self.chip.damage.clone() oninput=self.link.callback(|e: InputData| CustomChipMsg::SetDamage(e.value))/>
+                </div>
+                <div class="customChipField">
+                    <span class="Chip noselect">{"Hits"}</span>
+                    <input type="text" value=self.chip.hits.clone() oninput=self.link.callback(|e: InputData| CustomChipMsg::SetHits(e.value))/>
+                </div>
+                {pickers}
+                <div class="customChipField">
+                    <span class="Chip noselect">{"Description"}</span>
+                    <textarea class="pasteText" rows="5" value=self.chip.description.clone() oninput=self.link.callback(|e: InputData| CustomChipMsg::SetDescription(e.value))/>
+                </div>
+            </div>
+        }
+    }
+}
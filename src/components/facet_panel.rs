@@ -0,0 +1,81 @@
+use yew::prelude::*;
+
+use crate::chip_library::{BattleChip, Facet, FacetCount, FacetFilter, FacetGroup};
+
+/// the filter panel for a list of chips, counts are worked out from the chips passed in
+pub(crate) fn facet_panel(filter: &FacetFilter, chips: &[&BattleChip], toggled: &Callback<Facet>, cleared: &Callback<MouseEvent>) -> Html {
+    html!{
+        <FacetPanel counts={filter.counts(chips)} any_selected={!filter.is_empty()} toggled={toggled.clone()} cleared={cleared.clone()}/>
+    }
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub(crate) struct FacetPanelProps {
+    pub counts: Vec<FacetCount>,
+    pub any_selected: bool,
+    pub toggled: Callback<Facet>,
+    pub cleared: Callback<MouseEvent>,
+}
+
+pub(crate) struct FacetPanel {
+    props: FacetPanelProps,
+}
+
+impl Component for FacetPanel {
+    type Properties = FacetPanelProps;
+    type Message = ();
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+        }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            return true;
+        }
+        false
+    }
+
+    fn view(&self) -> Html {
+        let groups = FacetGroup::ALL.iter().map(|group| {
+            let facets = self.props.counts.iter().filter(|count| count.facet.group() == *group).map(|count| {
+                let facet = count.facet;
+                let toggled = self.props.toggled.reform(move |_: MouseEvent| facet);
+                let class = if count.selected {
+                    "facet facetSelected"
+                } else if count.count == 0 {
+                    "facet facetEmpty"
+                } else {
+                    "facet"
+                };
+                let text = String::from(facet.label()) + " (" + &count.count.to_string() + ")";
+                html!{
+                    <button class=class onclick=toggled>{text}</button>
+                }
+            }).collect::<Html>();
+            // groups with something picked start open
+            let open = self.props.counts.iter().any(|count| count.selected && count.facet.group() == *group);
+            html!{
+                <details class="facetGroup" open=open>
+                    <summary class="Chip noselect">{group.name()}</summary>
+                    {facets}
+                </details>
+            }
+        }).collect::<Html>();
+
+        html!{
+            <div class="facetPanel">
+                <span unselectable="on" class="Chip noselect">{"Filter"}</span>
+                <button class="facet" onclick=self.props.cleared.clone() disabled=!self.props.any_selected>{"Clear"}</button>
+                {groups}
+            </div>
+        }
+    }
+}
@@ -3,11 +3,12 @@ use yew::{prelude::*, agent::{Dispatcher, Dispatched}};
 use yewtil::function_component;
 use crate::{
     components::{
-        ChipSortOptions,
         chips::FolderChipComponent as FolderChip,
-        sort_box::ChipSortBox
+        sort_box::{ChipSortBox, sort_header},
+        search_box::ChipSearch,
+        facet_panel::facet_panel,
     }, 
-    chip_library::ChipLibrary,
+    chip_library::{BattleChip, ChipLibrary, Facet, FacetFilter, SortKey, SortRow, SortSpec},
     agents::{
         global_msg::{
             GlobalMsgBus,
@@ -33,10 +34,16 @@ use std::sync::atomic::Ordering;
 pub(crate) struct FolderProps {
     pub active: bool,
     pub in_folder_group: bool,
+    /// bumped whenever the folder is changed from outside of this component
+    pub revision: u32,
 }
 
 pub(crate) enum FolderMsg {
-    ChangeSort(ChipSortOptions),
+    ChangeSort(SortKey),
+    AddSortKey(SortKey),
+    ChangeSearch(String),
+    ToggleFacet(Facet),
+    ClearFacets,
     ChangeUsed(usize),
     ReturnToPack(usize),
     SetHighlightedChip(usize),
@@ -45,17 +52,29 @@ pub(crate) enum FolderMsg {
     JoinFolerGroup,
     LeaveFolderGroup,
     ClearFolder,
+    SelectPreset(String),
+    LoadPreset,
+    SavePreset,
+    RenamePreset,
+    DuplicatePreset,
+    DeletePreset,
     DoNothing,
 }
 
 pub(crate) struct FolderComponent {
     props: FolderProps,
     _link: ComponentLink<Self>,
-    sort_by: ChipSortOptions,
+    sort: SortSpec,
     return_to_pack: Callback<MouseEvent>,
     change_used_callback: Callback<MouseEvent>,
     chip_mouseover: Callback<MouseEvent>,
     sort_change_callback: Callback<ChangeData>,
+    header_clicked: Callback<(SortKey, bool)>,
+    search: ChipSearch,
+    search_changed: Callback<InputData>,
+    facets: FacetFilter,
+    facet_toggled: Callback<Facet>,
+    facets_cleared: Callback<MouseEvent>,
     event_bus: Dispatcher<GlobalMsgBus>,
     set_desc_bus: Dispatcher<ChipDescMsgBus>,
     chip_limit_change: Callback<ChangeData>,
@@ -63,6 +82,15 @@ pub(crate) struct FolderComponent {
     leave_folder_group_callback: Callback<MouseEvent>,
     jack_out_callback: Callback<MouseEvent>,
     clear_folder_callback: Callback<MouseEvent>,
+    select_preset_callback: Callback<ChangeData>,
+    load_preset_callback: Callback<MouseEvent>,
+    save_preset_callback: Callback<MouseEvent>,
+    rename_preset_callback: Callback<MouseEvent>,
+    duplicate_preset_callback: Callback<MouseEvent>,
+    delete_preset_callback: Callback<MouseEvent>,
+    /// the preset picked in the dropdown, defaults to the active one
+    selected_preset: Option<String>,
+    preset_name_ref: NodeRef,
 }
 
 fn mouse_event_to_index(e: MouseEvent) -> Option<usize> {
@@ -114,11 +142,14 @@ impl Component for FolderComponent {
         let return_to_pack = link.callback(return_pack_callback);
         let sort_change_callback = link.callback(|e: ChangeData| {
             if let ChangeData::Select(val) = e {
-                FolderMsg::ChangeSort(ChipSortOptions::from(val.value().as_ref()))
+                SortKey::from_name(&val.value()).map_or(FolderMsg::DoNothing, FolderMsg::ChangeSort)
             } else {
                 FolderMsg::DoNothing
             }
         });
+        let header_clicked = link.callback(|(key, shift): (SortKey, bool)| {
+            if shift {FolderMsg::AddSortKey(key)} else {FolderMsg::ChangeSort(key)}
+        });
         let chip_limit_change = link.callback(|e: ChangeData| {
             if let ChangeData::Value(text) = e {
                 text.parse::<usize>().map_or(FolderMsg::DoNothing, |val| FolderMsg::ChangeChipLimit(val))
@@ -126,6 +157,9 @@ impl Component for FolderComponent {
                 FolderMsg::DoNothing
             }
         });
+        let search_changed = link.callback(|e: InputData| FolderMsg::ChangeSearch(e.value));
+        let facet_toggled = link.callback(FolderMsg::ToggleFacet);
+        let facets_cleared = link.callback(|_: MouseEvent| FolderMsg::ClearFacets);
         let chip_mouseover = link.callback(handle_mouseover_event);
         let set_desc_bus = ChipDescMsgBus::dispatcher();
         let event_bus = GlobalMsgBus::dispatcher();
@@ -133,14 +167,33 @@ impl Component for FolderComponent {
         let leave_folder_group_callback = link.callback(|_:MouseEvent| FolderMsg::LeaveFolderGroup);
         let jack_out_callback = link.callback(|_: MouseEvent| FolderMsg::JackOut);
         let clear_folder_callback = link.callback(|_: MouseEvent| FolderMsg::ClearFolder);
+        let select_preset_callback = link.callback(|e: ChangeData| {
+            if let ChangeData::Select(val) = e {
+                FolderMsg::SelectPreset(val.value())
+            } else {
+                FolderMsg::DoNothing
+            }
+        });
+        let load_preset_callback = link.callback(|_: MouseEvent| FolderMsg::LoadPreset);
+        let save_preset_callback = link.callback(|_: MouseEvent| FolderMsg::SavePreset);
+        let rename_preset_callback = link.callback(|_: MouseEvent| FolderMsg::RenamePreset);
+        let duplicate_preset_callback = link.callback(|_: MouseEvent| FolderMsg::DuplicatePreset);
+        let delete_preset_callback = link.callback(|_: MouseEvent| FolderMsg::DeletePreset);
+        let selected_preset = unsafe{ChipLibrary::get_instance().presets.try_borrow().unchecked_unwrap()}.active().map(String::from);
 
         Self {
             props,
             _link: link,
-            sort_by: ChipSortOptions::Name,
+            sort: SortSpec::default(),
             return_to_pack,
             change_used_callback,
             sort_change_callback,
+            header_clicked,
+            search: ChipSearch::default(),
+            search_changed,
+            facets: FacetFilter::default(),
+            facet_toggled,
+            facets_cleared,
             event_bus,
             set_desc_bus,
             chip_mouseover,
@@ -149,18 +202,36 @@ impl Component for FolderComponent {
             join_folder_group_callback,
             jack_out_callback,
             clear_folder_callback,
+            select_preset_callback,
+            load_preset_callback,
+            save_preset_callback,
+            rename_preset_callback,
+            duplicate_preset_callback,
+            delete_preset_callback,
+            selected_preset,
+            preset_name_ref: NodeRef::default(),
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         let updated = match msg {
-            FolderMsg::ChangeSort(sort_by) => {
-                if self.sort_by != sort_by {
-                    self.sort_by = sort_by;
-                    true
-                } else {
-                    false
-                }
+            FolderMsg::ChangeSearch(text) => self.search.set_text(text),
+            FolderMsg::ToggleFacet(facet) => {
+                self.facets.toggle(facet);
+                true
+            }
+            FolderMsg::ClearFacets => {
+                let had_facets = !self.facets.is_empty();
+                self.facets.clear();
+                had_facets
+            }
+            FolderMsg::ChangeSort(key) => {
+                self.sort.sort_by(key);
+                true
+            },
+            FolderMsg::AddSortKey(key) => {
+                self.sort.then_by(key);
+                true
             },
             FolderMsg::ClearFolder => {
                 let count = ChipLibrary::get_instance().clear_folder();
@@ -190,6 +261,49 @@ impl Component for FolderComponent {
                 true
             },
             FolderMsg::SetHighlightedChip(idx) => self.set_highlighted_chip(idx),
+            FolderMsg::SelectPreset(name) => {
+                self.selected_preset = if name.is_empty() {None} else {Some(name)};
+                false
+            }
+            FolderMsg::LoadPreset => self.load_preset(),
+            FolderMsg::SavePreset => {
+                let name = self.preset_name_input().or_else(|| self.selected_preset.clone()).unwrap_or_default();
+                let res = ChipLibrary::get_instance().save_preset(&name);
+                let msg = name.clone() + " preset saved";
+                self.preset_changed(res, Some(name), msg)
+            }
+            FolderMsg::RenamePreset => {
+                let (selected, name) = match (self.selected_preset.clone(), self.preset_name_input()) {
+                    (Some(selected), Some(name)) => (selected, name),
+                    _ => {
+                        unsafe{alert("Pick a preset and type its new name first")};
+                        return false;
+                    }
+                };
+                let res = ChipLibrary::get_instance().rename_preset(&selected, &name);
+                let msg = selected + " preset renamed to " + &name;
+                self.preset_changed(res, Some(name), msg)
+            }
+            FolderMsg::DuplicatePreset => {
+                let (selected, name) = match (self.selected_preset.clone(), self.preset_name_input()) {
+                    (Some(selected), Some(name)) => (selected, name),
+                    _ => {
+                        unsafe{alert("Pick a preset and type a name for the copy first")};
+                        return false;
+                    }
+                };
+                let res = ChipLibrary::get_instance().duplicate_preset(&selected, &name);
+                let msg = name.clone() + " preset created";
+                self.preset_changed(res, Some(name), msg)
+            }
+            FolderMsg::DeletePreset => {
+                let selected = match self.selected_preset.clone() {
+                    Some(selected) => selected,
+                    None => return false,
+                };
+                let res = ChipLibrary::get_instance().delete_preset(&selected);
+                self.preset_changed(res, None, selected + " preset deleted")
+            }
             FolderMsg::DoNothing => false,
             FolderMsg::JoinFolerGroup => {
                 self.event_bus.send(GlobalMsgReq::JoinGroup);
@@ -216,7 +330,7 @@ impl Component for FolderComponent {
             self.props = props;
             self.set_desc_bus.send(ChipDescMsg::ClearDesc);
             return true;
-        } else if props.in_folder_group != self.props.in_folder_group {
+        } else if props.in_folder_group != self.props.in_folder_group || props.revision != self.props.revision {
             self.props = props;
             return true;
         } else {
@@ -247,16 +361,20 @@ impl Component for FolderComponent {
                     onchange=self.chip_limit_change.clone()
                     style="height: calc(1.5em + .75rem + 2px); font-size: 1rem"
                 />
-                <ChipSortBox sort_by=self.sort_by include_owned=false sort_changed=self.sort_change_callback.clone()/>
+                <ChipSortBox keys=SortKey::FOLDER sort=self.sort.clone() sort_changed=self.sort_change_callback.clone()/>
+                {self.search.view(&self.search_changed)}
+                {self.build_facet_panel()}
                 <br/>
                 <br/>
                 {self.generate_buttons()}
                 <br/>
+                {self.presets_box()}
+                <br/>
                 {list_spectators()}
             </div>
             <div class=col2_display>
                 <div class=folder_containter_class>
-                    <FolderTopRow />
+                    <FolderTopRow sort=self.sort.clone() sort_clicked=Some(self.header_clicked.clone())/>
                     {self.build_folder()}
                 </div>
             </div>
@@ -269,8 +387,14 @@ impl Component for FolderComponent {
 
 impl FolderComponent {
 
+    fn build_facet_panel(&self) -> Html {
+        let folder = unsafe{ChipLibrary::get_instance().folder.try_borrow().unchecked_unwrap()};
+        let searched = folder.iter().filter(|chip| self.search.matches(&chip.chip)).map(|chip| &*chip.chip).collect::<Vec<&BattleChip>>();
+        facet_panel(&self.facets, &searched, &self.facet_toggled, &self.facets_cleared)
+    }
+
     fn build_folder(&self) -> Html {
-        let mut folder = unsafe{ChipLibrary::get_instance().folder.try_borrow_mut().unchecked_unwrap()};
+        let folder = unsafe{ChipLibrary::get_instance().folder.try_borrow().unchecked_unwrap()};
         if folder.len() == 0 {
             return html!{
                 <span class="noselect Chip">
@@ -278,49 +402,20 @@ impl FolderComponent {
                 </span>
             }
         }
-        match self.sort_by {
-            ChipSortOptions::Name => {
-                folder.sort_by(|a, b| {
-                    a.chip.class.cmp(&b.chip.class).then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::Element => {
-                folder.sort_by(|a, b| {
-                    a.chip.element.cmp(&b.chip.element).then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::MaxDamage => {
-                folder.sort_by(|a, b| {
-                    unsafe{a.chip.max_dmg().partial_cmp(&b.chip.max_dmg()).unchecked_unwrap()}.reverse().then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::AverageDamage => {
-                folder.sort_by(|a, b| {
-                    unsafe{a.chip.avg_dmg().partial_cmp(&b.chip.avg_dmg()).unchecked_unwrap()}.reverse().then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::Skill => {
-                folder.sort_by(|a, b| {
-                    a.chip.skill().cmp(&b.chip.skill()).then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::Range => {
-                folder.sort_by(|a, b| {
-                    a.chip.range.cmp(&b.chip.range).then_with(||a.chip.name.cmp(&b.chip.name))
-                });
+        // the folder itself is left in the order it's kept in, each chip is listed with its index in it
+        let mut matched = folder.iter().enumerate().filter(|(_, chip)| {
+            self.search.matches(&chip.chip) && self.facets.matches(&chip.chip)
+        }).collect::<Vec<_>>();
+        self.sort.sort(&mut matched, |(_, chip)| SortRow{used: chip.used as u32, relevance: self.search.relevance(&chip.chip), ..SortRow::new(&chip.chip)});
+        if matched.is_empty() {
+            return html!{
+                <span class="noselect Chip">
+                {"Nothing matched your search"}
+                </span>
             }
-            ChipSortOptions::Owned => {
-                #[cfg(not(debug_assertions))]
-                unsafe{core::hint::unreachable_unchecked()};
-                #[cfg(debug_assertions)]
-                unreachable!();
-            },
         }
 
-        
-        let folder_len = folder.len();
-
-        folder.iter().zip(0..folder_len).map(|(chip, index)|{
+        matched.into_iter().map(|(index, chip)|{
             let battlechip = Rc::clone(&chip.chip);
             html!{
                 <FolderChip 
@@ -361,6 +456,87 @@ impl FolderComponent {
         }
     }
 
+    fn presets_box(&self) -> Html {
+        let presets = unsafe{ChipLibrary::get_instance().presets.try_borrow().unchecked_unwrap()};
+        let selected = self.selected_preset.as_deref().unwrap_or("");
+        let options = presets.presets().iter().map(|preset| {
+            html!{
+                <option value=preset.name.clone() selected={preset.name == selected}>{&preset.name}</option>
+            }
+        }).collect::<Html>();
+
+        html!{
+            <div class="centercontent presetBox">
+                <span unselectable="on" class="Chip noselect">{"Folder Presets"}</span>
+                <select class="chip-sort-select" onchange=self.select_preset_callback.clone()>
+                    <option value="" selected={selected.is_empty()}>{"--"}</option>
+                    {options}
+                </select>
+                <input type="text" class="chip-search-input" placeholder="preset name" ref=self.preset_name_ref.clone()/>
+                <button class="sideButtons ripple" onclick=self.load_preset_callback.clone()>
+                    <span class="Chip">{"Load"}</span>
+                </button>
+                <button class="sideButtons ripple" onclick=self.save_preset_callback.clone()>
+                    <span class="Chip">{"Save"}</span>
+                </button>
+                <button class="sideButtons ripple" onclick=self.rename_preset_callback.clone()>
+                    <span class="Chip">{"Rename"}</span>
+                </button>
+                <button class="sideButtons ripple" onclick=self.duplicate_preset_callback.clone()>
+                    <span class="Chip">{"Duplicate"}</span>
+                </button>
+                <button class="sideButtons ripple" onclick=self.delete_preset_callback.clone()>
+                    <span class="Chip">{"Delete"}</span>
+                </button>
+            </div>
+        }
+    }
+
+    /// the trimmed text in the preset name box, None if it's empty
+    fn preset_name_input(&self) -> Option<String> {
+        let input = self.preset_name_ref.cast::<web_sys::HtmlInputElement>()?;
+        let name = input.value().trim().to_owned();
+        if name.is_empty() {
+            return None;
+        }
+        input.set_value("");
+        Some(name)
+    }
+
+    /// report the result of changing a preset, selecting the given preset if it worked
+    fn preset_changed(&mut self, res: Result<(), &'static str>, selected: Option<String>, msg: String) -> bool {
+        if let Err(why) = res {
+            unsafe{alert(why)};
+            return false;
+        }
+        self.selected_preset = selected;
+        self.event_bus.send(GlobalMsgReq::SetHeaderMsg(msg));
+        true
+    }
+
+    /// swap the folder for the selected preset
+    fn load_preset(&mut self) -> bool {
+        let name = match &self.selected_preset {
+            Some(name) => name.clone(),
+            None => return false,
+        };
+        let unsupplied = match ChipLibrary::get_instance().switch_preset(&name) {
+            Ok(unsupplied) => unsupplied,
+            Err(why) => {
+                unsafe{alert(why)};
+                return false;
+            }
+        };
+        if !unsupplied.is_empty() {
+            let mut msg = String::from("Your pack couldn't supply these chips:\n");
+            msg.push_str(&unsupplied.join("\n"));
+            unsafe{alert(&msg)};
+        }
+        self.event_bus.send(GlobalMsgReq::SetHeaderMsg(String::from("Switched to the ") + &name + " preset"));
+        self.set_desc_bus.send(ChipDescMsg::ClearDesc);
+        true
+    }
+
     fn set_highlighted_chip(&mut self, idx: usize) -> bool {
         let chip_library = ChipLibrary::get_instance();
         let folder = unsafe{chip_library.folder.try_borrow().unchecked_unwrap()};
@@ -391,28 +567,19 @@ impl FolderComponent {
 
 }
 
+// the group folders show the same header, just without sorting
 #[function_component(FolderTopRow)]
-pub(crate) fn folder_top_row() -> Html {
+pub(crate) fn folder_top_row(#[prop_or_default] sort: &SortSpec, #[prop_or_default] sort_clicked: &Option<Callback<(SortKey, bool)>>) -> Html {
     html! {
         <div class="chip-top-row">
             <div class="chip-col-1 Chip nopadding">
                 {"#"}
             </div>
-            <div class="chip-col-3 Chip nopadding" style="white-space: nowrap">
-                {"NAME"}
-            </div>
-            <div class="chip-col-1-5 Chip nopadding">
-                {"SKILL"}
-            </div>
-            <div class="chip-col-1-5 Chip nopadding">
-                {"DMG"}
-            </div>
-            <div class="chip-col-2 Chip nopadding">
-                {"ELEM"}
-            </div>
-            <div class="chip-col-1 Chip nopadding">
-                {"U"}
-            </div>
+            {sort_header("NAME", "chip-col-3 Chip nopadding nowrap", SortKey::Name, sort, sort_clicked)}
+            {sort_header("SKILL", "chip-col-1-5 Chip nopadding", SortKey::Skill, sort, sort_clicked)}
+            {sort_header("DMG", "chip-col-1-5 Chip nopadding", SortKey::MaxDamage, sort, sort_clicked)}
+            {sort_header("ELEM", "chip-col-2 Chip nopadding", SortKey::Element, sort, sort_clicked)}
+            {sort_header("U", "chip-col-1 Chip nopadding", SortKey::Used, sort, sort_clicked)}
         </div>
     }
-}
\ No newline at end of file
+}
@@ -21,6 +21,8 @@ pub(crate) struct GroupFolderProps {
 
 pub(crate) enum GroupFolderComponentMsg {
     GroupFoldersUpdated,
+    /// only re-rendered if it's this folder
+    PlayerFolderUpdated(String),
     LeftGroup,
     DoNothing,
     SetHighlightedChip(String),
@@ -44,6 +46,8 @@ impl Component for GroupFolderComponent {
                 GroupFldrAgentOutMsg::JoinedGroup => GroupFolderComponentMsg::DoNothing,
                 GroupFldrAgentOutMsg::LeftGroup => GroupFolderComponentMsg::LeftGroup,
                 GroupFldrAgentOutMsg::GroupUpdated => GroupFolderComponentMsg::GroupFoldersUpdated,
+                GroupFldrAgentOutMsg::PlayerUpdated(player) => GroupFolderComponentMsg::PlayerFolderUpdated(player),
+                GroupFldrAgentOutMsg::Reconnecting => GroupFolderComponentMsg::DoNothing,
             }
         });
         let _group_bridge = GroupFldrMsgBus::bridge(callback);
@@ -61,6 +65,7 @@ impl Component for GroupFolderComponent {
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             GroupFolderComponentMsg::GroupFoldersUpdated => true,
+            GroupFolderComponentMsg::PlayerFolderUpdated(player) => player == self.props.player_name,
             GroupFolderComponentMsg::LeftGroup => true,
             GroupFolderComponentMsg::DoNothing => false,
             GroupFolderComponentMsg::SetHighlightedChip(name) => {
@@ -138,8 +143,8 @@ impl GroupFolderComponent {
 
         let folder_len = folder.len();
         folder.iter().zip(0..folder_len).map(|(chip, index)|{
-            let battlechip = match library.library.get(&chip.name) {
-                Some(chip) => chip.clone(),
+            let battlechip = match library.get_chip(&chip.name) {
+                Some(chip) => chip,
                 None => Rc::new(BattleChip::unknown_chip(&chip.name)),
             };
             html!{
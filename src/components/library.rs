@@ -3,32 +3,23 @@ use yew::agent::{Dispatcher, Dispatched};
 use yew::html::{ChangeData, InputData};
 use yewtil::function_component;
 use std::rc::Rc;
-use unchecked_unwrap::UncheckedUnwrap;
 use wasm_bindgen::JsCast;
 
-use crate::components::{ChipSortOptions, chips::LibraryChip, sort_box::ChipSortBox};
-use crate::chip_library::{BattleChip, ChipLibrary};
+use crate::components::{chips::LibraryChip, sort_box::{ChipSortBox, sort_header}, search_box::ChipSearch, facet_panel::facet_panel};
+use crate::chip_library::{BattleChip, ChipLibrary, Facet, FacetFilter, SortKey, SortRow, SortSpec};
 use crate::agents::chip_desc::{ChipDescMsg, ChipDescMsgBus};
 use crate::util::list_spectators;
 
 
 
 #[function_component(LibraryTopRow)]
-pub(crate) fn library_top_row() -> Html {
+pub(crate) fn library_top_row(sort: &SortSpec, sort_clicked: &Option<Callback<(SortKey, bool)>>) -> Html {
     html! {
         <div class="chip-top-row noselect">
-            <div class="chip-col-4 Chip nopadding" style="white-space: nowrap">
-                {"NAME"}
-            </div>
-            <div class="chip-col-1-5 Chip nopadding">
-                {"SKILL"}
-            </div>
-            <div class="chip-col-1-5 Chip nopadding">
-                {"DMG"}
-            </div>
-            <div class="chip-col-2 Chip nopadding">
-                {"ELEM"}
-            </div>
+            {sort_header("NAME", "chip-col-4 Chip nopadding nowrap", SortKey::Name, sort, sort_clicked)}
+            {sort_header("SKILL", "chip-col-1-5 Chip nopadding", SortKey::Skill, sort, sort_clicked)}
+            {sort_header("DMG", "chip-col-1-5 Chip nopadding", SortKey::MaxDamage, sort, sort_clicked)}
+            {sort_header("ELEM", "chip-col-2 Chip nopadding", SortKey::Element, sort, sort_clicked)}
         </div>
     }
 }
@@ -36,11 +27,16 @@ pub(crate) fn library_top_row() -> Html {
 #[derive(Properties, Clone, PartialEq)]
 pub(crate) struct LibraryProps {
     pub active: bool,
+    /// bumped whenever custom chips are added, changed or removed
+    pub revision: u32,
 }
 
 pub(crate) enum LibraryMessage {
-    ChangeSort(ChipSortOptions),
+    ChangeSort(SortKey),
+    AddSortKey(SortKey),
     ChangeFilter(String),
+    ToggleFacet(Facet),
+    ClearFacets,
     SetHighlightedChip(String),
     DoNothing,
 }
@@ -62,9 +58,13 @@ fn handle_mouseover_event(e: MouseEvent) -> LibraryMessage {
 pub(crate) struct LibraryComponent{
     props: LibraryProps,
     _link: ComponentLink<Self>,
-    sort_by: ChipSortOptions,
-    filter_by: String,
+    sort: SortSpec,
+    search: ChipSearch,
+    facets: FacetFilter,
+    facet_toggled: Callback<Facet>,
+    facets_cleared: Callback<MouseEvent>,
     sort_changed: Callback<ChangeData>,
+    header_clicked: Callback<(SortKey, bool)>,
     text_changed: Callback<InputData>,
     chip_mouseover: Callback<MouseEvent>,
     set_desc_bus: Dispatcher<ChipDescMsgBus>,
@@ -78,23 +78,32 @@ impl Component for LibraryComponent {
         let sort_changed = _link.callback(|e: ChangeData| {
             //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str("sort change emitted"));
             if let ChangeData::Select(val) = e {
-                LibraryMessage::ChangeSort(ChipSortOptions::from(val.value().as_ref()))
+                SortKey::from_name(&val.value()).map_or(LibraryMessage::DoNothing, LibraryMessage::ChangeSort)
             } else {
                 LibraryMessage::DoNothing
             }
         });
+        let header_clicked = _link.callback(|(key, shift): (SortKey, bool)| {
+            if shift {LibraryMessage::AddSortKey(key)} else {LibraryMessage::ChangeSort(key)}
+        });
         let text_changed = _link.callback(|e: InputData| {
             //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str("text change emitted"));
             LibraryMessage::ChangeFilter(e.value)
         });
+        let facet_toggled = _link.callback(LibraryMessage::ToggleFacet);
+        let facets_cleared = _link.callback(|_: MouseEvent| LibraryMessage::ClearFacets);
         let chip_mouseover = _link.callback(handle_mouseover_event);
         let set_desc_bus = ChipDescMsgBus::dispatcher();
         Self {
             props,
             _link,
-            sort_by: ChipSortOptions::Name,
-            filter_by: String::default(),
+            sort: SortSpec::default(),
+            search: ChipSearch::default(),
+            facets: FacetFilter::default(),
+            facet_toggled,
+            facets_cleared,
             sort_changed,
+            header_clicked,
             text_changed,
             chip_mouseover,
             set_desc_bus,
@@ -103,19 +112,25 @@ impl Component for LibraryComponent {
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            LibraryMessage::ChangeSort(opt) => {
-                
-                if self.sort_by == opt {
-                    return false;
-                }
-                self.sort_by = opt;
+            LibraryMessage::ChangeSort(key) => {
+                self.sort.sort_by(key);
+                true
+            }
+            LibraryMessage::AddSortKey(key) => {
+                self.sort.then_by(key);
                 true
             }
             LibraryMessage::DoNothing => false,
-            LibraryMessage::ChangeFilter(val) => {
-                self.filter_by = val.trim().to_ascii_lowercase();
+            LibraryMessage::ChangeFilter(val) => self.search.set_text(val),
+            LibraryMessage::ToggleFacet(facet) => {
+                self.facets.toggle(facet);
                 true
             }
+            LibraryMessage::ClearFacets => {
+                let had_facets = !self.facets.is_empty();
+                self.facets.clear();
+                had_facets
+            }
             LibraryMessage::SetHighlightedChip(name) => {
                 self.set_desc_bus.send(ChipDescMsg::SetDesc(name));
                 false
@@ -131,6 +146,9 @@ impl Component for LibraryComponent {
             self.props = props;
             self.set_desc_bus.send(ChipDescMsg::ClearDesc);
             return true;
+        } else if props.revision != self.props.revision {
+            self.props = props;
+            return true;
         } else {
             return false;
         }
@@ -147,14 +165,15 @@ impl Component for LibraryComponent {
         html! {
             <>
             <div class=col1_display>
-                <ChipSortBox include_owned={false} sort_by={self.sort_by} sort_changed={self.sort_changed.clone()}/>
-                {self.build_search_box()}
+                <ChipSortBox keys={SortKey::LIBRARY} sort={self.sort.clone()} sort_changed={self.sort_changed.clone()}/>
+                {self.search.view(&self.text_changed)}
+                {self.build_facet_panel()}
                 <br/>
                 {list_spectators()}
             </div>
             <div class=col2_display>
                 <div class=library_containter_class>
-                    <LibraryTopRow/>
+                    <LibraryTopRow sort={self.sort.clone()} sort_clicked={Some(self.header_clicked.clone())}/>
                     {self.build_library_chips()}
                  </div>
             </div>
@@ -166,16 +185,12 @@ impl Component for LibraryComponent {
 
 impl LibraryComponent {
 
-    fn build_search_box(&self) -> Html {
-        let text_changed = self.text_changed.clone();
-
-        html! {
-            <>
-            <br/>
-            <span unselectable="on" class="Chip">{"Search"}</span>
-            <input type="text" class="chip-search-input" value={self.filter_by.clone()} oninput=text_changed/>
-            </>
-        }
+    fn build_facet_panel(&self) -> Html {
+        let chips = ChipLibrary::get_instance().chips();
+        let searched = chips.iter().filter(|chip| {
+            self.search.matches(chip)
+        }).map(|chip| &**chip).collect::<Vec<&BattleChip>>();
+        facet_panel(&self.facets, &searched, &self.facet_toggled, &self.facets_cleared)
     }
 
     fn build_library_chips(&self) -> Html {
@@ -195,48 +210,16 @@ impl LibraryComponent {
         }).collect::<Html>()
     }
 
-    fn fetch_chips(&self) -> Vec<&Rc<BattleChip>> {
-        let mut chip_lib = if self.filter_by.is_empty() {
-            ChipLibrary::get_instance().library.values().collect::<Vec<&Rc<BattleChip>>>()
-        } else {
-            ChipLibrary::get_instance().library.values().filter(|chip| {
-                chip.name.to_ascii_lowercase().starts_with(&self.filter_by)
-            }).collect::<Vec<&Rc<BattleChip>>>()
-        };
-
-        match self.sort_by {
-            ChipSortOptions::Name => {
-                chip_lib.sort_unstable_by(|a, b| {
-                    a.class.cmp(&b.class).then_with(||a.name.cmp(&b.name))
-                });
-            }
-            ChipSortOptions::Element => {
-                chip_lib.sort_unstable_by(|a, b| {
-                    a.element.cmp(&b.element).then_with(||a.name.cmp(&b.name))
-                });
-            }
-            ChipSortOptions::MaxDamage => {
-                chip_lib.sort_unstable_by(|a, b| {
-                    unsafe{a.max_dmg().partial_cmp(&b.max_dmg()).unchecked_unwrap()}.reverse().then_with(||a.name.cmp(&b.name))
-                });
-            }
-            ChipSortOptions::AverageDamage => {
-                chip_lib.sort_unstable_by(|a, b| {
-                    unsafe{a.avg_dmg().partial_cmp(&b.avg_dmg()).unchecked_unwrap()}.reverse().then_with(||a.name.cmp(&b.name))
-                });
-            }
-            ChipSortOptions::Skill => {
-                chip_lib.sort_unstable_by(|a, b| {
-                    a.skill().cmp(&b.skill()).then_with(||a.name.cmp(&b.name))
-                });
-            }
-            ChipSortOptions::Range => {
-                chip_lib.sort_unstable_by(|a, b| {
-                    a.range.cmp(&b.range).then_with(||a.name.cmp(&b.name))
-                });
-            }
-            ChipSortOptions::Owned => unsafe{core::hint::unreachable_unchecked()},
+    fn fetch_chips(&self) -> Vec<Rc<BattleChip>> {
+        let mut chip_lib = ChipLibrary::get_instance().chips();
+        if !self.search.is_empty() || !self.facets.is_empty() {
+            chip_lib.retain(|chip| self.search.matches(chip) && self.facets.matches(chip));
         }
+
+        let in_folder = ChipLibrary::get_instance().folder_counts();
+        self.sort.sort(&mut chip_lib, |chip| {
+            SortRow{in_folder: in_folder.get(&chip.name).copied().unwrap_or(0), relevance: self.search.relevance(chip), ..SortRow::new(chip)}
+        });
         chip_lib
     }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
-use crate::chip_library::{ChipLibrary, PackChip};
-use crate::components::{ChipSortOptions, chips::PackChipComponent, sort_box::ChipSortBox};
+use crate::chip_library::{BattleChip, ChipLibrary, Facet, FacetFilter, PackChip, SortKey, SortRow, SortSpec};
+use crate::components::{chips::PackChipComponent, sort_box::{ChipSortBox, sort_header}, search_box::ChipSearch, facet_panel::facet_panel};
 use yew::prelude::*;
 use yew::agent::{Dispatcher, Dispatched};
 use yewtil::function_component;
@@ -11,7 +11,7 @@ use crate::agents::{
         GroupFldrAgentReq,
     }
 };
-use crate::util::{alert, list_spectators};
+use crate::util::{alert, list_spectators, save_json, save_txt};
 use yew::events::MouseEvent;
 use wasm_bindgen::{JsCast, JsValue, closure::Closure};
 
@@ -19,27 +19,15 @@ use std::collections::HashMap;
 use unchecked_unwrap::UncheckedUnwrap;
 
 #[function_component(PackTopRow)]
-pub(crate) fn pack_top_row() -> Html {
+pub(crate) fn pack_top_row(sort: &SortSpec, sort_clicked: &Option<Callback<(SortKey, bool)>>) -> Html {
     html! {
         <div class="chip-top-row noselect">
-            <div class="chip-col-3 Chip nopadding" style="white-space: nowrap">
-                {"NAME"}
-            </div>
-            <div class="chip-col-1-5 Chip nopadding">
-                {"SKILL"}
-            </div>
-            <div class="chip-col-1-5 Chip nopadding">
-                {"DMG"}
-            </div>
-            <div class="chip-col-2 Chip nopadding">
-                {"ELEM"}
-            </div>
-            <div class="chip-col-1 Chip nopadding">
-                {"O"}
-            </div>
-            <div class="chip-col-1 Chip nopadding">
-                {"U"}
-            </div>
+            {sort_header("NAME", "chip-col-3 Chip nopadding nowrap", SortKey::Name, sort, sort_clicked)}
+            {sort_header("SKILL", "chip-col-1-5 Chip nopadding", SortKey::Skill, sort, sort_clicked)}
+            {sort_header("DMG", "chip-col-1-5 Chip nopadding", SortKey::MaxDamage, sort, sort_clicked)}
+            {sort_header("ELEM", "chip-col-2 Chip nopadding", SortKey::Element, sort, sort_clicked)}
+            {sort_header("O", "chip-col-1 Chip nopadding", SortKey::Owned, sort, sort_clicked)}
+            {sort_header("U", "chip-col-1 Chip nopadding", SortKey::Used, sort, sort_clicked)}
         </div>
     }
 }
@@ -47,10 +35,16 @@ pub(crate) fn pack_top_row() -> Html {
 #[derive(Properties, Clone)]
 pub(crate) struct PackProps {
     pub active: bool,
+    /// bumped whenever the pack is changed from outside of this component
+    pub revision: u32,
 }
 
 pub(crate) enum PackMsg {
-    ChangeSort(ChipSortOptions),
+    ChangeSort(SortKey),
+    AddSortKey(SortKey),
+    ChangeSearch(String),
+    ToggleFacet(Facet),
+    ClearFacets,
     MoveToFolder(String),
     SetHighlightedChip(String),
     RemoveFromPack(String),
@@ -62,15 +56,26 @@ pub(crate) enum PackMsg {
     ExportTxt,
     EraseData,
     ImportJson,
+    ImportTxt,
+    ManageProfiles,
+    ManageCustomChips,
+    ManageSources,
+    ManageSettings,
     DoNothing,
 }
 
 pub(crate) struct PackComponent {
     props: PackProps,
-    sort_by: ChipSortOptions,
+    sort: SortSpec,
     _link: ComponentLink<Self>,
     event_bus: Dispatcher<GlobalMsgBus>,
     sort_changed: Callback<ChangeData>,
+    header_clicked: Callback<(SortKey, bool)>,
+    search: ChipSearch,
+    search_changed: Callback<InputData>,
+    facets: FacetFilter,
+    facet_toggled: Callback<Facet>,
+    facets_cleared: Callback<MouseEvent>,
     move_to_folder_callback: Callback<MouseEvent>,
     set_desc_bus: Dispatcher<ChipDescMsgBus>,
     chip_mouseover: Callback<MouseEvent>,
@@ -78,6 +83,11 @@ pub(crate) struct PackComponent {
     export_json_callback: Callback<MouseEvent>,
     export_txt_callback: Callback<MouseEvent>,
     erase_data_callback: Callback<MouseEvent>,
+    import_txt_callback: Callback<MouseEvent>,
+    manage_profiles_callback: Callback<MouseEvent>,
+    custom_chips_callback: Callback<MouseEvent>,
+    sources_callback: Callback<MouseEvent>,
+    settings_callback: Callback<MouseEvent>,
     import_data_callback: Callback<MouseEvent>,
     open_context_menu_callback: Callback<MouseEvent>,
     context_menu: Option<(String, String, String)>,
@@ -147,15 +157,26 @@ impl Component for PackComponent {
         let sort_changed = link.callback(|e: ChangeData| {
             //web_sys::console::log_1(&wasm_bindgen::JsValue::from_str("sort change emitted"));
             if let ChangeData::Select(val) = e {
-                PackMsg::ChangeSort(ChipSortOptions::from(val.value().as_ref()))
+                SortKey::from_name(&val.value()).map_or(PackMsg::DoNothing, PackMsg::ChangeSort)
             } else {
                 PackMsg::DoNothing
             }
         });
+        let header_clicked = link.callback(|(key, shift): (SortKey, bool)| {
+            if shift {PackMsg::AddSortKey(key)} else {PackMsg::ChangeSort(key)}
+        });
+        let search_changed = link.callback(|e: InputData| PackMsg::ChangeSearch(e.value));
+        let facet_toggled = link.callback(PackMsg::ToggleFacet);
+        let facets_cleared = link.callback(|_: MouseEvent| PackMsg::ClearFacets);
         let jack_out_callback = link.callback(|_: MouseEvent| PackMsg::JackOut);
         let export_json_callback = link.callback(|_: MouseEvent| PackMsg::ExportJson);
         let export_txt_callback = link.callback(|_: MouseEvent| PackMsg::ExportTxt);
         let erase_data_callback = link.callback(|_: MouseEvent| PackMsg::EraseData);
+        let import_txt_callback = link.callback(|_: MouseEvent| PackMsg::ImportTxt);
+        let manage_profiles_callback = link.callback(|_: MouseEvent| PackMsg::ManageProfiles);
+        let custom_chips_callback = link.callback(|_: MouseEvent| PackMsg::ManageCustomChips);
+        let sources_callback = link.callback(|_: MouseEvent| PackMsg::ManageSources);
+        let settings_callback = link.callback(|_: MouseEvent| PackMsg::ManageSettings);
         let import_data_callback = link.callback(|_: MouseEvent| PackMsg::ImportJson);
         let open_context_menu_callback = link.callback(open_ctx_menu);
         let chip_mouseover = link.callback(handle_mouseover_event);
@@ -163,15 +184,26 @@ impl Component for PackComponent {
         Self {
             props,
             _link: link,
-            sort_by: ChipSortOptions::Name,
+            sort: SortSpec::default(),
             event_bus,
             move_to_folder_callback,
             sort_changed,
+            header_clicked,
+            search: ChipSearch::default(),
+            search_changed,
+            facets: FacetFilter::default(),
+            facet_toggled,
+            facets_cleared,
             chip_mouseover,
             set_desc_bus,
             export_json_callback,
             export_txt_callback,
             erase_data_callback,
+            import_txt_callback,
+            manage_profiles_callback,
+            custom_chips_callback,
+            sources_callback,
+            settings_callback,
             import_data_callback,
             jack_out_callback,
             context_menu: None,
@@ -182,12 +214,13 @@ impl Component for PackComponent {
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            PackMsg::ChangeSort(sort_opt) => {
-                if self.sort_by != sort_opt {
-                    self.sort_by = sort_opt;
-                    return true;
-                }
-                false
+            PackMsg::ChangeSort(key) => {
+                self.sort.sort_by(key);
+                true
+            },
+            PackMsg::AddSortKey(key) => {
+                self.sort.then_by(key);
+                true
             },
             PackMsg::JackOut => {
                 let count = ChipLibrary::get_instance().jack_out();
@@ -200,11 +233,17 @@ impl Component for PackComponent {
                 false
             }
             PackMsg::ExportJson => {
-                ChipLibrary::get_instance().export_json();
+                match ChipLibrary::get_instance().export_json() {
+                    Ok(json) => save_json(json),
+                    Err(msg) => unsafe{alert(msg)},
+                }
                 false
             },
             PackMsg::ExportTxt => {
-                ChipLibrary::get_instance().export_txt();
+                match ChipLibrary::get_instance().export_txt() {
+                    Ok(text) => save_txt(text),
+                    Err(msg) => unsafe{alert(msg)},
+                }
                 false
             },
             PackMsg::EraseData => {
@@ -215,6 +254,36 @@ impl Component for PackComponent {
                 self.event_bus.send(GlobalMsgReq::ImportData);
                 false
             },
+            PackMsg::ImportTxt => {
+                self.event_bus.send(GlobalMsgReq::ImportText);
+                false
+            },
+            PackMsg::ManageProfiles => {
+                self.event_bus.send(GlobalMsgReq::ManageProfiles);
+                false
+            },
+            PackMsg::ManageCustomChips => {
+                self.event_bus.send(GlobalMsgReq::ManageCustomChips);
+                false
+            },
+            PackMsg::ManageSources => {
+                self.event_bus.send(GlobalMsgReq::ManageSources);
+                false
+            },
+            PackMsg::ManageSettings => {
+                self.event_bus.send(GlobalMsgReq::ManageSettings);
+                false
+            },
+            PackMsg::ChangeSearch(text) => self.search.set_text(text),
+            PackMsg::ToggleFacet(facet) => {
+                self.facets.toggle(facet);
+                true
+            }
+            PackMsg::ClearFacets => {
+                let had_facets = !self.facets.is_empty();
+                self.facets.clear();
+                had_facets
+            }
             PackMsg::DoNothing => false,
             PackMsg::MoveToFolder(name) => self.move_chip_to_folder(&name),
             PackMsg::RemoveFromPack(name) => self.remove_from_pack(&name),
@@ -242,6 +311,9 @@ impl Component for PackComponent {
             self.props = props;
             self.set_desc_bus.send(ChipDescMsg::ClearDesc);
             return true;
+        } else if props.revision != self.props.revision {
+            self.props = props;
+            return true;
         } else {
             return false;
         }
@@ -258,7 +330,9 @@ impl Component for PackComponent {
         html!{
             <>
             <div class=col1_display>
-                <ChipSortBox include_owned={true} sort_by={self.sort_by} sort_changed={self.sort_changed.clone()}/>
+                <ChipSortBox keys={SortKey::PACK} sort={self.sort.clone()} sort_changed={self.sort_changed.clone()}/>
+                {self.search.view(&self.search_changed)}
+                {self.build_facet_panel()}
                 <br/>
                 <br/>
                 {self.generate_buttons()}
@@ -267,7 +341,7 @@ impl Component for PackComponent {
             </div>
             <div class=col2_display>
                 <div class=pack_containter_class oncontextmenu=self.open_context_menu_callback.clone()>
-                    <PackTopRow />
+                    <PackTopRow sort={self.sort.clone()} sort_clicked={Some(self.header_clicked.clone())}/>
                     {self.build_pack_chips()}
                 </div>
             </div>
@@ -279,6 +353,12 @@ impl Component for PackComponent {
 
 impl PackComponent {
 
+    fn build_facet_panel(&self) -> Html {
+        let pack = unsafe{ChipLibrary::get_instance().pack.try_borrow().unchecked_unwrap()};
+        let searched = pack.values().filter(|chip| self.search.matches(&chip.chip)).map(|chip| &*chip.chip).collect::<Vec<&BattleChip>>();
+        facet_panel(&self.facets, &searched, &self.facet_toggled, &self.facets_cleared)
+    }
+
     fn build_pack_chips(&self) -> Html {
         let lib = ChipLibrary::get_instance();
         let pack = unsafe{lib.pack.try_borrow().unchecked_unwrap()};
@@ -291,6 +371,13 @@ impl PackComponent {
         }
 
         let mut pack_list = self.fetch_and_sort_pack(&pack);
+        if pack_list.is_empty() {
+            return html!{
+                <span class="noselect Chip">
+                {"Nothing matched your search"}
+                </span>
+            }
+        }
         pack_list.drain(..).map(|chip| {
             html!{
                     <PackChipComponent used={chip.used} owned={chip.owned} chip={chip.chip.clone()} add_to_folder={self.move_to_folder_callback.clone()} on_mouse_enter={self.chip_mouseover.clone()}/>
@@ -300,44 +387,19 @@ impl PackComponent {
     }
 
     fn fetch_and_sort_pack<'a>(&self, pack: &'a HashMap<String, PackChip>) -> Vec<&'a PackChip> {
-        let mut pack_list = pack.values().collect::<Vec<&PackChip>>();
-        match self.sort_by {
-            ChipSortOptions::Name => {
-                pack_list.sort_unstable_by(|a, b| {
-                    a.chip.class.cmp(&b.chip.class).then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::Element => {
-                pack_list.sort_unstable_by(|a, b| {
-                    a.chip.element.cmp(&b.chip.element).then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::MaxDamage => {
-                pack_list.sort_unstable_by(|a, b| {
-                    unsafe{a.chip.max_dmg().partial_cmp(&b.chip.max_dmg()).unchecked_unwrap()}.reverse().then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::AverageDamage => {
-                pack_list.sort_unstable_by(|a, b| {
-                    unsafe{a.chip.avg_dmg().partial_cmp(&b.chip.avg_dmg()).unchecked_unwrap()}.reverse().then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::Skill => {
-                pack_list.sort_unstable_by(|a, b| {
-                    a.chip.skill().cmp(&b.chip.skill()).then_with(||a.chip.name.cmp(&b.chip.name))
-                });
-            }
-            ChipSortOptions::Range => {
-                pack_list.sort_unstable_by(|a, b| {
-                    a.chip.range.cmp(&b.chip.range).then_with(||a.chip.name.cmp(&b.chip.name))
-                });
+        let mut pack_list = pack.values().filter(|chip| {
+            self.search.matches(&chip.chip) && self.facets.matches(&chip.chip)
+        }).collect::<Vec<&PackChip>>();
+        let in_folder = ChipLibrary::get_instance().folder_counts();
+        self.sort.sort(&mut pack_list, |chip| {
+            SortRow{
+                owned: chip.owned,
+                used: chip.used,
+                in_folder: in_folder.get(&chip.chip.name).copied().unwrap_or(0),
+                relevance: self.search.relevance(&chip.chip),
+                ..SortRow::new(&chip.chip)
             }
-            ChipSortOptions::Owned => {
-                pack_list.sort_unstable_by(|a,b| {
-                    a.owned.cmp(&b.owned).reverse().then_with(|| a.chip.name.cmp(&b.chip.name))
-                });
-            }
-        }
+        });
         pack_list
     }
 
@@ -364,6 +426,26 @@ impl PackComponent {
                 <button class="sideButtons ripple" onclick=self.import_data_callback.clone()>
                     <span class="Chip">{"Import Data"}</span>
                 </button>
+                <br/>
+                <button class="sideButtons ripple" onclick=self.import_txt_callback.clone()>
+                    <span class="Chip">{"Import Txt"}</span>
+                </button>
+                <br/>
+                <button class="sideButtons ripple" onclick=self.manage_profiles_callback.clone()>
+                    <span class="Chip">{"Profiles"}</span>
+                </button>
+                <br/>
+                <button class="sideButtons ripple" onclick=self.custom_chips_callback.clone()>
+                    <span class="Chip">{"Custom Chips"}</span>
+                </button>
+                <br/>
+                <button class="sideButtons ripple" onclick=self.sources_callback.clone()>
+                    <span class="Chip">{"Chip Sources"}</span>
+                </button>
+                <br/>
+                <button class="sideButtons ripple" onclick=self.settings_callback.clone()>
+                    <span class="Chip">{"Settings"}</span>
+                </button>
             </div>
         }
 
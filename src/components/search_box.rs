@@ -0,0 +1,118 @@
+use yew::prelude::*;
+use yew::html::InputData;
+
+use crate::chip_library::{BattleChip, Query};
+
+/// What's typed into a search box and the last search that could be read
+///
+/// while the text can't be read the previous search keeps applying so the list doesn't jump around
+#[derive(Default)]
+pub(crate) struct ChipSearch {
+    text: String,
+    query: Option<Query>,
+    error: Option<String>,
+}
+
+impl ChipSearch {
+    /// returns true if the text changed
+    pub(crate) fn set_text(&mut self, text: String) -> bool {
+        if self.text == text {
+            return false;
+        }
+        match Query::parse(&text) {
+            Ok(Query::All) => {
+                self.query = None;
+                self.error = None;
+            }
+            Ok(query) => {
+                self.query = Some(query);
+                self.error = None;
+            }
+            Err(why) => {
+                let mut error = why.to_string();
+                if let Some(first) = error.get_mut(0..1) {
+                    first.make_ascii_uppercase();
+                }
+                self.error = Some(error);
+            }
+        }
+        self.text = text;
+        true
+    }
+
+    pub(crate) fn matches(&self, chip: &BattleChip) -> bool {
+        self.query.as_ref().map_or(true, |query| query.matches(chip))
+    }
+
+    /// how closely a name search matches the chip for sorting by relevance, lower is closer
+    ///
+    /// every chip scores the same when the search isn't for a name
+    pub(crate) fn relevance(&self, chip: &BattleChip) -> u32 {
+        self.query.as_ref().and_then(|query| query.name_score(chip)).unwrap_or(0)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.query.is_none()
+    }
+
+    pub(crate) fn view(&self, text_changed: &Callback<InputData>) -> Html {
+        html!{
+            <ChipSearchBox text={self.text.clone()} error={self.error.clone()} text_changed={text_changed.clone()}/>
+        }
+    }
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub(crate) struct ChipSearchBoxProps {
+    pub text: String,
+    pub error: Option<String>,
+    pub text_changed: Callback<InputData>,
+}
+
+pub(crate) struct ChipSearchBox {
+    props: ChipSearchBoxProps,
+}
+
+impl Component for ChipSearchBox {
+    type Properties = ChipSearchBoxProps;
+    type Message = ();
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+        }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+            return true;
+        }
+        false
+    }
+
+    fn view(&self) -> Html {
+        let error = match &self.props.error {
+            Some(error) => html!{<div class="searchError">{error}</div>},
+            None => html!{},
+        };
+        html!{
+            <>
+            <br/>
+            <span unselectable="on" class="Chip noselect">{"Search"}</span>
+            <input
+                type="text" class="chip-search-input"
+                placeholder="elem:fire dmg>=10 \"knock back\""
+                title="Search by name, or by elem, class, skill, range, type, dmg and avg, e.g. elem:aqua range:close. Quoted text searches descriptions, combine with AND, OR, NOT and brackets"
+                value={self.props.text.clone()}
+                oninput=self.props.text_changed.clone()
+            />
+            {error}
+            </>
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use yew::prelude::*;
+use yew::html::InputData;
+
+use group_protocol::Encoding;
+
+use crate::chip_library::{ChipLibrary, ConfigOverrides};
+use crate::util::alert;
+
+#[derive(Properties, Clone, PartialEq)]
+pub(crate) struct SettingsPanelProps {
+    /// the settings were saved
+    pub saved: Callback<String>,
+    pub closed: Callback<MouseEvent>,
+}
+
+pub(crate) enum SettingsMsg {
+    SetGroupServer(String),
+    SetLibraryUrl(String),
+    SetUpdateDelay(String),
+    ToggleJsonProtocol,
+    Save,
+    Reset,
+}
+
+/// Edits the active profile's own settings, anything left blank uses the default
+pub(crate) struct SettingsPanel {
+    props: SettingsPanelProps,
+    link: ComponentLink<Self>,
+    group_server: String,
+    library_url: String,
+    update_delay: String,
+    /// send the group protocol as JSON so it can be read along with
+    json_protocol: bool,
+}
+
+/// the text to show for a setting, blank if the profile doesn't set it
+fn setting_text<T: ToString>(setting: &Option<T>) -> String {
+    setting.as_ref().map_or_else(String::new, T::to_string)
+}
+
+/// blank text leaves the setting to the default
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {None} else {Some(text.to_owned())}
+}
+
+impl Component for SettingsPanel {
+    type Properties = SettingsPanelProps;
+    type Message = SettingsMsg;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let settings = ChipLibrary::get_instance().settings();
+        Self {
+            props,
+            link,
+            group_server: setting_text(&settings.group_server),
+            library_url: setting_text(&settings.library_url),
+            update_delay: setting_text(&settings.folder_update_delay),
+            json_protocol: settings.protocol_encoding == Some(Encoding::Json),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            SettingsMsg::SetGroupServer(text) => {
+                self.group_server = text;
+                false
+            }
+            SettingsMsg::SetLibraryUrl(text) => {
+                self.library_url = text;
+                false
+            }
+            SettingsMsg::SetUpdateDelay(text) => {
+                self.update_delay = text;
+                false
+            }
+            SettingsMsg::ToggleJsonProtocol => {
+                self.json_protocol = !self.json_protocol;
+                true
+            }
+            SettingsMsg::Save => self.save(),
+            SettingsMsg::Reset => {
+                self.group_server.clear();
+                self.library_url.clear();
+                self.update_delay.clear();
+                self.json_protocol = false;
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props != props {
+            self.props = props;
+        }
+        false
+    }
+
+    fn view(&self) -> Html {
+        let library = ChipLibrary::get_instance();
+        let defaults = library.runtime_config.defaults();
+        let save_callback = self.link.callback(|_: MouseEvent| SettingsMsg::Save);
+        let reset_callback = self.link.callback(|_: MouseEvent| SettingsMsg::Reset);
+        let title = String::from("Settings for ") + &library.active_profile();
+        html!{
+            <div class="yew-modal">
+                <div class="yew-modal-content">
+                    <div class="yew-modal-header">
+                        <h2>{title}</h2>
+                    </div>
+                    <div class="yew-modal-body">
+                        <div class="customChipForm">
+                            <div class="customChipField">
+                                <span class="Chip noselect">{"Group Server"}</span>
+                                <input type="text" placeholder=defaults.group_server value=self.group_server.clone() oninput=self.link.callback(|e: InputData| SettingsMsg::SetGroupServer(e.value))/>
+                            </div>
+                            <div class="customChipField">
+                                <span class="Chip noselect">{"Library URL"}</span>
+                                <input type="text" placeholder=defaults.library_url value=self.library_url.clone() oninput=self.link.callback(|e: InputData| SettingsMsg::SetLibraryUrl(e.value))/>
+                            </div>
+                            <div class="customChipField">
+                                <span class="Chip noselect">{"Folder Update Delay (ms)"}</span>
+                                <input type="text" placeholder=defaults.protocol.folder_update_delay.to_string() value=self.update_delay.clone() oninput=self.link.callback(|e: InputData| SettingsMsg::SetUpdateDelay(e.value))/>
+                            </div>
+                            <div class="customChipField">
+                                <span class="Chip noselect">{"Send Group Messages as JSON"}</span>
+                                <input type="checkbox" checked=self.json_protocol onclick=self.link.callback(|_: MouseEvent| SettingsMsg::ToggleJsonProtocol)/>
+                            </div>
+                        </div>
+                        {"Leave a setting blank to use the default. The group server and message encoding are used the next time you join a group, and the library URL the next time the page loads. JSON messages are bigger but can be read in the browser's network tools."}
+                        {self.query_notice()}
+                    </div>
+                    <div class="yew-modal-footer">
+                        <span style="padding-left: 5px">
+                            <button class="ok-button" onclick=save_callback>{"Save"}</button>
+                            <button class="inactiveNavTab" onclick=reset_callback>{"Reset"}</button>
+                        </span>
+                        <span style="float: right">
+                            <button class="inactiveNavTab" onclick=self.props.closed.clone()>{"Close"}</button>
+                        </span>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+}
+
+impl SettingsPanel {
+
+    fn save(&mut self) -> bool {
+        let folder_update_delay = match non_empty(&self.update_delay) {
+            Some(text) => match text.parse::<u32>() {
+                Ok(delay) => Some(delay),
+                Err(_) => {
+                    unsafe{alert("The folder update delay needs to be a whole number of milliseconds")};
+                    return false;
+                }
+            },
+            None => None,
+        };
+        let settings = ConfigOverrides {
+            group_server: non_empty(&self.group_server),
+            library_url: non_empty(&self.library_url),
+            folder_update_delay,
+            protocol_encoding: if self.json_protocol {Some(Encoding::Json)} else {None},
+        };
+        if let Err(why) = ChipLibrary::get_instance().save_settings(&settings) {
+            unsafe{alert(why)};
+            return false;
+        }
+        self.props.saved.emit(String::from("Settings saved"));
+        false
+    }
+
+    /// settings in the page's address beat the profile's, so say which ones are
+    fn query_notice(&self) -> Html {
+        let query = &ChipLibrary::get_instance().runtime_config.query;
+        let mut overridden = Vec::new();
+        if query.group_server.is_some() {
+            overridden.push("Group Server");
+        }
+        if query.library_url.is_some() {
+            overridden.push("Library URL");
+        }
+        if query.folder_update_delay.is_some() {
+            overridden.push("Folder Update Delay");
+        }
+        if query.protocol_encoding.is_some() {
+            overridden.push("Group Message Encoding");
+        }
+        if overridden.is_empty() {
+            return html!{};
+        }
+        html!{
+            <div class="searchError">
+                {"The page's address is overriding "}{overridden.join(", ")}
+            </div>
+        }
+    }
+}
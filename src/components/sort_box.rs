@@ -1,11 +1,40 @@
 use yew::prelude::*;
 
-use crate::components::ChipSortOptions;
+use crate::chip_library::{SortKey, SortSpec};
+
+/// a column header that sorts by its key when clicked, shift clicking adds it as a tiebreak instead
+///
+/// headers without a callback (e.g. the group folders) are just text
+pub(crate) fn sort_header(text: &'static str, class: &'static str, key: SortKey, sort: &SortSpec, clicked: &Option<Callback<(SortKey, bool)>>) -> Html {
+    let clicked = match clicked {
+        Some(clicked) => clicked,
+        None => {
+            return html!{
+                <div class=class>{text}</div>
+            }
+        }
+    };
+    let mut label = String::from(text);
+    if let Some((idx, direction)) = sort.position(key) {
+        label.push(' ');
+        label.push_str(direction.arrow());
+        // only number the tiebreaks
+        if idx > 0 {
+            label.push_str(&(idx + 1).to_string());
+        }
+    }
+    let class = String::from(class) + " sortHeader";
+    let onclick = clicked.reform(move |e: MouseEvent| (key, e.shift_key()));
+    html!{
+        <div class=class onclick=onclick title="Click to sort by this, shift click to break ties with it">{label}</div>
+    }
+}
 
 #[derive(Properties, Clone, PartialEq)]
 pub(crate) struct ChipSortBoxProps {
-    pub sort_by: ChipSortOptions,
-    pub include_owned: bool,
+    pub sort: SortSpec,
+    /// what this list can be sorted by
+    pub keys: &'static [SortKey],
     pub sort_changed: Callback<ChangeData>,
 }
 
@@ -36,27 +65,20 @@ impl Component for ChipSortBox {
     }
 
     fn view(&self) -> Html {
+        let primary = self.props.sort.primary();
+        let options = self.props.keys.iter().map(|key| {
+            html!{
+                <option value=key.as_str() selected={*key == primary}>{key.label()}</option>
+            }
+        }).collect::<Html>();
         html!{
             <>
             <span unselectable="on" class="Chip noselect">{"Sort By"}</span>
-            <select value={self.props.sort_by.as_str()} class="chip-sort-select" onchange=self.props.sort_changed.clone()>
-                <option value="Name">{"Name"}</option>
-                <option value="Element">{"Element"}</option>
-                <option value="MaxDamage">{"MaxDamage"}</option>
-                <option value="AverageDamage">{"AverageDamage"}</option>
-                <option value="Skill">{"Skill"}</option>
-                <option value="Range">{"Range"}</option>
-                {
-                    if self.props.include_owned {
-                        html!{
-                            <option value="Owned">{"Owned"}</option>
-                        }
-                    } else {
-                        html!{}
-                    }
-                }
+            <select value={primary.as_str()} class="chip-sort-select" onchange=self.props.sort_changed.clone()>
+                {options}
             </select>
+            <div class="sortOrder noselect">{self.props.sort.describe()}</div>
             </>
         }
     }
-}
\ No newline at end of file
+}
@@ -11,7 +11,7 @@ mod agents;
 
 use wasm_bindgen::prelude::*;
 
-use chip_library::ChipLibrary;
+use chip_library::{CachedLibrary, ChipLibrary, ChipSource, ConfigOverrides, RuntimeConfig};
 use app::App;
 
 // Use `wee_alloc` as the global allocator.
@@ -20,9 +20,15 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 /// The entrypoint for the webapp
 ///
-/// data is assumed to be the chips.json file's text
+/// data is assumed to be the chips.json file's text, or undefined if it couldn't be downloaded
+/// (or hasn't changed) so the saved copy gets used, etag is the one sent with it
+///
+/// sources is a JSON list of the other chip sources, like `[{"Namespace": "Expansion", "Data": "[...]"}]`,
+/// in the order they're laid over chips.json
+///
+/// config is JSON settings to use in place of the defaults, like `{"GroupServer": "ws://localhost:8080/join"}`
 #[wasm_bindgen]
-pub fn run(data: &str) -> Result<(), JsValue> {
+pub fn run(data: Option<String>, etag: Option<String>, sources: Option<String>, config: Option<String>) -> Result<(), JsValue> {
     
     // Use a higher log level on debug
     #[cfg(debug_assertions)]
@@ -32,8 +38,10 @@ pub fn run(data: &str) -> Result<(), JsValue> {
     #[cfg(not(debug_assertions))]
     wasm_logger::init(wasm_logger::Config::new(log::Level::Error));
 
+    let sources = sources.and_then(|text| serde_json::from_str::<Vec<ChipSource>>(&text).ok()).unwrap_or_default();
+
     // deserialize the chip library before starting
-    if let Err(why) = ChipLibrary::init(data) {
+    if let Err(why) = ChipLibrary::init(data.as_deref(), etag.as_deref(), &sources, runtime_config(config), util::local_storage(), Box::new(util::WindowAlert), Box::new(util::BrowserClock)) {
         return Err(wasm_bindgen::JsValue::from_str(&why));
     }
     
@@ -41,6 +49,30 @@ pub fn run(data: &str) -> Result<(), JsValue> {
     Ok(())
 }
 
+/// the settings run was given, and any the page's address sets
+fn runtime_config(config: Option<String>) -> RuntimeConfig {
+    RuntimeConfig {
+        base: config.and_then(|text| ConfigOverrides::from_json(&text)).unwrap_or_default(),
+        query: ConfigOverrides::from_query(&util::query_string()),
+    }
+}
+
+/// Where the active profile downloads chips.json from, config is the same as what's given to run
+#[wasm_bindgen]
+pub fn library_url(config: Option<String>) -> String {
+    let runtime = runtime_config(config);
+    match util::local_storage() {
+        Some(storage) => ChipLibrary::startup_config(&*storage, &runtime).library_url,
+        None => runtime.resolve(&ConfigOverrides::default()).library_url,
+    }
+}
+
+/// The ETag of the saved copy of chips.json, so the download can be skipped if it hasn't changed
+#[wasm_bindgen]
+pub fn cached_library_etag() -> Option<String> {
+    util::local_storage().and_then(|storage| CachedLibrary::etag(&*storage))
+}
+
 #[wasm_bindgen]
 pub fn save_before_exit() -> Result<(), JsValue> {
     ChipLibrary::get_instance().save_data().map_err(|s| wasm_bindgen::JsValue::from_str(s))
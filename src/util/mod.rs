@@ -1,6 +1,6 @@
 use yew::prelude::*;
 use wasm_bindgen::prelude::*;
-use crate::chip_library::Elements;
+use crate::chip_library::{Clock, Elements, DataStore, Notifier};
 use crate::ChipLibrary;
 
 use unchecked_unwrap::UncheckedUnwrap;
@@ -71,6 +71,107 @@ pub unsafe fn alert(msg: &str) {
     let _ = window.alert_with_message(msg);
 }
 
+/// ask the user to confirm something, false if they cancel
+pub(crate) fn confirm(msg: &str) -> bool {
+    match web_sys::window() {
+        Some(window) => window.confirm_with_message(msg).unwrap_or(false),
+        None => false,
+    }
+}
+
+/// load the page again, for changes that need the library rebuilt
+pub(crate) fn reload_page() {
+    if let Some(window) = web_sys::window() {
+        let _ = window.location().reload();
+    }
+}
+
+/// the page address's query string, like "?server=..."
+pub(crate) fn query_string() -> String {
+    web_sys::window().and_then(|window| window.location().search().ok()).unwrap_or_default()
+}
+
+/// The browser's localStorage, None if it isn't available
+pub(crate) fn local_storage() -> Option<Box<dyn DataStore>> {
+    let window = web_sys::window()?;
+    let storage = window.local_storage().ok().flatten()?;
+    Some(Box::new(LocalStorage(storage)))
+}
+
+pub(crate) struct LocalStorage(web_sys::Storage);
+
+impl DataStore for LocalStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        //flatten an Option<Option<String>> into Option<String>
+        self.0.get_item(key).ok().flatten()
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<(), &'static str> {
+        self.0.set_item(key, value).map_err(|_| "Failed to write to local storage, it may be full")
+    }
+
+    fn remove_item(&self, key: &str) {
+        let _ = self.0.remove_item(key);
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let len = self.0.length().unwrap_or(0);
+        (0..len).filter_map(|idx| self.0.key(idx).ok().flatten()).collect()
+    }
+}
+
+/// Shows messages using `window.alert` and asks questions with `window.confirm`
+pub(crate) struct WindowAlert;
+
+impl Notifier for WindowAlert {
+    fn alert(&self, msg: &str) {
+        unsafe{alert(msg)};
+    }
+
+    fn confirm(&self, msg: &str) -> bool {
+        confirm(msg)
+    }
+}
+
+/// Timestamps the combat log using `Date.now()`
+pub(crate) struct BrowserClock;
+
+impl Clock for BrowserClock {
+    fn now(&self) -> f64 {
+        js_sys::Date::now()
+    }
+}
+
+/// formats a timestamp from the combat log as local "HH:MM:SS"
+pub(crate) fn format_time(time: f64) -> String {
+    let date = js_sys::Date::new(&JsValue::from_f64(time));
+    let mut text = String::with_capacity(8);
+    for (idx, part) in [date.get_hours(), date.get_minutes(), date.get_seconds()].iter().enumerate() {
+        if idx > 0 {
+            text.push(':');
+        }
+        if *part < 10 {
+            text.push('0');
+        }
+        text.push_str(&part.to_string());
+    }
+    text
+}
+
+/// formats a timestamp as the local date, e.g. "2021-03-14"
+pub(crate) fn format_date(time: f64) -> String {
+    let date = js_sys::Date::new(&JsValue::from_f64(time));
+    let mut text = date.get_full_year().to_string();
+    for part in [date.get_month() + 1, date.get_date()].iter() {
+        text.push('-');
+        if *part < 10 {
+            text.push('0');
+        }
+        text.push_str(&part.to_string());
+    }
+    text
+}
+
 #[wasm_bindgen(module="/static/util.js")]
 extern "C" {
     pub(crate) fn save_json(data: String);
@@ -1,12 +1,60 @@
-import { run, save_before_exit } from '../Cargo.toml'
+import { run, save_before_exit, cached_library_etag, library_url } from '../Cargo.toml'
 
-const URL = "https://spartan364.hopto.org/manager/chips.json";
+// settings to use in place of the defaults can be set on the page before this runs,
+// e.g. window.chipManagerConfig = {"GroupServer": "ws://localhost:8080/join", "LibraryUrl": "/chips.json"}
+const CONFIG: string | undefined = (window as any).chipManagerConfig ? JSON.stringify((window as any).chipManagerConfig) : undefined;
+
+// the other chip sources to lay over chips.json, a list of {"Namespace": "...", "Url": "..."}
+const SOURCES_URL = "https://spartan364.hopto.org/manager/sources.json";
+
+// undefined data means the saved copy of the library gets used
+async function fetch_library(): Promise<[string | undefined, string | undefined]> {
+    let etag = cached_library_etag();
+    let headers: Record<string, string> = {};
+    if (etag) {
+        headers["If-None-Match"] = etag;
+    }
+    try {
+        let body = await fetch(library_url(CONFIG), { headers: headers });
+        if (body.status === 304) {
+            return [undefined, etag];
+        }
+        if (!body.ok) {
+            return [undefined, undefined];
+        }
+        return [await body.text(), body.headers.get("ETag") || undefined];
+    } catch (_) {
+        return [undefined, undefined];
+    }
+}
+
+// every source listed in sources.json, Data is left out for ones that couldn't be downloaded
+async function fetch_sources(): Promise<string> {
+    let sources: { Namespace: string, Data?: string }[] = [];
+    try {
+        let body = await fetch(SOURCES_URL);
+        if (!body.ok) {
+            return "[]";
+        }
+        let listed: { Namespace: string, Url: string }[] = await body.json();
+        for (let source of listed) {
+            let data: string | undefined = undefined;
+            try {
+                let chips = await fetch(source.Url);
+                if (chips.ok) {
+                    data = await chips.text();
+                }
+            } catch (_) { }
+            sources.push({ Namespace: source.Namespace, Data: data });
+        }
+    } catch (_) { }
+    return JSON.stringify(sources);
+}
 
 async function start() {
     
     try {
-        let body = await fetch(URL);
-        let result = await body.text();
+        let [[result, etag], sources] = await Promise.all([fetch_library(), fetch_sources()]);
         window.addEventListener("beforeunload", function (e) {
             let confirmationMessage = 'Progress might be lost if you leave without saving an export.';
 
@@ -14,7 +62,7 @@ async function start() {
             save_before_exit();
             return confirmationMessage; //Gecko + Webkit, Safari, Chrome etc.
         });
-        run(result);
+        run(result, etag, sources, CONFIG);
 
     } catch (_) {
         alert("an error occurred in loading chips, inform Major");